@@ -0,0 +1,494 @@
+//! The secc-agent config file, built from the store in one place for every
+//! platform.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+use anyhow::{Error, anyhow};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::ShellExt;
+
+use crate::{
+    command,
+    group::{self, BalanceMode},
+    policy, protocol,
+    server::{ServerInfo, TransportOptions},
+    shell,
+    state::{AccessMode, AgentConfigState, AgentInfoState, ProtocolMode},
+    store::{self, COMBINED_BLOCK_RULES_PATH, COMBINED_DIRECT_RULES_PATH, PROXY_RULES_PATH},
+};
+
+pub const AGENT_CONFIG_PATH: &str = "agent.json";
+/// Oldest agent that reads `--config`.
+const MIN_AGENT_VERSION: (u64, u64, u64) = (0, 2, 0);
+/// First agent version with a config format this build doesn't know.
+const MAX_AGENT_VERSION: (u64, u64, u64) = (0, 3, 0);
+
+pub const CAPABILITY_LOAD_BALANCE: &str = "load_balance";
+pub const CAPABILITY_POLICY_ROUTES: &str = "policy_routes";
+/// The agent re-reads its config and rule lists on a `reload` stdin line.
+pub const CAPABILITY_RELOAD: &str = "reload";
+
+/// What the bundled agent reported about itself.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AgentInfo {
+    pub version: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub compatible: bool,
+    /// Why the agent can't be used, `None` when it is compatible.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl AgentInfo {
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|x| x == capability)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgentConfig {
+    pub proxy_rules: PathBuf,
+    pub direct_rules: PathBuf,
+    pub block_rules: PathBuf,
+    pub access_mode: AccessMode,
+    /// Never `auto`, the protocol auto mode currently picked.
+    pub protocol: ProtocolMode,
+    /// `None` until a server is selected.
+    pub remote: Option<Upstream>,
+    /// Further servers of a load-balance group.
+    pub upstreams: Vec<Upstream>,
+    pub balance: Option<BalanceMode>,
+    /// Policy routes, tried in order before the proxy rules.
+    pub routes: Vec<Route>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Upstream {
+    /// `host:port` for the config protocol.
+    pub address: String,
+    pub cert: PathBuf,
+    /// Hash of the cert written to `cert`, an edited cert keeps its path but
+    /// still needs a new tunnel. Only compared, the agent reads the file.
+    #[serde(skip)]
+    pub cert_hash: Vec<u8>,
+    pub transport: TransportOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Route {
+    pub rules: PathBuf,
+    pub upstream: Upstream,
+}
+
+impl AgentConfig {
+    /// `dir` is the store directory, `active` the servers behind the active
+    /// target best first, and `routes` the store-relative rule paths of the
+    /// enabled policies with their server. Servers without a port for
+    /// `protocol` are left out.
+    pub fn build(
+        dir: &Path,
+        access_mode: AccessMode,
+        protocol: ProtocolMode,
+        active: &[ServerInfo],
+        balance: Option<BalanceMode>,
+        routes: &[(String, ServerInfo)],
+    ) -> Self {
+        let upstream = |server: &ServerInfo| {
+            server.address(&protocol).map(|address| Upstream {
+                address,
+                cert: dir.join(store::server_cert_path(&server.host)),
+                cert_hash: Sha256::digest(server.cert.as_bytes()).to_vec(),
+                transport: server.transport.clone(),
+            })
+        };
+        let mut active = active.iter().filter_map(upstream);
+        let remote = active.next();
+        let upstreams: Vec<Upstream> = active.collect();
+        let routes = routes
+            .iter()
+            .filter_map(|(rules_path, server)| {
+                upstream(server).map(|upstream| Route {
+                    rules: dir.join(rules_path),
+                    upstream,
+                })
+            })
+            .collect();
+        Self {
+            proxy_rules: dir.join(PROXY_RULES_PATH),
+            direct_rules: dir.join(COMBINED_DIRECT_RULES_PATH),
+            block_rules: dir.join(COMBINED_BLOCK_RULES_PATH),
+            access_mode,
+            protocol,
+            remote,
+            balance: balance.filter(|_| !upstreams.is_empty()),
+            upstreams,
+            routes,
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Whether going from `running` to this config needs a new agent process.
+    /// Rule lists, access mode and policy routes are reloadable; the tunnel
+    /// itself isn't.
+    pub fn needs_restart(&self, running: &AgentConfig) -> bool {
+        self.protocol != running.protocol
+            || self.remote != running.remote
+            || self.upstreams != running.upstreams
+            || self.balance != running.balance
+    }
+}
+
+/// The config the agent would be started with right now, without the
+/// features the agent doesn't report.
+pub fn build_config(app: &AppHandle) -> Result<AgentConfig, Error> {
+    let config_path = store::get_config_path(app, AGENT_CONFIG_PATH)?;
+    let dir = config_path.parent().unwrap_or(Path::new(""));
+    let mut config = AgentConfig::build(
+        dir,
+        command::get_access_mode(app.clone()),
+        protocol::effective_protocol(app),
+        &group::active_servers(app),
+        group::active_balance(app),
+        &policy::routes(app),
+    );
+    let info = agent_info(app);
+    if !info.has_capability(CAPABILITY_LOAD_BALANCE) && !config.upstreams.is_empty() {
+        println!(
+            "agent {} can't load balance, using one server",
+            info.version
+        );
+        config.upstreams.clear();
+        config.balance = None;
+    }
+    if !info.has_capability(CAPABILITY_POLICY_ROUTES) && !config.routes.is_empty() {
+        println!(
+            "agent {} has no policy routes, ignoring policies",
+            info.version
+        );
+        config.routes.clear();
+    }
+    Ok(config)
+}
+
+/// The bundled agent's version and capabilities. An answer is cached, a
+/// failed query isn't and is tried again on the next call. The state isn't
+/// locked while the agent runs.
+pub fn agent_info(app: &AppHandle) -> AgentInfo {
+    {
+        let info_state = app.state::<Mutex<AgentInfoState>>();
+        let info_state = info_state.lock().unwrap();
+        if let Some(info) = info_state.get() {
+            return info.clone();
+        }
+    }
+    let info = match query_agent(app) {
+        Ok(mut info) => {
+            info.message = check_version(&info.version).err();
+            info.compatible = info.message.is_none();
+            let info_state = app.state::<Mutex<AgentInfoState>>();
+            let mut info_state = info_state.lock().unwrap();
+            info_state.set(Some(info.clone()));
+            info
+        }
+        Err(e) => AgentInfo {
+            message: Some(format!("secc-agent could not be queried: {}", e)),
+            ..Default::default()
+        },
+    };
+    if let Some(message) = &info.message {
+        eprintln!("{}", message);
+    }
+    info
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Diagnostics {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub agent: AgentInfo,
+    pub agent_running: bool,
+    pub config_path: Option<PathBuf>,
+    /// The config the running agent was started with.
+    pub config: Option<AgentConfig>,
+}
+
+pub fn diagnostics(app: &AppHandle) -> Diagnostics {
+    let config = {
+        let config_state = app.state::<Mutex<AgentConfigState>>();
+        let config_state = config_state.lock().unwrap();
+        config_state.get().cloned()
+    };
+    Diagnostics {
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        agent: agent_info(app),
+        agent_running: shell::is_sidecar_running(app),
+        config_path: store::get_config_path(app, AGENT_CONFIG_PATH).ok(),
+        config,
+    }
+}
+
+fn query_agent(app: &AppHandle) -> Result<AgentInfo, Error> {
+    parse_agent_info(run_agent(app, "--capabilities"), || {
+        run_agent(app, "--version")
+    })
+}
+
+// agents with a capability query print it as json, older ones only know
+// `--version` and may reject the unknown flag with a failing exit
+fn parse_agent_info(
+    capabilities: Result<String, Error>,
+    version: impl FnOnce() -> Result<String, Error>,
+) -> Result<AgentInfo, Error> {
+    if let Ok(output) = capabilities
+        && let Ok(info) = serde_json::from_str::<AgentInfo>(output.trim())
+    {
+        return Ok(info);
+    }
+    let output = version()?;
+    let version = output.split_whitespace().last().unwrap_or_default();
+    Ok(AgentInfo {
+        version: version.trim_start_matches('v').to_string(),
+        ..Default::default()
+    })
+}
+
+fn run_agent(app: &AppHandle, arg: &str) -> Result<String, Error> {
+    let mut command: Command = app.shell().sidecar("secc-agent")?.arg(arg).into();
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`secc-agent {}` exited with {}",
+            arg,
+            output.status
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|x| x.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+fn check_version(version: &str) -> Result<(), String> {
+    let format = |(major, minor, patch): (u64, u64, u64)| format!("{}.{}.{}", major, minor, patch);
+    let supported = format!(
+        "at least {} and below {}",
+        format(MIN_AGENT_VERSION),
+        format(MAX_AGENT_VERSION)
+    );
+    match parse_version(version) {
+        Some(parsed) if (MIN_AGENT_VERSION..MAX_AGENT_VERSION).contains(&parsed) => Ok(()),
+        Some(_) => Err(format!(
+            "secc-agent {} is not supported, this secc needs a version {}",
+            version, supported
+        )),
+        None => Err(format!(
+            "secc-agent reported an unreadable version `{}`, this secc needs a version {}",
+            version, supported
+        )),
+    }
+}
+
+/// Write the current config and the server certs it refers to, returning the
+/// config and its path.
+pub fn write_config(app: &AppHandle) -> Result<(AgentConfig, PathBuf), Error> {
+    let config = build_config(app)?;
+    let servers = group::active_servers(app)
+        .into_iter()
+        .chain(policy::routes(app).into_iter().map(|(_, server)| server));
+    for server in servers {
+        store::set_server_cert(app, &server)?;
+    }
+    let config_path = store::get_config_path(app, AGENT_CONFIG_PATH)?;
+    store::write_atomic(&config_path, &serde_json::to_vec_pretty(&config)?)?;
+    Ok((config, config_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(host: &str, tcp_port: Option<u16>) -> ServerInfo {
+        ServerInfo {
+            host: host.to_string(),
+            quic_port: 4433,
+            tcp_port,
+            cert: String::new(),
+            cert_key: String::new(),
+            alias: None,
+            transport: TransportOptions::default(),
+        }
+    }
+
+    fn build(protocol: ProtocolMode, active: &[ServerInfo]) -> AgentConfig {
+        AgentConfig::build(
+            Path::new("/store"),
+            AccessMode::Auto,
+            protocol,
+            active,
+            Some(BalanceMode::RoundRobin),
+            &[],
+        )
+    }
+
+    #[test]
+    fn paths_are_under_the_store_dir() {
+        let config = build(ProtocolMode::Tcp, &[]);
+        assert_eq!(config.proxy_rules, Path::new("/store/proxy_list.txt"));
+        assert_eq!(
+            config.direct_rules,
+            Path::new("/store/combined_direct_list.txt")
+        );
+        assert_eq!(
+            config.block_rules,
+            Path::new("/store/combined_block_list.txt")
+        );
+        assert_eq!(config.remote, None);
+    }
+
+    #[test]
+    fn remote_uses_the_port_of_the_protocol() {
+        let servers = [server("a.example", Some(443))];
+        let quic = build(ProtocolMode::Quic, &servers);
+        assert_eq!(quic.remote.unwrap().address, "a.example:4433");
+        let tcp = build(ProtocolMode::Tcp, &servers);
+        let remote = tcp.remote.unwrap();
+        assert_eq!(remote.address, "a.example:443");
+        assert_eq!(
+            remote.cert,
+            Path::new("/store").join(store::server_cert_path("a.example"))
+        );
+    }
+
+    #[test]
+    fn servers_without_a_tcp_port_are_skipped() {
+        let servers = [server("a.example", None), server("b.example", Some(443))];
+        let config = build(ProtocolMode::Tcp, &servers);
+        assert_eq!(config.remote.unwrap().address, "b.example:443");
+        assert!(config.upstreams.is_empty());
+        assert_eq!(config.balance, None);
+    }
+
+    #[test]
+    fn load_balance_members_become_upstreams() {
+        let servers = [
+            server("a.example", Some(443)),
+            server("b.example", Some(443)),
+        ];
+        let config = build(ProtocolMode::Tcp, &servers);
+        assert_eq!(config.remote.unwrap().address, "a.example:443");
+        assert_eq!(config.upstreams.len(), 1);
+        assert_eq!(config.upstreams[0].address, "b.example:443");
+        assert_eq!(config.balance, Some(BalanceMode::RoundRobin));
+    }
+
+    #[test]
+    fn routes_point_at_the_policy_rules() {
+        let routes = [("policies/work.txt".to_string(), server("w.example", None))];
+        let config = AgentConfig::build(
+            Path::new("/store"),
+            AccessMode::Proxy,
+            ProtocolMode::Quic,
+            &[],
+            None,
+            &routes,
+        );
+        assert_eq!(config.routes.len(), 1);
+        assert_eq!(
+            config.routes[0].rules,
+            Path::new("/store/policies/work.txt")
+        );
+        assert_eq!(config.routes[0].upstream.address, "w.example:4433");
+    }
+
+    #[test]
+    fn changed_settings_compare_unequal() {
+        let servers = [server("a.example", Some(443))];
+        let config = build(ProtocolMode::Tcp, &servers);
+        assert_eq!(config, build(ProtocolMode::Tcp, &servers));
+        assert_ne!(config, build(ProtocolMode::Quic, &servers));
+        let mut tuned = servers.clone();
+        tuned[0].transport.tcp_pool_size = 8;
+        assert_ne!(config, build(ProtocolMode::Tcp, &tuned));
+    }
+
+    #[test]
+    fn only_tunnel_changes_need_a_restart() {
+        let servers = [server("a.example", Some(443))];
+        let config = build(ProtocolMode::Tcp, &servers);
+        let mut proxy = config.clone();
+        proxy.access_mode = AccessMode::Proxy;
+        assert!(!proxy.needs_restart(&config));
+        assert!(build(ProtocolMode::Quic, &servers).needs_restart(&config));
+        let other = [server("b.example", Some(443))];
+        assert!(build(ProtocolMode::Tcp, &other).needs_restart(&config));
+        let mut recerted = servers.clone();
+        recerted[0].cert = "new cert".to_string();
+        assert!(build(ProtocolMode::Tcp, &recerted).needs_restart(&config));
+    }
+
+    #[test]
+    fn versions_are_parsed_leniently() {
+        assert_eq!(parse_version("0.2.1"), Some((0, 2, 1)));
+        assert_eq!(parse_version("0.2"), Some((0, 2, 0)));
+        assert_eq!(parse_version("0.2.1-beta.1"), Some((0, 2, 1)));
+        assert_eq!(parse_version("secc"), None);
+    }
+
+    #[test]
+    fn old_agents_fall_back_to_the_version() {
+        let version = || Ok("secc-agent v0.2.1\n".to_string());
+        let failed = parse_agent_info(Err(anyhow!("exited with 2")), version).unwrap();
+        assert_eq!(failed.version, "0.2.1");
+        assert!(failed.capabilities.is_empty());
+        let usage = parse_agent_info(Ok("usage: secc-agent".to_string()), version).unwrap();
+        assert_eq!(usage.version, "0.2.1");
+        let json = r#"{"version": "0.3.0", "capabilities": ["routes"]}"#;
+        let info = parse_agent_info(Ok(json.to_string()), || unreachable!()).unwrap();
+        assert_eq!(info.version, "0.3.0");
+        assert_eq!(info.capabilities, ["routes"]);
+        assert!(parse_agent_info(Err(anyhow!("missing")), || Err(anyhow!("missing"))).is_err());
+    }
+
+    #[test]
+    fn only_the_supported_range_is_compatible() {
+        assert!(check_version("0.2.0").is_ok());
+        assert!(check_version("0.2.9").is_ok());
+        assert!(check_version("0.1.5").is_err());
+        assert!(check_version("0.3.0").is_err());
+        assert!(check_version("").is_err());
+    }
+
+    #[test]
+    fn serializes_as_json() {
+        let servers = [server("a.example", Some(443))];
+        let config = build(ProtocolMode::Tcp, &servers);
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["access_mode"], "auto");
+        assert_eq!(json["protocol"], "tcp");
+        assert_eq!(json["remote"]["transport"]["congestion_control"], "bbr");
+        // the cert hash is kept in memory only
+        assert!(json["remote"].get("cert_hash").is_none());
+        let mut parsed: AgentConfig = serde_json::from_value(json).unwrap();
+        let remote = parsed.remote.as_mut().unwrap();
+        remote.cert_hash = config.remote.as_ref().unwrap().cert_hash.clone();
+        assert_eq!(parsed, config);
+    }
+}
@@ -0,0 +1,767 @@
+//! Export and import of the whole configuration as a single json bundle, to
+//! move a setup to another machine.
+//!
+//! Private keys are left out unless asked for. Included keys are encrypted
+//! with chacha20-poly1305 under a key derived from a passphrase with argon2id.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Error, anyhow};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::{
+    ChaCha20Poly1305, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng, rand_core::RngCore},
+};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{
+    group::{self, ServerGroup},
+    history::HistorySource,
+    policy::{self, Policy},
+    profile::{self, Profile},
+    rules::{
+        import,
+        lint::{self, LintReport, Severity},
+        refresh,
+        source::{self, RuleFormat, RuleSource, RuleTarget},
+    },
+    server::{AddrInfo, ServerInfo},
+    state::{AccessMode, BindMode, ProtocolMode},
+    store,
+};
+
+pub const BUNDLE_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+
+/// The user's own rule lists carried by a bundle.
+const RULE_LISTS: [(&str, &str); 3] = [
+    (store::CUSTOM_PROXY_RULES_PATH, "proxy"),
+    (store::DIRECT_RULES_PATH, "direct"),
+    (store::BLOCK_RULES_PATH, "block"),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub version: u32,
+    /// Unix time in seconds.
+    pub exported_at: u64,
+    /// Servers without their private keys, those only travel in `keys`.
+    pub servers: Vec<ServerInfo>,
+    #[serde(default)]
+    pub server_groups: Vec<ServerGroup>,
+    #[serde(default)]
+    pub active_server: Option<String>,
+    pub socks: AddrInfo,
+    pub http: AddrInfo,
+    pub access_mode: AccessMode,
+    pub bind_mode: BindMode,
+    pub protocol_mode: ProtocolMode,
+    /// The user's own rule lists, keyed by store path.
+    #[serde(default)]
+    pub rules: BTreeMap<String, String>,
+    #[serde(default)]
+    pub rule_sources: Vec<RuleSource>,
+    #[serde(default)]
+    pub policies: Vec<Policy>,
+    /// Rule lists of the policies, keyed by policy name.
+    #[serde(default)]
+    pub policy_rules: BTreeMap<String, String>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// The local TLS cert, its key is part of `keys`.
+    #[serde(default)]
+    pub cert: String,
+    #[serde(default)]
+    pub keys: Option<EncryptedKeys>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedKeys {
+    /// Base64 of the argon2 salt.
+    pub salt: String,
+    /// Base64 of the chacha20-poly1305 nonce.
+    pub nonce: String,
+    /// Base64 of the encrypted `BundleKeys` json.
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct BundleKeys {
+    cert_key: String,
+    /// Private keys of the servers, keyed by host.
+    server_keys: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Keep what the bundle doesn't mention, bundle entries win on conflicts
+    /// and rule lists are appended to.
+    Merge,
+    /// Make the configuration exactly what the bundle holds.
+    Replace,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    Server,
+    Group,
+    Policy,
+    Profile,
+    RuleSource,
+    Setting,
+}
+
+/// An existing entry the import overwrites with a different value.
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportConflict {
+    pub kind: ConflictKind,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct ConfigImportReport {
+    pub conflicts: Vec<ImportConflict>,
+    /// Whether the bundle carries encrypted keys.
+    pub has_keys: bool,
+    pub keys_imported: bool,
+    pub applied: bool,
+}
+
+/// Collect the current configuration. Keys are only included, encrypted,
+/// when a passphrase is given.
+pub fn export(app: &AppHandle, passphrase: Option<&str>) -> Result<ConfigBundle, Error> {
+    let mut bundle = collect(app)?;
+    let mut keys = BundleKeys {
+        cert_key: store::get_cert(app, store::CERT_KEY_PATH).unwrap_or_default(),
+        server_keys: BTreeMap::new(),
+    };
+    for server in bundle.servers.iter_mut() {
+        let cert_key = std::mem::take(&mut server.cert_key);
+        keys.server_keys.insert(server.host.clone(), cert_key);
+    }
+    if let Some(passphrase) = passphrase {
+        if passphrase.is_empty() {
+            return Err(anyhow!("a passphrase is needed to export keys"));
+        }
+        bundle.keys = Some(encrypt_keys(&keys, passphrase)?);
+    }
+    Ok(bundle)
+}
+
+/// Check a bundle against the current configuration and apply it unless
+/// `dry_run`. Nothing is written when the result would be invalid. Keys are
+/// only imported when a passphrase is given.
+pub fn import(
+    app: &AppHandle,
+    bundle: ConfigBundle,
+    mode: ImportMode,
+    passphrase: Option<&str>,
+    dry_run: bool,
+) -> Result<ConfigImportReport, Error> {
+    if bundle.version > BUNDLE_VERSION {
+        return Err(anyhow!(
+            "the bundle was written by a newer secc (version {})",
+            bundle.version
+        ));
+    }
+    let mut report = ConfigImportReport {
+        has_keys: bundle.keys.is_some(),
+        ..Default::default()
+    };
+    let keys = match (&bundle.keys, passphrase) {
+        (Some(keys), Some(passphrase)) => Some(decrypt_keys(keys, passphrase)?),
+        _ => None,
+    };
+    let current = collect(app)?;
+    let merged = merge(current, bundle, mode, &mut report.conflicts)?;
+    validate(&merged)?;
+    if dry_run {
+        return Ok(report);
+    }
+    apply(app, merged, keys.as_ref())?;
+    report.keys_imported = keys.is_some();
+    report.applied = true;
+    Ok(report)
+}
+
+fn collect(app: &AppHandle) -> Result<ConfigBundle, Error> {
+    let mut rules = BTreeMap::new();
+    for (rule_path, _) in RULE_LISTS {
+        rules.insert(rule_path.to_string(), store::get_rules(app, rule_path)?);
+    }
+    let policies = store::get_policies(app)?;
+    let mut policy_rules = BTreeMap::new();
+    for policy in policies.iter() {
+        let content = store::get_rules(app, &policy.rules_path()).unwrap_or_default();
+        policy_rules.insert(policy.name.clone(), content);
+    }
+    let mode = |key: &str| store::get_value_by_key(app, key).ok().flatten();
+    Ok(ConfigBundle {
+        version: BUNDLE_VERSION,
+        exported_at: refresh::now_secs(),
+        servers: store::get_servers(app)?.unwrap_or_default(),
+        server_groups: store::get_server_groups(app)?,
+        active_server: mode(store::ACTIVE_SERVER).filter(|x| !x.is_empty()),
+        socks: store::get_address(app, store::SOCKS_ADDR)?
+            .unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1080)),
+        http: store::get_address(app, store::HTTP_ADDR)?
+            .unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1081)),
+        access_mode: mode(store::ACCESS_MODE)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(AccessMode::Auto),
+        bind_mode: mode(store::BIND_MODE)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(BindMode::Socks),
+        protocol_mode: mode(store::PROTOCOL_MODE)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(ProtocolMode::Tcp),
+        rules,
+        rule_sources: store::get_rule_sources(app)?,
+        policies,
+        policy_rules,
+        profiles: store::get_profiles(app)?,
+        cert: store::get_cert(app, store::CERT_PATH).unwrap_or_default(),
+        keys: None,
+    })
+}
+
+/// Combine the current configuration with a bundle, noting the entries
+/// present on both sides with different values.
+fn merge(
+    current: ConfigBundle,
+    bundle: ConfigBundle,
+    mode: ImportMode,
+    conflicts: &mut Vec<ImportConflict>,
+) -> Result<ConfigBundle, Error> {
+    // exports leave the keys out, a server only differing in its key is the same
+    let without_key = |x: &ServerInfo| {
+        let mut server = x.clone();
+        server.cert_key.clear();
+        serde_json::to_value(server).ok()
+    };
+    let mut servers = merge_entries_by(
+        &current.servers,
+        bundle.servers,
+        mode,
+        |x| x.host.clone(),
+        without_key,
+        ConflictKind::Server,
+        conflicts,
+    );
+    // keys missing from the bundle stay what they were
+    for server in servers.iter_mut().filter(|x| x.cert_key.is_empty()) {
+        if let Some(existing) = current.servers.iter().find(|x| x.host == server.host) {
+            server.cert_key = existing.cert_key.clone();
+        }
+    }
+    let server_groups = merge_entries(
+        &current.server_groups,
+        bundle.server_groups,
+        mode,
+        |x| x.name.clone(),
+        ConflictKind::Group,
+        conflicts,
+    );
+    let policies = merge_entries(
+        &current.policies,
+        bundle.policies,
+        mode,
+        |x| x.name.clone(),
+        ConflictKind::Policy,
+        conflicts,
+    );
+    let profiles = merge_entries(
+        &current.profiles,
+        bundle.profiles,
+        mode,
+        |x| x.name.clone(),
+        ConflictKind::Profile,
+        conflicts,
+    );
+    let rule_sources = merge_entries(
+        &current.rule_sources,
+        bundle.rule_sources,
+        mode,
+        |x| x.id.clone(),
+        ConflictKind::RuleSource,
+        conflicts,
+    );
+
+    let mut setting = |name: &str, current: serde_json::Value, imported: serde_json::Value| {
+        if current != imported {
+            conflicts.push(ImportConflict {
+                kind: ConflictKind::Setting,
+                name: name.to_string(),
+            });
+        }
+    };
+    setting(
+        store::SOCKS_ADDR,
+        serde_json::to_value(&current.socks)?,
+        serde_json::to_value(&bundle.socks)?,
+    );
+    setting(
+        store::HTTP_ADDR,
+        serde_json::to_value(&current.http)?,
+        serde_json::to_value(&bundle.http)?,
+    );
+    setting(
+        store::ACCESS_MODE,
+        serde_json::to_value(current.access_mode)?,
+        serde_json::to_value(bundle.access_mode)?,
+    );
+    setting(
+        store::BIND_MODE,
+        serde_json::to_value(current.bind_mode)?,
+        serde_json::to_value(bundle.bind_mode)?,
+    );
+    setting(
+        store::PROTOCOL_MODE,
+        serde_json::to_value(current.protocol_mode)?,
+        serde_json::to_value(bundle.protocol_mode)?,
+    );
+    let active_server = match mode {
+        ImportMode::Merge => bundle.active_server.or(current.active_server),
+        ImportMode::Replace => bundle.active_server,
+    };
+    let cert = match (mode, bundle.cert.is_empty()) {
+        (ImportMode::Merge, true) => current.cert,
+        _ => bundle.cert,
+    };
+
+    let (rules, policy_rules) = match mode {
+        ImportMode::Merge => {
+            let mut rules = current.rules;
+            for (rule_path, content) in bundle.rules {
+                let existing = rules.remove(&rule_path).unwrap_or_default();
+                rules.insert(rule_path, append_rules(&existing, &content)?);
+            }
+            let mut policy_rules = current.policy_rules;
+            for (name, content) in bundle.policy_rules {
+                let existing = policy_rules.remove(&name).unwrap_or_default();
+                policy_rules.insert(name, append_rules(&existing, &content)?);
+            }
+            (rules, policy_rules)
+        }
+        ImportMode::Replace => (bundle.rules, bundle.policy_rules),
+    };
+
+    Ok(ConfigBundle {
+        version: BUNDLE_VERSION,
+        exported_at: bundle.exported_at,
+        servers,
+        server_groups,
+        active_server,
+        socks: bundle.socks,
+        http: bundle.http,
+        access_mode: bundle.access_mode,
+        bind_mode: bundle.bind_mode,
+        protocol_mode: bundle.protocol_mode,
+        rules,
+        rule_sources,
+        policies,
+        policy_rules,
+        profiles,
+        cert,
+        keys: None,
+    })
+}
+
+fn merge_entries<T: Serialize + Clone>(
+    current: &[T],
+    imported: Vec<T>,
+    mode: ImportMode,
+    key: impl Fn(&T) -> String,
+    kind: ConflictKind,
+    conflicts: &mut Vec<ImportConflict>,
+) -> Vec<T> {
+    let compared = |x: &T| serde_json::to_value(x).ok();
+    merge_entries_by(current, imported, mode, key, compared, kind, conflicts)
+}
+
+/// Like `merge_entries`, comparing what `compared` returns for two entries
+/// with the same key.
+fn merge_entries_by<T: Clone>(
+    current: &[T],
+    imported: Vec<T>,
+    mode: ImportMode,
+    key: impl Fn(&T) -> String,
+    compared: impl Fn(&T) -> Option<serde_json::Value>,
+    kind: ConflictKind,
+    conflicts: &mut Vec<ImportConflict>,
+) -> Vec<T> {
+    for entry in imported.iter() {
+        let existing = current.iter().find(|x| key(x) == key(entry));
+        if let Some(existing) = existing
+            && compared(existing) != compared(entry)
+        {
+            conflicts.push(ImportConflict {
+                kind,
+                name: key(entry),
+            });
+        }
+    }
+    if mode == ImportMode::Replace {
+        return imported;
+    }
+    let mut merged: Vec<T> = current
+        .iter()
+        .filter(|x| !imported.iter().any(|entry| key(entry) == key(x)))
+        .cloned()
+        .collect();
+    merged.extend(imported);
+    merged
+}
+
+fn append_rules(existing: &str, content: &str) -> Result<String, Error> {
+    let report =
+        import::import_rules(RuleFormat::Plain, content, RuleTarget::Proxy).map_err(Error::msg)?;
+    Ok(import::append_rules(
+        existing,
+        report.entries(RuleTarget::Proxy),
+    ))
+}
+
+fn validate(bundle: &ConfigBundle) -> Result<(), Error> {
+    for server in bundle.servers.iter() {
+        server
+            .validate()
+            .map_err(|e| anyhow!("server `{}`: {}", server.host, e))?;
+    }
+    group::validate_groups(&bundle.server_groups, &bundle.servers).map_err(Error::msg)?;
+    policy::validate_policies(&bundle.policies, &bundle.servers, &bundle.server_groups)
+        .map_err(Error::msg)?;
+    source::validate_sources(&bundle.rule_sources).map_err(Error::msg)?;
+    profile::validate_profiles(
+        &bundle.profiles,
+        &bundle.servers,
+        &bundle.server_groups,
+        &bundle.rule_sources,
+        &bundle.policies,
+    )
+    .map_err(Error::msg)?;
+    if let Some(active_server) = &bundle.active_server
+        && !bundle.servers.iter().any(|x| &x.host == active_server)
+        && !bundle
+            .server_groups
+            .iter()
+            .any(|x| &x.name == active_server)
+    {
+        return Err(anyhow!("active server `{}` is unknown", active_server));
+    }
+
+    for rule_path in bundle.rules.keys() {
+        if !RULE_LISTS.iter().any(|(x, _)| x == rule_path) {
+            return Err(anyhow!("unknown rule list `{}`", rule_path));
+        }
+    }
+    let content = |rule_path: &str| {
+        bundle
+            .rules
+            .get(rule_path)
+            .map(|x| x.as_str())
+            .unwrap_or_default()
+    };
+    for (rule_path, _) in RULE_LISTS {
+        let others: Vec<(&str, &str)> = RULE_LISTS
+            .iter()
+            .filter(|(x, _)| *x != rule_path)
+            .map(|(x, label)| (*label, content(x)))
+            .collect();
+        check_lint(rule_path, lint::lint_rules(content(rule_path), &others))?;
+    }
+    for (name, rules) in bundle.policy_rules.iter() {
+        let policy = bundle.policies.iter().find(|x| &x.name == name);
+        let policy = policy.ok_or(anyhow!("rules for unknown policy `{}`", name))?;
+        let others = [
+            ("direct", content(store::DIRECT_RULES_PATH)),
+            ("block", content(store::BLOCK_RULES_PATH)),
+        ];
+        check_lint(&policy.rules_path(), lint::lint_rules(rules, &others))?;
+    }
+    Ok(())
+}
+
+fn check_lint(rule_path: &str, report: LintReport) -> Result<(), Error> {
+    if !report.has_errors() {
+        return Ok(());
+    }
+    let issue = report.issues.iter().find(|x| x.severity == Severity::Error);
+    let message = issue.map(|x| x.message.clone()).unwrap_or_default();
+    Err(anyhow!("`{}` has invalid rules: {}", rule_path, message))
+}
+
+fn apply(app: &AppHandle, bundle: ConfigBundle, keys: Option<&BundleKeys>) -> Result<(), Error> {
+    let mut servers = bundle.servers;
+    if let Some(keys) = keys {
+        for server in servers.iter_mut() {
+            if let Some(cert_key) = keys.server_keys.get(&server.host) {
+                server.cert_key = cert_key.clone();
+            }
+        }
+        store::set_cert(app, store::CERT_KEY_PATH, &keys.cert_key)?;
+    }
+    store::set_servers(app, &servers, HistorySource::Import)?;
+    store::set_server_groups(app, &bundle.server_groups)?;
+    let active_server = bundle.active_server.unwrap_or_default();
+    store::set_str_config(app, store::ACTIVE_SERVER, &active_server)?;
+    store::set_address(app, store::SOCKS_ADDR, bundle.socks)?;
+    store::set_address(app, store::HTTP_ADDR, bundle.http)?;
+    store::set_str_config(app, store::ACCESS_MODE, &bundle.access_mode.to_string())?;
+    store::set_str_config(app, store::BIND_MODE, &bundle.bind_mode.to_string())?;
+    store::set_str_config(app, store::PROTOCOL_MODE, &bundle.protocol_mode.to_string())?;
+    store::set_rule_sources(app, &bundle.rule_sources)?;
+    store::init_rule_source_files(app)?;
+    store::set_policies(app, &bundle.policies)?;
+    store::set_profiles(app, &bundle.profiles)?;
+    for policy in bundle.policies.iter() {
+        if let Some(rules) = bundle.policy_rules.get(&policy.name) {
+            store::set_rules(app, &policy.rules_path(), rules, HistorySource::Import)?;
+        }
+    }
+    for (rule_path, rules) in bundle.rules.iter() {
+        store::set_rules(app, rule_path, rules, HistorySource::Import)?;
+    }
+    if !bundle.cert.is_empty() {
+        store::set_cert(app, store::CERT_PATH, &bundle.cert)?;
+    }
+    store::combine_proxy_rules(app)?;
+    Ok(())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], Error> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("derive key error: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt_keys(keys: &BundleKeys, passphrase: &str) -> Result<EncryptedKeys, Error> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+    let cipher = ChaCha20Poly1305::new(&key.into());
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(keys)?;
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| anyhow!("encrypt keys error"))?;
+    Ok(EncryptedKeys {
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+fn decrypt_keys(keys: &EncryptedKeys, passphrase: &str) -> Result<BundleKeys, Error> {
+    let salt = STANDARD.decode(&keys.salt)?;
+    let nonce = STANDARD.decode(&keys.nonce)?;
+    let ciphertext = STANDARD.decode(&keys.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err(anyhow!("the keys in the bundle are damaged"));
+    }
+    let key = derive_key(passphrase, &salt)?;
+    let cipher = ChaCha20Poly1305::new(&key.into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("wrong passphrase or damaged keys"))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        group::{BalanceMode, GroupStrategy},
+        server::TransportOptions,
+    };
+
+    fn server(host: &str, cert_key: &str) -> ServerInfo {
+        ServerInfo {
+            host: host.to_string(),
+            quic_port: 4433,
+            tcp_port: Some(443),
+            cert: format!("{} cert", host),
+            cert_key: cert_key.to_string(),
+            alias: None,
+            transport: TransportOptions::default(),
+        }
+    }
+
+    fn group(name: &str, servers: &[&str]) -> ServerGroup {
+        ServerGroup {
+            name: name.to_string(),
+            strategy: GroupStrategy::Fallback,
+            balance: BalanceMode::default(),
+            servers: servers.iter().map(|x| x.to_string()).collect(),
+            interval: 300,
+        }
+    }
+
+    fn bundle(servers: Vec<ServerInfo>, server_groups: Vec<ServerGroup>) -> ConfigBundle {
+        ConfigBundle {
+            version: BUNDLE_VERSION,
+            exported_at: 0,
+            servers,
+            server_groups,
+            active_server: None,
+            socks: AddrInfo::new("127.0.0.1".to_string(), 1080),
+            http: AddrInfo::new("127.0.0.1".to_string(), 1081),
+            access_mode: AccessMode::Auto,
+            bind_mode: BindMode::Socks,
+            protocol_mode: ProtocolMode::Tcp,
+            rules: BTreeMap::new(),
+            rule_sources: Vec::new(),
+            policies: Vec::new(),
+            policy_rules: BTreeMap::new(),
+            profiles: Vec::new(),
+            cert: String::new(),
+            keys: None,
+        }
+    }
+
+    fn names(conflicts: &[ImportConflict]) -> Vec<(ConflictKind, &str)> {
+        conflicts
+            .iter()
+            .map(|x| (x.kind, x.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn keys_survive_an_encryption_round_trip() {
+        let keys = BundleKeys {
+            cert_key: "local key".to_string(),
+            server_keys: BTreeMap::from([("a.example".to_string(), "a key".to_string())]),
+        };
+        let encrypted = encrypt_keys(&keys, "correct horse").unwrap();
+        assert!(!encrypted.ciphertext.contains("a key"));
+        let decrypted = decrypt_keys(&encrypted, "correct horse").unwrap();
+        assert_eq!(decrypted.cert_key, "local key");
+        assert_eq!(decrypted.server_keys, keys.server_keys);
+    }
+
+    #[test]
+    fn wrong_passphrase_is_reported() {
+        let encrypted = encrypt_keys(&BundleKeys::default(), "correct horse").unwrap();
+        let error = decrypt_keys(&encrypted, "battery staple").unwrap_err();
+        assert_eq!(error.to_string(), "wrong passphrase or damaged keys");
+    }
+
+    #[test]
+    fn merge_keeps_entries_the_bundle_lacks() {
+        let current = bundle(
+            vec![server("a.example", "a key"), server("b.example", "b key")],
+            vec![group("all", &["a.example", "b.example"])],
+        );
+        let mut changed = server("a.example", "");
+        changed.tcp_port = Some(8443);
+        let imported = bundle(
+            vec![changed, server("c.example", "")],
+            vec![group("all", &["a.example", "b.example"])],
+        );
+        let mut conflicts = Vec::new();
+        let merged = merge(current, imported, ImportMode::Merge, &mut conflicts).unwrap();
+        // the group is identical, only the changed server conflicts
+        assert_eq!(names(&conflicts), [(ConflictKind::Server, "a.example")]);
+        let hosts: Vec<&str> = merged.servers.iter().map(|x| x.host.as_str()).collect();
+        assert_eq!(hosts, ["b.example", "a.example", "c.example"]);
+        assert_eq!(merged.servers[1].tcp_port, Some(8443));
+        validate(&merged).unwrap();
+    }
+
+    #[test]
+    fn replace_drops_entries_the_bundle_lacks() {
+        let mut current = bundle(
+            vec![server("a.example", "a key"), server("b.example", "b key")],
+            vec![group("all", &["a.example", "b.example"])],
+        );
+        current.access_mode = AccessMode::Proxy;
+        let imported = bundle(
+            vec![server("a.example", "")],
+            vec![group("all", &["a.example"])],
+        );
+        let mut conflicts = Vec::new();
+        let merged = merge(current, imported, ImportMode::Replace, &mut conflicts).unwrap();
+        // a.example only lacks its key, like every exported server
+        assert_eq!(
+            names(&conflicts),
+            [
+                (ConflictKind::Group, "all"),
+                (ConflictKind::Setting, store::ACCESS_MODE),
+            ]
+        );
+        let hosts: Vec<&str> = merged.servers.iter().map(|x| x.host.as_str()).collect();
+        assert_eq!(hosts, ["a.example"]);
+        assert_eq!(merged.access_mode, AccessMode::Auto);
+        validate(&merged).unwrap();
+    }
+
+    #[test]
+    fn cert_keys_are_kept_when_the_bundle_omits_them() {
+        for mode in [ImportMode::Merge, ImportMode::Replace] {
+            let current = bundle(vec![server("a.example", "a key")], Vec::new());
+            let imported = bundle(
+                vec![server("a.example", ""), server("b.example", "")],
+                Vec::new(),
+            );
+            let mut conflicts = Vec::new();
+            let merged = merge(current, imported, mode, &mut conflicts).unwrap();
+            assert!(conflicts.is_empty(), "{:?}", mode);
+            assert_eq!(merged.servers[0].cert_key, "a key", "{:?}", mode);
+            assert_eq!(merged.servers[1].cert_key, "", "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn merge_appends_rule_lists() {
+        let mut current = bundle(Vec::new(), Vec::new());
+        current.rules.insert(
+            store::DIRECT_RULES_PATH.to_string(),
+            "a.com\nb.com\n".to_string(),
+        );
+        let mut imported = bundle(Vec::new(), Vec::new());
+        imported.rules.insert(
+            store::DIRECT_RULES_PATH.to_string(),
+            "b.com\nc.com\n".to_string(),
+        );
+        let merged = merge(current, imported, ImportMode::Merge, &mut Vec::new()).unwrap();
+        assert_eq!(
+            merged.rules[store::DIRECT_RULES_PATH],
+            "a.com\nb.com\nc.com\n"
+        );
+    }
+
+    #[test]
+    fn validate_rejects_dangling_references() {
+        let broken = bundle(
+            vec![server("a.example", "")],
+            vec![group("all", &["a.example", "gone.example"])],
+        );
+        assert_eq!(
+            validate(&broken).unwrap_err().to_string(),
+            "group `all` contains unknown server `gone.example`"
+        );
+        let mut broken = bundle(vec![server("a.example", "")], Vec::new());
+        broken.active_server = Some("gone.example".to_string());
+        assert_eq!(
+            validate(&broken).unwrap_err().to_string(),
+            "active server `gone.example` is unknown"
+        );
+        let mut broken = bundle(Vec::new(), Vec::new());
+        broken.rules.insert(
+            store::BLOCK_RULES_PATH.to_string(),
+            "*.bad.com\n".to_string(),
+        );
+        assert!(
+            validate(&broken)
+                .unwrap_err()
+                .to_string()
+                .starts_with("`block_list.txt` has invalid rules")
+        );
+    }
+}
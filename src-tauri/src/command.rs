@@ -1,67 +1,149 @@
-use std::sync::Mutex;
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
 
 use tauri::{AppHandle, Emitter, Manager};
 
 use crate::{
+    agent::{self, Diagnostics},
+    bundle::{self, ConfigBundle, ConfigImportReport, ImportMode},
+    connection,
+    group::{self, ServerGroup, ServerHealth},
+    history::{self, HistorySource, HistoryVersion},
+    network::{self, NetworkDecision, NetworkInfo, NetworkSettings},
+    policy::{self, Policy, RouteDecision},
+    profile::{self, Profile},
+    protocol,
+    rules::{
+        export::{self, ExportFile, ExportFormat},
+        import::{self, ImportReport},
+        lint::{self, LintIssue, LintKind, LintReport, Severity},
+        refresh::{self, SourceStatuses},
+        source::{self, RuleFormat, RuleSource, RuleTarget},
+    },
     server::{AddrInfo, ListenConfig, ListenConfigOption, ServerInfo},
     shell,
-    state::{AccessMode, AgentState, BindMode, ProtocolMode},
+    state::{
+        AccessMode, AgentConfigState, AgentState, BindMode, ConnectionStatus, HealthState,
+        ProtocolMode,
+    },
     store::{self, HTTP_ADDR, SOCKS_ADDR},
     tray::{self},
+    watch,
 };
 
+/// Access, bind or protocol mode changed, or secc was turned on or off.
+pub const MODES_CHANGED_EVENT: &str = "modes_changed";
+
 pub fn restart_agent(app: &AppHandle) {
     shell::kill_sidecar(app);
     shell::call_sidecar(app);
+    protocol::agent_started(app);
+}
+
+/// Bring the agent in line with the store: nothing when its config is
+/// unchanged, a reload when only reloadable settings changed, otherwise a
+/// restart. With secc turned off the agent stays stopped, `open_secc` starts
+/// it with the stored config.
+pub fn apply_agent_config(app: &AppHandle) {
+    let agent_state = {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let agent_state = agent_state.lock().unwrap();
+        agent_state.get()
+    };
+    if !agent_state {
+        return;
+    }
+    let running = {
+        let config_state = app.state::<Mutex<AgentConfigState>>();
+        let config_state = config_state.lock().unwrap();
+        config_state.get().cloned()
+    };
+    let config = agent::build_config(app).ok();
+    if let (Some(config), Some(running)) = (config, running)
+        && shell::is_sidecar_running(app)
+    {
+        if config == running {
+            println!("agent config unchanged, not restarting");
+            return;
+        }
+        if !config.needs_restart(&running) && shell::reload_sidecar(app) {
+            println!("agent config reloaded");
+            return;
+        }
+    }
+    restart_agent(app);
+}
+
+/// Make the running agent re-read its rule lists, restarting it when it
+/// can't reload.
+pub fn reload_agent(app: &AppHandle) {
+    if !shell::is_sidecar_running(app) {
+        return;
+    }
+    let running = {
+        let config_state = app.state::<Mutex<AgentConfigState>>();
+        let config_state = config_state.lock().unwrap();
+        config_state.get().cloned()
+    };
+    let needs_restart = match (agent::build_config(app), running) {
+        (Ok(config), Some(running)) => config.needs_restart(&running),
+        _ => true,
+    };
+    if needs_restart || !shell::reload_sidecar(app) {
+        restart_agent(app);
+    }
 }
 
 #[tauri::command]
 pub fn open_secc(app: AppHandle) {
+    // don't point the system proxy at an agent that can't start
+    let agent_info = agent::agent_info(&app);
+    if !agent_info.compatible {
+        let message = agent_info.message.unwrap_or_default();
+        app.emit(shell::AGENT_INCOMPATIBLE_EVENT, &message).unwrap();
+        connection::set(&app, ConnectionStatus::Error);
+        return;
+    }
     let bind_mode = get_bind_mode(app.clone());
-    match bind_mode {
-        BindMode::Socks => {
-            shell::switch_to_socks(&app);
-        }
-        BindMode::Http => {
-            shell::switch_to_http(&app);
-        }
+    shell::switch_to_bind_mode(&app, bind_mode);
+    {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let mut agent_state = agent_state.lock().unwrap();
+        agent_state.set(true);
     }
-    restart_agent(&app);
-    let agent_state = app.state::<Mutex<AgentState>>();
-    let mut agent_state = agent_state.lock().unwrap();
-    agent_state.set(true);
-    tray::change_tray_icon(&app, true).unwrap();
+    apply_agent_config(&app);
+    tray::update_tray_icon(&app).unwrap();
+    app.emit(MODES_CHANGED_EVENT, ()).unwrap();
 }
 
 #[tauri::command]
 pub fn close_secc(app: AppHandle) {
     shell::kill_sidecar(&app);
     shell::switch_to_direct(&app);
-    let agent_state = app.state::<Mutex<AgentState>>();
-    let mut agent_state = agent_state.lock().unwrap();
-    agent_state.set(false);
-    tray::change_tray_icon(&app, false).unwrap();
+    {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let mut agent_state = agent_state.lock().unwrap();
+        agent_state.set(false);
+    }
+    connection::set(&app, ConnectionStatus::Off);
+    app.emit(MODES_CHANGED_EVENT, ()).unwrap();
 }
 
 #[tauri::command]
 pub fn switch_access_mode(app: AppHandle, access_mode: AccessMode) {
     println!("access_mode: {:?}", access_mode);
     store::set_str_config(&app, store::ACCESS_MODE, access_mode.to_string().as_str()).unwrap();
-    restart_agent(&app);
+    apply_agent_config(&app);
+    // the icon tells global mode apart
+    tray::update_tray_icon(&app).unwrap();
+    app.emit(MODES_CHANGED_EVENT, ()).unwrap();
 }
 
 #[tauri::command]
 pub fn switch_bind_mode(app: AppHandle, bind_mode: BindMode) {
     println!("bind_mode: {:?}", bind_mode);
-    match bind_mode {
-        BindMode::Socks => {
-            shell::switch_to_socks(&app);
-        }
-        BindMode::Http => {
-            shell::switch_to_http(&app);
-        }
-    }
+    shell::switch_to_bind_mode(&app, bind_mode);
     store::set_str_config(&app, store::BIND_MODE, bind_mode.to_string().as_str()).unwrap();
+    app.emit(MODES_CHANGED_EVENT, ()).unwrap();
 }
 
 #[tauri::command]
@@ -73,7 +155,8 @@ pub fn switch_protocol_mode(app: AppHandle, protocol_mode: ProtocolMode) {
         protocol_mode.to_string().as_str(),
     )
     .unwrap();
-    restart_agent(&app);
+    apply_agent_config(&app);
+    app.emit(MODES_CHANGED_EVENT, ()).unwrap();
 }
 
 #[tauri::command]
@@ -116,32 +199,75 @@ pub fn get_server(app: AppHandle, host: &str) -> Option<ServerInfo> {
 }
 
 #[tauri::command]
-pub fn add_server(app: AppHandle, server: ServerInfo) {
-    store::add_server(&app, server).unwrap();
+pub fn add_server(app: AppHandle, server: ServerInfo) -> Result<(), String> {
+    server.validate()?;
+    store::add_server(&app, server).map_err(|e| e.to_string())?;
     app.emit("refresh_servers", ()).unwrap();
+    Ok(())
 }
 
+/// A server is only deleted when nothing would be left pointing at it: it is
+/// removed from the groups it is in, unless that leaves a group empty, and no
+/// policy or profile may use it.
 #[tauri::command]
-pub fn delete_server(app: AppHandle, host: &str) {
-    let cloned_app = app.clone();
+pub fn delete_server(app: AppHandle, host: &str) -> Result<(), String> {
+    let mut groups = store::get_server_groups(&app).map_err(|e| e.to_string())?;
+    let policies = store::get_policies(&app).map_err(|e| e.to_string())?;
+    let profiles = store::get_profiles(&app).map_err(|e| e.to_string())?;
+    let users: Vec<String> = groups
+        .iter()
+        .filter(|x| {
+            x.servers.iter().any(|member| member == host)
+                && x.servers.iter().all(|member| member == host)
+        })
+        .map(|x| format!("group `{}`", x.name))
+        .chain(
+            policies
+                .iter()
+                .filter(|x| x.server == host)
+                .map(|x| format!("policy `{}`", x.name)),
+        )
+        .chain(
+            profiles
+                .iter()
+                .filter(|x| x.active_server.as_deref() == Some(host))
+                .map(|x| format!("profile `{}`", x.name)),
+        )
+        .collect();
+    if !users.is_empty() {
+        return Err(format!(
+            "server `{}` is still used by {}",
+            host,
+            users.join(", ")
+        ));
+    }
+
     let res = store::get_value_by_key(&app, store::ACTIVE_SERVER);
-    if let Ok(Some(server)) = res {
-        if server == host {
-            store::set_str_config(&cloned_app, store::ACTIVE_SERVER, "").unwrap();
-            app.emit("active_server_disable", ()).unwrap();
-            close_secc(app.clone());
-        }
+    if let Ok(Some(server)) = res
+        && server == host
+    {
+        store::set_str_config(&app, store::ACTIVE_SERVER, "").unwrap();
+        app.emit("active_server_disable", ()).unwrap();
+        close_secc(app.clone());
     }
-    store::delete_server(&app, host).unwrap();
+    store::delete_server(&app, host).map_err(|e| e.to_string())?;
+    for group in groups.iter_mut() {
+        group.servers.retain(|x| x != host);
+    }
+    store::set_server_groups(&app, &groups).map_err(|e| e.to_string())?;
     app.emit("refresh_servers", ()).unwrap();
+    Ok(())
 }
 
 #[tauri::command]
-pub fn update_server(app: AppHandle, server: ServerInfo) {
-    store::update_server(&app, server).unwrap();
+pub fn update_server(app: AppHandle, server: ServerInfo) -> Result<(), String> {
+    server.validate()?;
+    store::update_server(&app, server).map_err(|e| e.to_string())?;
     app.emit("refresh_servers", ()).unwrap();
+    Ok(())
 }
 
+/// `host` is a server host or a server group name.
 #[tauri::command]
 pub fn active_server(app: AppHandle, host: &str) {
     store::set_str_config(&app, store::ACTIVE_SERVER, host).unwrap();
@@ -153,6 +279,107 @@ pub fn get_active_server(app: AppHandle) -> Option<String> {
     res.ok().flatten()
 }
 
+#[tauri::command]
+pub fn get_profiles(app: AppHandle) -> Vec<Profile> {
+    let result = store::get_profiles(&app);
+    result.unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_profiles(app: AppHandle, profiles: Vec<Profile>) -> Result<(), String> {
+    println!("request body: {:#?}", profiles);
+    let servers = store::get_servers(&app).unwrap_or_default();
+    let groups = store::get_server_groups(&app).unwrap_or_default();
+    let sources = store::get_rule_sources(&app).unwrap_or_default();
+    let policies = store::get_policies(&app).unwrap_or_default();
+    profile::validate_profiles(
+        &profiles,
+        &servers.unwrap_or_default(),
+        &groups,
+        &sources,
+        &policies,
+    )?;
+    // network rules must not be left pointing at a removed profile
+    let network_settings = store::get_network_settings(&app).unwrap_or_default();
+    network::validate_settings(&network_settings, &profiles)?;
+    store::set_profiles(&app, &profiles).map_err(|e| e.to_string())
+}
+
+/// A profile holding the current configuration, not saved yet.
+#[tauri::command]
+pub fn capture_profile(app: AppHandle, name: &str) -> Result<Profile, String> {
+    profile::capture(&app, name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_active_profile(app: AppHandle) -> Option<String> {
+    let res = store::get_value_by_key(&app, store::ACTIVE_PROFILE);
+    res.ok().flatten().filter(|x| !x.is_empty())
+}
+
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, name: &str) -> Result<(), String> {
+    println!("request body: {}", name);
+    profile::switch(&app, name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_network_settings(app: AppHandle) -> NetworkSettings {
+    let result = store::get_network_settings(&app);
+    result.unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_network_settings(app: AppHandle, settings: NetworkSettings) -> Result<(), String> {
+    println!("request body: {:#?}", settings);
+    let profiles = store::get_profiles(&app).unwrap_or_default();
+    network::validate_settings(&settings, &profiles)?;
+    store::set_network_settings(&app, &settings).map_err(|e| e.to_string())?;
+    network::spawn_evaluation(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_network_info(app: AppHandle) -> NetworkInfo {
+    network::detect(&app).await
+}
+
+#[tauri::command]
+pub fn get_network_decisions(app: AppHandle) -> Vec<NetworkDecision> {
+    network::decisions(&app)
+}
+
+#[tauri::command]
+pub fn get_server_groups(app: AppHandle) -> Vec<ServerGroup> {
+    let result = store::get_server_groups(&app);
+    result.unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_server_groups(app: AppHandle, groups: Vec<ServerGroup>) -> Result<(), String> {
+    println!("request body: {:#?}", groups);
+    let servers = store::get_servers(&app).unwrap_or_default();
+    group::validate_groups(&groups, &servers.unwrap_or_default())?;
+    store::set_server_groups(&app, &groups).map_err(|e| e.to_string())?;
+    app.emit("refresh_servers", ()).unwrap();
+    check_group_health(app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_group_health(app: AppHandle) -> HashMap<String, ServerHealth> {
+    let health = app.state::<Mutex<HealthState>>();
+    let health = health.lock().unwrap();
+    health.get().clone()
+}
+
+#[tauri::command]
+pub fn check_group_health(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        group::check_health(&app).await;
+    });
+}
+
 #[tauri::command]
 pub fn set_listen_config(app: AppHandle, listen_config: ListenConfig) {
     println!("request body: {:#?}", listen_config);
@@ -172,9 +399,52 @@ pub fn get_listen_config(app: AppHandle) -> ListenConfigOption {
 }
 
 #[tauri::command]
-pub fn set_direct_rules(app: AppHandle, direct_rules: &str) {
+pub fn set_direct_rules(app: AppHandle, direct_rules: &str) -> Result<LintReport, LintReport> {
     println!("request body: {:#?}", direct_rules);
-    store::set_rules(&app, store::DIRECT_RULES_PATH, direct_rules).unwrap();
+    let report = lint_direct_rules(app.clone(), direct_rules);
+    if report.has_errors() {
+        return Err(report);
+    }
+    store::set_rules(
+        &app,
+        store::DIRECT_RULES_PATH,
+        direct_rules,
+        HistorySource::Ui,
+    )
+    .unwrap();
+    reload_agent(&app);
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn lint_direct_rules(app: AppHandle, direct_rules: &str) -> LintReport {
+    lint_against_others(&app, direct_rules, RuleTarget::Direct)
+}
+
+/// Lint a list against every list with a different outcome than `target`:
+/// the user's own lists and the downloaded copies of the enabled sources.
+fn lint_against_others(app: &AppHandle, rules: &str, target: RuleTarget) -> LintReport {
+    let mut others = Vec::new();
+    for (other, name, path) in [
+        (RuleTarget::Direct, "direct", store::DIRECT_RULES_PATH),
+        (RuleTarget::Proxy, "proxy", store::CUSTOM_PROXY_RULES_PATH),
+        (RuleTarget::Block, "block", store::BLOCK_RULES_PATH),
+    ] {
+        if other != target {
+            let content = store::get_rules(app, path).unwrap_or_default();
+            others.push((name.to_string(), content));
+        }
+    }
+    let sources = store::get_rule_sources(app).unwrap_or_default();
+    for source in sources.iter().filter(|x| x.enabled && x.target != target) {
+        let content = store::get_rules(app, &source.cache_path()).unwrap_or_default();
+        others.push((format!("{} source", source.id), content));
+    }
+    let others: Vec<(&str, &str)> = others
+        .iter()
+        .map(|(name, content)| (name.as_str(), content.as_str()))
+        .collect();
+    lint::lint_rules(rules, &others)
 }
 
 #[tauri::command]
@@ -184,13 +454,68 @@ pub fn get_direct_rules(app: AppHandle) -> String {
 }
 
 #[tauri::command]
-pub fn set_custom_proxy_rules(app: AppHandle, proxy_rules: &str, url: &str) {
+pub fn set_custom_proxy_rules(
+    app: AppHandle,
+    proxy_rules: &str,
+    url: &str,
+) -> Result<LintReport, LintReport> {
     println!("request body: {:#?}, {}", proxy_rules, url);
-    store::set_rules(&app, store::CUSTOM_PROXY_RULES_PATH, proxy_rules).unwrap();
-    store::set_str_config(&app, store::COMMUNITY_RULES, url).unwrap();
-    tauri::async_runtime::spawn(async move {
-        store::load_community_proxy_list(&app).await.unwrap();
-    });
+    let report = lint_custom_proxy_rules(app.clone(), proxy_rules);
+    if report.has_errors() {
+        return Err(report);
+    }
+    store::set_rules(
+        &app,
+        store::CUSTOM_PROXY_RULES_PATH,
+        proxy_rules,
+        HistorySource::Ui,
+    )
+    .unwrap();
+    reload_agent(&app);
+    let mut sources = store::get_rule_sources(&app).unwrap_or_default();
+    let community = sources
+        .iter_mut()
+        .find(|x| x.id == store::COMMUNITY_SOURCE_ID);
+    if let Some(community) = community {
+        community.url = url.to_string();
+        store::set_rule_sources(&app, &sources).unwrap();
+    }
+    refresh_rule_sources(app);
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn lint_custom_proxy_rules(app: AppHandle, proxy_rules: &str) -> LintReport {
+    lint_against_others(&app, proxy_rules, RuleTarget::Proxy)
+}
+
+#[tauri::command]
+pub fn set_block_rules(app: AppHandle, block_rules: &str) -> Result<LintReport, LintReport> {
+    println!("request body: {:#?}", block_rules);
+    let report = lint_block_rules(app.clone(), block_rules);
+    if report.has_errors() {
+        return Err(report);
+    }
+    store::set_rules(
+        &app,
+        store::BLOCK_RULES_PATH,
+        block_rules,
+        HistorySource::Ui,
+    )
+    .unwrap();
+    reload_agent(&app);
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_block_rules(app: AppHandle) -> String {
+    let result = store::get_rules(&app, store::BLOCK_RULES_PATH);
+    result.unwrap_or("".to_string())
+}
+
+#[tauri::command]
+pub fn lint_block_rules(app: AppHandle, block_rules: &str) -> LintReport {
+    lint_against_others(&app, block_rules, RuleTarget::Block)
 }
 
 #[tauri::command]
@@ -205,6 +530,179 @@ pub fn get_combined_proxy_rules(app: AppHandle) -> String {
     result.unwrap_or("".to_string())
 }
 
+#[tauri::command]
+pub fn get_combined_direct_rules(app: AppHandle) -> String {
+    let result = store::get_rules(&app, store::COMBINED_DIRECT_RULES_PATH);
+    result.unwrap_or("".to_string())
+}
+
+#[tauri::command]
+pub fn get_policies(app: AppHandle) -> Vec<Policy> {
+    let result = store::get_policies(&app);
+    result.unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_policies(app: AppHandle, policies: Vec<Policy>) -> Result<(), String> {
+    println!("request body: {:#?}", policies);
+    let servers = store::get_servers(&app).unwrap_or_default();
+    let groups = store::get_server_groups(&app).unwrap_or_default();
+    policy::validate_policies(&policies, &servers.unwrap_or_default(), &groups)?;
+    store::set_policies(&app, &policies).map_err(|e| e.to_string())?;
+    apply_agent_config(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_policy_rules(app: AppHandle, name: &str) -> Result<String, String> {
+    let policies = store::get_policies(&app).map_err(|e| e.to_string())?;
+    let policy = policies.iter().find(|x| x.name == name);
+    let policy = policy.ok_or(format!("unknown policy `{}`", name))?;
+    store::get_rules(&app, &policy.rules_path()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_policy_rules(
+    app: AppHandle,
+    name: &str,
+    policy_rules: &str,
+) -> Result<LintReport, LintReport> {
+    println!("request body: {:#?}, {}", policy_rules, name);
+    let policies = store::get_policies(&app).unwrap_or_default();
+    let Some(policy) = policies.iter().find(|x| x.name == name) else {
+        return Err(LintReport {
+            issues: vec![LintIssue {
+                line: 0,
+                entry: name.to_string(),
+                kind: LintKind::UnknownPolicy,
+                severity: Severity::Error,
+                message: format!("unknown policy `{}`", name),
+            }],
+        });
+    };
+    // policy rules are proxied, only through another server
+    let report = lint_against_others(&app, policy_rules, RuleTarget::Proxy);
+    if report.has_errors() {
+        return Err(report);
+    }
+    store::set_rules(&app, &policy.rules_path(), policy_rules, HistorySource::Ui).unwrap();
+    reload_agent(&app);
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn lookup_route(app: AppHandle, host: &str) -> Result<RouteDecision, String> {
+    policy::lookup_route(&app, host.trim()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_combined_block_rules(app: AppHandle) -> String {
+    let result = store::get_rules(&app, store::COMBINED_BLOCK_RULES_PATH);
+    result.unwrap_or("".to_string())
+}
+
+#[tauri::command]
+pub fn get_rule_sources(app: AppHandle) -> Vec<RuleSource> {
+    let result = store::get_rule_sources(&app);
+    result.unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_rule_sources(app: AppHandle, sources: Vec<RuleSource>) -> Result<(), String> {
+    println!("request body: {:#?}", sources);
+    source::validate_sources(&sources)?;
+    store::set_rule_sources(&app, &sources).map_err(|e| e.to_string())?;
+    refresh_rule_sources(app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn refresh_rule_sources(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = refresh::refresh_sources(&app, true).await {
+            eprintln!("refresh rule sources error: {:?}", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn import_rules(
+    app: AppHandle,
+    path: PathBuf,
+    format: RuleFormat,
+    target: RuleTarget,
+    replace: bool,
+) -> Result<ImportReport, String> {
+    println!("request body: {:?}, {:?}, {:?}", path, format, target);
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let report = import::import_rules(format, &content, target)?;
+    let lists = [
+        (store::CUSTOM_PROXY_RULES_PATH, RuleTarget::Proxy),
+        (store::DIRECT_RULES_PATH, RuleTarget::Direct),
+        (store::BLOCK_RULES_PATH, RuleTarget::Block),
+    ];
+    for (rule_path, target) in lists {
+        let imported = report.entries(target);
+        // lists without imported entries are left untouched, even when replacing
+        if imported.is_empty() {
+            continue;
+        }
+        let existing = match replace {
+            true => String::new(),
+            false => store::get_rules(&app, rule_path).unwrap_or_default(),
+        };
+        let rules = import::append_rules(&existing, imported);
+        store::set_rules(&app, rule_path, &rules, HistorySource::Import)
+            .map_err(|e| e.to_string())?;
+        app.emit(watch::REFRESH_RULES_EVENT, rule_path)
+            .map_err(|e| e.to_string())?;
+    }
+    reload_agent(&app);
+    Ok(report)
+}
+
+/// Write the combined lists into `directory`. `pac_proxy` defaults to the
+/// local listener of the current bind mode.
+#[tauri::command]
+pub fn export_rules(
+    app: AppHandle,
+    directory: PathBuf,
+    format: ExportFormat,
+    pac_proxy: Option<String>,
+) -> Result<Vec<ExportFile>, String> {
+    println!("request body: {:?}, {:?}", directory, format);
+    let proxy_rules = store::get_rules(&app, store::PROXY_RULES_PATH).map_err(|e| e.to_string())?;
+    let direct_rules =
+        store::get_rules(&app, store::COMBINED_DIRECT_RULES_PATH).map_err(|e| e.to_string())?;
+    let pac_proxy = match pac_proxy {
+        Some(pac_proxy) => pac_proxy,
+        None => match get_bind_mode(app.clone()) {
+            BindMode::Socks => {
+                let addr = store::get_address(&app, SOCKS_ADDR).ok().flatten();
+                let addr = addr.unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1080));
+                format!("SOCKS5 {0}:{1}; SOCKS {0}:{1}", addr.host, addr.port)
+            }
+            BindMode::Http => {
+                let addr = store::get_address(&app, HTTP_ADDR).ok().flatten();
+                let addr = addr.unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1081));
+                format!("PROXY {}:{}", addr.host, addr.port)
+            }
+        },
+    };
+    let files = export::export_rules(format, &proxy_rules, &direct_rules, &pac_proxy);
+    for file in files.iter() {
+        store::write_atomic(&directory.join(&file.name), file.content.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(files)
+}
+
+#[tauri::command]
+pub fn get_rule_sources_status(app: AppHandle) -> SourceStatuses {
+    let result = store::get_rule_sources_status(&app);
+    result.unwrap_or_default()
+}
+
 #[tauri::command]
 pub fn set_cert(app: AppHandle, cert: &str) {
     println!("request body: {:#?}", cert);
@@ -229,6 +727,95 @@ pub fn get_cert_key(app: AppHandle) -> String {
     result.unwrap_or("".to_string())
 }
 
+#[tauri::command]
+pub fn list_history(app: AppHandle, subject: &str) -> Result<Vec<HistoryVersion>, String> {
+    history::list_versions(&app, subject).map_err(|e| e.to_string())
+}
+
+/// `to` defaults to the current content.
+#[tauri::command]
+pub fn diff_history(
+    app: AppHandle,
+    subject: &str,
+    from: u64,
+    to: Option<u64>,
+) -> Result<String, String> {
+    history::diff(&app, subject, from, to).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn restore_history(app: AppHandle, subject: &str, id: u64) -> Result<(), String> {
+    println!("request body: {}, {}", subject, id);
+    let content = history::read_version(&app, subject, id).map_err(|e| e.to_string())?;
+    if subject == history::SERVERS_SUBJECT {
+        let servers: Vec<ServerInfo> = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+        for server in servers.iter() {
+            server.validate()?;
+        }
+        store::set_servers(&app, &servers, HistorySource::Restore).map_err(|e| e.to_string())?;
+        app.emit("refresh_servers", ()).unwrap();
+        apply_agent_config(&app);
+        return Ok(());
+    }
+    store::set_rules(&app, subject, &content, HistorySource::Restore).map_err(|e| e.to_string())?;
+    // source caches and policy lists aren't recombined by set_rules
+    store::combine_proxy_rules(&app).map_err(|e| e.to_string())?;
+    reload_agent(&app);
+    app.emit(watch::REFRESH_RULES_EVENT, subject).unwrap();
+    Ok(())
+}
+
+/// Keys are only exported, encrypted, when a passphrase is given.
+#[tauri::command]
+pub fn export_config(
+    app: AppHandle,
+    path: PathBuf,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    println!("request body: {:?}", path);
+    let bundle = bundle::export(&app, passphrase.as_deref()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_vec_pretty(&bundle).map_err(|e| e.to_string())?;
+    store::write_atomic(&path, &content).map_err(|e| e.to_string())
+}
+
+/// With `dry_run` the bundle is only checked and the conflicts reported.
+#[tauri::command]
+pub fn import_config(
+    app: AppHandle,
+    path: PathBuf,
+    mode: ImportMode,
+    passphrase: Option<String>,
+    dry_run: bool,
+) -> Result<ConfigImportReport, String> {
+    println!("request body: {:?}, {:?}, {}", path, mode, dry_run);
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let bundle: ConfigBundle = serde_json::from_str(&content)
+        .map_err(|e| format!("not a secc configuration bundle: {}", e))?;
+    let report = bundle::import(&app, bundle, mode, passphrase.as_deref(), dry_run)
+        .map_err(|e| e.to_string())?;
+    if !report.applied {
+        return Ok(report);
+    }
+    let agent_state = {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let agent_state = agent_state.lock().unwrap();
+        agent_state.get()
+    };
+    // the system proxy has to follow the imported listener and bind mode
+    if agent_state {
+        open_secc(app.clone());
+    }
+    refresh_rule_sources(app.clone());
+    app.emit("refresh_servers", ()).unwrap();
+    app.emit("refresh", ()).unwrap();
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_diagnostics(app: AppHandle) -> Diagnostics {
+    agent::diagnostics(&app)
+}
+
 #[tauri::command]
 pub fn close_app(app: AppHandle) {
     app.exit(0);
@@ -0,0 +1,83 @@
+//! Status of the connection the tray icon shows. The agent is connecting
+//! from its start until a request went through the tunnel, and in error when
+//! it can't start, exits on its own or the tunnel doesn't answer.
+
+use std::{sync::Mutex, time::Duration};
+
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    protocol,
+    rules::refresh,
+    state::{ConnectionState, ConnectionStatus},
+    tray,
+};
+
+/// Time the agent gets to bring the tunnel up before it is probed.
+const PROBE_DELAY: Duration = Duration::from_secs(2);
+const PROBE_ATTEMPTS: usize = 3;
+const PROBE_RETRY: Duration = Duration::from_secs(3);
+
+pub fn get(app: &AppHandle) -> ConnectionStatus {
+    let connection_state = app.state::<Mutex<ConnectionState>>();
+    let connection_state = connection_state.lock().unwrap();
+    connection_state.get()
+}
+
+pub fn set(app: &AppHandle, status: ConnectionStatus) {
+    {
+        let connection_state = app.state::<Mutex<ConnectionState>>();
+        let mut connection_state = connection_state.lock().unwrap();
+        connection_state.set(status);
+    }
+    if let Err(e) = tray::update_tray_icon(app) {
+        eprintln!("update tray icon error: {:?}", e);
+    }
+}
+
+/// The agent was just started: connecting until the tunnel answers a probe.
+pub fn agent_started(app: &AppHandle) {
+    let generation = {
+        let connection_state = app.state::<Mutex<ConnectionState>>();
+        let mut connection_state = connection_state.lock().unwrap();
+        connection_state.set(ConnectionStatus::Connecting)
+    };
+    if let Err(e) = tray::update_tray_icon(app) {
+        eprintln!("update tray icon error: {:?}", e);
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(PROBE_DELAY).await;
+        let mut connected = false;
+        for attempt in 0..PROBE_ATTEMPTS {
+            if attempt > 0 {
+                tokio::time::sleep(PROBE_RETRY).await;
+            }
+            // secc was turned off meanwhile
+            let Some(proxy) = refresh::tunnel_proxy(&app) else {
+                break;
+            };
+            if protocol::probe_tunnel(&app, proxy).await {
+                connected = true;
+                break;
+            }
+        }
+        let status = match connected {
+            true => ConnectionStatus::Connected,
+            false => ConnectionStatus::Error,
+        };
+        {
+            let connection_state = app.state::<Mutex<ConnectionState>>();
+            let mut connection_state = connection_state.lock().unwrap();
+            // the agent was restarted or stopped in the meantime
+            if connection_state.generation() != generation {
+                return;
+            }
+            connection_state.set(status);
+        }
+        println!("connection is {:?}", status);
+        if let Err(e) = tray::update_tray_icon(&app) {
+            eprintln!("update tray icon error: {:?}", e);
+        }
+    });
+}
@@ -0,0 +1,398 @@
+//! Server groups: several servers behind one name, picked by a strategy.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::{net::TcpStream, task::JoinSet};
+
+use crate::{
+    command, protocol,
+    rules::refresh::now_secs,
+    server::ServerInfo,
+    state::{AgentState, HealthState, ProtocolMode},
+    store,
+};
+
+pub const GROUP_HEALTH_EVENT: &str = "group_health";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const MIN_CHECK_INTERVAL: u64 = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupStrategy {
+    /// The healthy member with the lowest latency, measured as the round
+    /// trip of a probe over the protocol the agent uses.
+    UrlTest,
+    /// The first healthy member, in the configured order.
+    Fallback,
+    /// Every healthy member, the agent spreads connections over them.
+    LoadBalance,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceMode {
+    #[default]
+    RoundRobin,
+    /// The same destination always uses the same member.
+    ConsistentHash,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerGroup {
+    pub name: String,
+    pub strategy: GroupStrategy,
+    #[serde(default)]
+    pub balance: BalanceMode,
+    /// Hosts of the member servers.
+    pub servers: Vec<String>,
+    /// Seconds between two health checks.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    300
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ServerHealth {
+    /// `None` when the server can't be probed over the protocol in use.
+    pub healthy: Option<bool>,
+    /// Round trip of the probe in milliseconds: the answer to a QUIC packet,
+    /// or the TCP handshake.
+    pub latency: Option<u64>,
+    pub error: Option<String>,
+    pub checked_at: u64,
+}
+
+impl ServerGroup {
+    /// The members the strategy would use, best first. Members known to be
+    /// healthy come before members of unknown health, which includes members
+    /// that were never checked; members known to be down are left out. When
+    /// every member is down the group falls back to its first member so there
+    /// is always something to connect to.
+    pub fn select<'a>(
+        &self,
+        servers: &'a [ServerInfo],
+        health: &HashMap<String, ServerHealth>,
+    ) -> Vec<&'a ServerInfo> {
+        let members: Vec<&ServerInfo> = self
+            .servers
+            .iter()
+            .filter_map(|host| servers.iter().find(|x| x.host == *host))
+            .collect();
+        let healthy = |server: &&ServerInfo| health.get(&server.host).and_then(|x| x.healthy);
+        let mut ranked: Vec<&ServerInfo> = members
+            .iter()
+            .copied()
+            .filter(|x| healthy(x) != Some(false))
+            .collect();
+        if ranked.is_empty() {
+            return members.into_iter().take(1).collect();
+        }
+        // stable sorts, ties keep the configured order
+        match self.strategy {
+            GroupStrategy::UrlTest => {
+                let latency = |server: &&ServerInfo| {
+                    health
+                        .get(&server.host)
+                        .and_then(|x| x.latency)
+                        .unwrap_or(u64::MAX)
+                };
+                ranked.sort_by_key(|x| (healthy(x).is_none(), latency(x)));
+                ranked.truncate(1);
+            }
+            GroupStrategy::Fallback => {
+                ranked.sort_by_key(|x| healthy(x).is_none());
+                ranked.truncate(1);
+            }
+            GroupStrategy::LoadBalance => ranked.sort_by_key(|x| healthy(x).is_none()),
+        }
+        ranked
+    }
+}
+
+pub fn validate_groups(groups: &[ServerGroup], servers: &[ServerInfo]) -> Result<(), String> {
+    for (index, group) in groups.iter().enumerate() {
+        if group.name.is_empty() {
+            return Err("group name must not be empty".to_string());
+        }
+        if groups[..index].iter().any(|x| x.name == group.name) {
+            return Err(format!("group name `{}` is used twice", group.name));
+        }
+        if servers.iter().any(|x| x.host == group.name) {
+            return Err(format!(
+                "group name `{}` is already a server host",
+                group.name
+            ));
+        }
+        if group.servers.is_empty() {
+            return Err(format!("group `{}` has no servers", group.name));
+        }
+        if let Some(host) = group
+            .servers
+            .iter()
+            .find(|host| !servers.iter().any(|x| x.host == **host))
+        {
+            return Err(format!(
+                "group `{}` contains unknown server `{}`",
+                group.name, host
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The servers behind a server host or group name, best first.
+pub fn resolve_target(app: &AppHandle, name: &str) -> Vec<ServerInfo> {
+    let servers = store::get_servers(app).ok().flatten().unwrap_or_default();
+    if let Some(server) = servers.iter().find(|x| x.host == name) {
+        return vec![server.clone()];
+    }
+    let groups = store::get_server_groups(app).unwrap_or_default();
+    let Some(group) = groups.iter().find(|x| x.name == name) else {
+        return Vec::new();
+    };
+    let health = app.state::<Mutex<HealthState>>();
+    let health = health.lock().unwrap();
+    group
+        .select(&servers, health.get())
+        .into_iter()
+        .cloned()
+        .collect()
+}
+
+/// The servers behind `ACTIVE_SERVER`, which names a server or a group.
+pub fn active_servers(app: &AppHandle) -> Vec<ServerInfo> {
+    match store::get_value_by_key(app, store::ACTIVE_SERVER) {
+        Ok(Some(name)) => resolve_target(app, &name),
+        _ => Vec::new(),
+    }
+}
+
+/// How a load-balance group spreads connections, `None` when the active
+/// target is a server or a group of another strategy.
+pub fn active_balance(app: &AppHandle) -> Option<BalanceMode> {
+    let name = store::get_value_by_key(app, store::ACTIVE_SERVER).ok()??;
+    let groups = store::get_server_groups(app).unwrap_or_default();
+    groups
+        .into_iter()
+        .find(|x| x.name == name && x.strategy == GroupStrategy::LoadBalance)
+        .map(|x| x.balance)
+}
+
+/// Probe every grouped server, restarting the agent when the active group now
+/// picks a different server.
+pub async fn check_health(app: &AppHandle) {
+    let servers = store::get_servers(app).ok().flatten().unwrap_or_default();
+    let groups = store::get_server_groups(app).unwrap_or_default();
+    let protocol_mode = command::get_protocol_mode(app.clone());
+    let before = active_servers(app);
+
+    // an unreachable server takes the whole timeout, probe them side by side
+    let mut probes = JoinSet::new();
+    for server in servers
+        .into_iter()
+        .filter(|x| groups.iter().any(|group| group.servers.contains(&x.host)))
+    {
+        probes.spawn(async move {
+            let health = probe(&server, protocol_mode).await;
+            (server.host, health)
+        });
+    }
+    let mut results = HashMap::new();
+    while let Some(result) = probes.join_next().await {
+        match result {
+            Ok((host, health)) => {
+                results.insert(host, health);
+            }
+            Err(e) => eprintln!("probe server error: {:?}", e),
+        }
+    }
+    let snapshot = {
+        let health = app.state::<Mutex<HealthState>>();
+        let mut health = health.lock().unwrap();
+        health.set(results);
+        health.get().clone()
+    };
+    if let Err(e) = app.emit(GROUP_HEALTH_EVENT, &snapshot) {
+        eprintln!("emit {} error: {:?}", GROUP_HEALTH_EVENT, e);
+    }
+
+    let after = active_servers(app);
+    let hosts = |servers: &[ServerInfo]| servers.iter().map(|x| x.host.clone()).collect::<Vec<_>>();
+    let running = app.state::<Mutex<AgentState>>().lock().unwrap().get();
+    if hosts(&before) != hosts(&after) && running {
+        println!("active group now selects {:?}", hosts(&after));
+        command::apply_agent_config(app);
+    }
+}
+
+pub fn spawn_health_checker(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            check_health(&app).await;
+            let groups = store::get_server_groups(&app).unwrap_or_default();
+            let interval = groups
+                .iter()
+                .map(|x| x.interval)
+                .min()
+                .unwrap_or(default_interval())
+                .max(MIN_CHECK_INTERVAL);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+/// Probe a server over the protocol the agent would reach it with: QUIC
+/// unless the protocol mode is TCP. In auto mode a server QUIC gets no answer
+/// from is tried over TCP, which the agent falls back to.
+pub async fn probe(server: &ServerInfo, protocol_mode: ProtocolMode) -> ServerHealth {
+    if protocol_mode == ProtocolMode::Tcp {
+        return probe_tcp(server).await;
+    }
+    let health = probe_quic(server).await;
+    if health.healthy == Some(true)
+        || protocol_mode == ProtocolMode::Quic
+        || server.tcp_port.is_none()
+    {
+        return health;
+    }
+    probe_tcp(server).await
+}
+
+async fn probe_quic(server: &ServerInfo) -> ServerHealth {
+    let mut health = ServerHealth {
+        checked_at: now_secs(),
+        ..Default::default()
+    };
+    match protocol::quic_round_trip(server).await {
+        Ok(Some(round_trip)) => {
+            health.healthy = Some(true);
+            health.latency = Some(round_trip.as_millis() as u64);
+        }
+        Ok(None) => {
+            health.healthy = Some(false);
+            health.error = Some("no answer over quic".to_string());
+        }
+        Err(e) => {
+            health.healthy = Some(false);
+            health.error = Some(e.to_string());
+        }
+    }
+    health
+}
+
+// latency is the TCP handshake time, servers without a tcp port can't be probed
+pub async fn probe_tcp(server: &ServerInfo) -> ServerHealth {
+    let mut health = ServerHealth {
+        checked_at: now_secs(),
+        ..Default::default()
+    };
+    let Some(tcp_port) = server.tcp_port else {
+        health.error = Some("no tcp port to probe".to_string());
+        return health;
+    };
+    let start = Instant::now();
+    let connect = TcpStream::connect((server.host.as_str(), tcp_port));
+    match tokio::time::timeout(PROBE_TIMEOUT, connect).await {
+        Ok(Ok(_)) => {
+            health.healthy = Some(true);
+            health.latency = Some(start.elapsed().as_millis() as u64);
+        }
+        Ok(Err(e)) => {
+            health.healthy = Some(false);
+            health.error = Some(e.to_string());
+        }
+        Err(_) => {
+            health.healthy = Some(false);
+            health.error = Some("timed out".to_string());
+        }
+    }
+    health
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::TransportOptions;
+
+    fn server(host: &str) -> ServerInfo {
+        ServerInfo {
+            host: host.to_string(),
+            quic_port: 4433,
+            tcp_port: Some(443),
+            cert: String::new(),
+            cert_key: String::new(),
+            alias: None,
+            transport: TransportOptions::default(),
+        }
+    }
+
+    fn health(healthy: Option<bool>, latency: Option<u64>) -> ServerHealth {
+        ServerHealth {
+            healthy,
+            latency,
+            ..Default::default()
+        }
+    }
+
+    fn select(strategy: GroupStrategy, health: &HashMap<String, ServerHealth>) -> Vec<String> {
+        let servers = [server("a"), server("b"), server("c"), server("d")];
+        let group = ServerGroup {
+            name: "group".to_string(),
+            strategy,
+            balance: BalanceMode::default(),
+            servers: vec!["a".into(), "b".into(), "c".into(), "d".into()],
+            interval: default_interval(),
+        };
+        group
+            .select(&servers, health)
+            .into_iter()
+            .map(|x| x.host.clone())
+            .collect()
+    }
+
+    #[test]
+    fn unknown_members_rank_below_healthy_ones() {
+        // a can't be probed, b is down, c and d answered
+        let health = HashMap::from([
+            ("a".to_string(), health(None, None)),
+            ("b".to_string(), health(Some(false), None)),
+            ("c".to_string(), health(Some(true), Some(80))),
+            ("d".to_string(), health(Some(true), Some(20))),
+        ]);
+        assert_eq!(select(GroupStrategy::UrlTest, &health), ["d"]);
+        assert_eq!(select(GroupStrategy::Fallback, &health), ["c"]);
+        assert_eq!(select(GroupStrategy::LoadBalance, &health), ["c", "d", "a"]);
+    }
+
+    #[test]
+    fn unknown_members_are_used_when_nothing_is_healthy() {
+        let health = HashMap::from([
+            ("a".to_string(), health(Some(false), None)),
+            ("c".to_string(), health(None, None)),
+        ]);
+        // b and d were never checked
+        assert_eq!(select(GroupStrategy::UrlTest, &health), ["b"]);
+        assert_eq!(select(GroupStrategy::Fallback, &health), ["b"]);
+        assert_eq!(select(GroupStrategy::LoadBalance, &health), ["b", "c", "d"]);
+    }
+
+    #[test]
+    fn first_member_when_everything_is_down() {
+        let health = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|x| (x.to_string(), health(Some(false), None)))
+            .collect();
+        assert_eq!(select(GroupStrategy::UrlTest, &health), ["a"]);
+        assert_eq!(select(GroupStrategy::LoadBalance, &health), ["a"]);
+    }
+}
@@ -0,0 +1,202 @@
+//! Bounded version history of the rule lists and the server list.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{Error, anyhow};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use tauri::AppHandle;
+use tauri_plugin_store::resolve_store_path;
+
+use crate::{
+    policy::POLICIES_DIR,
+    rules::{refresh::now_secs, source::RULE_SOURCES_DIR},
+    store::{self, BLOCK_RULES_PATH, CUSTOM_PROXY_RULES_PATH, DIRECT_RULES_PATH},
+};
+
+pub const HISTORY_DIR: &str = "history";
+/// Subject of the `SERVERS` array, every other subject is a store-relative
+/// rule file path.
+pub const SERVERS_SUBJECT: &str = "servers";
+const INDEX_PATH: &str = "index.json";
+/// Versions kept per subject, the oldest are dropped first.
+const MAX_VERSIONS: usize = 30;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HistorySource {
+    /// The content found when the history of a subject started.
+    Initial,
+    Ui,
+    Import,
+    CommunityRefresh,
+    /// Edited outside secc.
+    External,
+    Restore,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryVersion {
+    pub id: u64,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    pub source: HistorySource,
+    pub size: usize,
+}
+
+/// Record the content a subject is about to get. Call it before writing, so
+/// the content it replaces can be kept when the history is still empty.
+/// Unchanged content is not recorded twice.
+pub fn record(app: &AppHandle, subject: &str, content: &str, source: HistorySource) {
+    if let Err(e) = try_record(app, subject, content, source) {
+        eprintln!("record history of {} error: {:?}", subject, e);
+    }
+}
+
+fn try_record(
+    app: &AppHandle,
+    subject: &str,
+    content: &str,
+    source: HistorySource,
+) -> Result<(), Error> {
+    let dir = subject_dir(app, subject)?;
+    fs::create_dir_all(&dir)?;
+    let mut versions = read_index(app, subject)?;
+    if versions.is_empty() {
+        let previous = current(app, subject).unwrap_or_default();
+        if !previous.trim().is_empty() && previous != content {
+            push_version(
+                app,
+                subject,
+                &mut versions,
+                &previous,
+                HistorySource::Initial,
+            )?;
+        }
+    }
+    if let Some(last) = versions.last()
+        && read_version(app, subject, last.id).ok().as_deref() == Some(content)
+    {
+        return Ok(());
+    }
+    push_version(app, subject, &mut versions, content, source)?;
+    while versions.len() > MAX_VERSIONS {
+        let dropped = versions.remove(0);
+        let _ = fs::remove_file(dir.join(version_file(dropped.id)));
+    }
+    let index = serde_json::to_vec_pretty(&versions)?;
+    store::write_atomic(&dir.join(INDEX_PATH), &index)
+}
+
+fn push_version(
+    app: &AppHandle,
+    subject: &str,
+    versions: &mut Vec<HistoryVersion>,
+    content: &str,
+    source: HistorySource,
+) -> Result<(), Error> {
+    let timestamp = now_secs();
+    // two writes within a millisecond still get their own version
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_millis() as u64)
+        .unwrap_or_default();
+    let id = versions.last().map_or(millis, |x| millis.max(x.id + 1));
+    let path = subject_dir(app, subject)?.join(version_file(id));
+    store::write_atomic(&path, content.as_bytes())?;
+    versions.push(HistoryVersion {
+        id,
+        timestamp,
+        source,
+        size: content.len(),
+    });
+    Ok(())
+}
+
+/// Versions of a subject, newest first.
+pub fn list_versions(app: &AppHandle, subject: &str) -> Result<Vec<HistoryVersion>, Error> {
+    let mut versions = read_index(app, subject)?;
+    versions.reverse();
+    Ok(versions)
+}
+
+pub fn read_version(app: &AppHandle, subject: &str, id: u64) -> Result<String, Error> {
+    let path = subject_dir(app, subject)?.join(version_file(id));
+    fs::read_to_string(&path).map_err(|e| anyhow!("version {} of {}: {}", id, subject, e))
+}
+
+/// Unified diff from version `from` to version `to`, or to the current
+/// content when `to` is `None`.
+pub fn diff(app: &AppHandle, subject: &str, from: u64, to: Option<u64>) -> Result<String, Error> {
+    let old = read_version(app, subject, from)?;
+    let (new, to_name) = match to {
+        Some(to) => (read_version(app, subject, to)?, to.to_string()),
+        None => (current(app, subject)?, "current".to_string()),
+    };
+    let diff = TextDiff::from_lines(&old, &new);
+    Ok(diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&from.to_string(), &to_name)
+        .to_string())
+}
+
+/// Drop every version of a subject whose file is gone.
+pub fn forget(app: &AppHandle, subject: &str) -> Result<(), Error> {
+    let dir = subject_dir(app, subject)?;
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// The content a subject has right now.
+pub fn current(app: &AppHandle, subject: &str) -> Result<String, Error> {
+    validate_subject(subject)?;
+    if subject == SERVERS_SUBJECT {
+        let servers = store::get_servers(app)?.unwrap_or_default();
+        return Ok(serde_json::to_string_pretty(&servers)?);
+    }
+    store::get_rules(app, subject)
+}
+
+pub fn validate_subject(subject: &str) -> Result<(), Error> {
+    let file_in = |dir: &str| {
+        subject
+            .strip_prefix(dir)
+            .and_then(|x| x.strip_prefix('/'))
+            .and_then(|x| x.strip_suffix(".txt"))
+            .is_some_and(|name| {
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            })
+    };
+    let valid = subject == SERVERS_SUBJECT
+        || [CUSTOM_PROXY_RULES_PATH, DIRECT_RULES_PATH, BLOCK_RULES_PATH].contains(&subject)
+        || file_in(POLICIES_DIR)
+        || file_in(RULE_SOURCES_DIR);
+    match valid {
+        true => Ok(()),
+        false => Err(anyhow!("`{}` has no history", subject)),
+    }
+}
+
+fn read_index(app: &AppHandle, subject: &str) -> Result<Vec<HistoryVersion>, Error> {
+    let path = subject_dir(app, subject)?.join(INDEX_PATH);
+    match fs::read(&path) {
+        Ok(index) => Ok(serde_json::from_slice(&index)?),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+fn subject_dir(app: &AppHandle, subject: &str) -> Result<PathBuf, Error> {
+    validate_subject(subject)?;
+    let dir = format!("{}/{}", HISTORY_DIR, subject.replace('/', "__"));
+    Ok(resolve_store_path(app, dir)?)
+}
+
+fn version_file(id: u64) -> String {
+    format!("{}.snap", id)
+}
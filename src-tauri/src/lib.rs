@@ -1,14 +1,31 @@
 use std::sync::Mutex;
 
 use anyhow::Error;
-use state::{AgentState, SidecarState};
+use state::{
+    AgentConfigState, AgentInfoState, AgentState, ConnectionState, HealthState, NetworkState,
+    ProtocolState, ReconnectState, RuleRefreshLock, SidecarChild, SidecarState, WrittenFiles,
+};
 use tauri::{App, Manager};
+mod agent;
+mod bundle;
 mod command;
+mod connection;
+mod group;
+mod history;
+mod migrate;
+mod network;
+mod policy;
+mod profile;
+mod protocol;
+mod reconnect;
+mod recovery;
+mod rules;
 mod server;
 mod shell;
 mod state;
 mod store;
 mod tray;
+mod watch;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -18,16 +35,32 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .manage(Mutex::new(SidecarState::default()))
         .manage(Mutex::new(AgentState::default()))
+        .manage(RuleRefreshLock::default())
+        .manage(Mutex::new(HealthState::default()))
+        .manage(Mutex::new(ProtocolState::default()))
+        .manage(Mutex::new(AgentConfigState::default()))
+        .manage(Mutex::new(AgentInfoState::default()))
+        .manage(Mutex::new(SidecarChild::default()))
+        .manage(Mutex::new(WrittenFiles::default()))
+        .manage(Mutex::new(NetworkState::default()))
+        .manage(Mutex::new(ReconnectState::default()))
+        .manage(Mutex::new(ConnectionState::default()))
         .invoke_handler(tauri::generate_handler![
             command::close_app,
             command::set_listen_config,
             command::get_listen_config,
             command::set_direct_rules,
             command::get_direct_rules,
+            command::lint_direct_rules,
             command::set_custom_proxy_rules,
             command::get_custom_proxy_rules,
+            command::lint_custom_proxy_rules,
+            command::set_block_rules,
+            command::get_block_rules,
+            command::lint_block_rules,
             command::set_cert,
             command::get_cert,
             command::set_cert_key,
@@ -41,6 +74,38 @@ pub fn run() {
             command::switch_protocol_mode,
             command::get_protocol_mode,
             command::get_combined_proxy_rules,
+            command::get_combined_direct_rules,
+            command::get_combined_block_rules,
+            command::get_rule_sources,
+            command::set_rule_sources,
+            command::refresh_rule_sources,
+            command::get_rule_sources_status,
+            command::get_diagnostics,
+            command::list_history,
+            command::diff_history,
+            command::restore_history,
+            command::export_config,
+            command::import_config,
+            command::import_rules,
+            command::export_rules,
+            command::get_policies,
+            command::set_policies,
+            command::get_policy_rules,
+            command::set_policy_rules,
+            command::lookup_route,
+            command::get_server_groups,
+            command::set_server_groups,
+            command::get_group_health,
+            command::check_group_health,
+            command::get_profiles,
+            command::set_profiles,
+            command::capture_profile,
+            command::get_active_profile,
+            command::switch_profile,
+            command::get_network_settings,
+            command::set_network_settings,
+            command::get_network_info,
+            command::get_network_decisions,
             command::get_servers,
             command::get_server,
             command::add_server,
@@ -53,8 +118,8 @@ pub fn run() {
             init_setup(app)?;
             Ok(())
         })
-        .on_window_event(|win, event| {
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+        .on_window_event(|win, event| match event {
+            tauri::WindowEvent::CloseRequested { api, .. } => {
                 #[cfg(not(target_os = "macos"))]
                 {
                     event.window().hide().unwrap();
@@ -65,6 +130,12 @@ pub fn run() {
                 }
                 api.prevent_close();
             }
+            tauri::WindowEvent::ThemeChanged(_) => {
+                if let Err(e) = tray::update_tray_icon(win.app_handle()) {
+                    eprintln!("update tray icon error: {:?}", e);
+                }
+            }
+            _ => {}
         })
         .build(tauri::generate_context!())
         .expect("error while running tauri application")
@@ -84,18 +155,48 @@ fn init_setup(app: &mut App) -> Result<(), Error> {
         app.set_activation_policy(tauri::ActivationPolicy::Accessory);
     }
     let app_handle = app.app_handle().clone();
-    if let Err(e) = store::init_all(&app_handle) {
-        eprintln!("initial config files error: {:?}", e);
+    // broken files are moved aside before the store loads them
+    let mut recovery_notes = recovery::recover_store(&app_handle);
+    match store::init_all(&app_handle) {
+        Ok(()) => {
+            if let Err(e) = recovery::backup_config(&app_handle) {
+                eprintln!("backup config error: {:?}", e);
+            }
+        }
+        Err(e) => {
+            eprintln!("initial config files error: {:?}", e);
+            recovery_notes.push(format!("Setting up the settings files failed: {}", e));
+        }
     }
+    recovery::show_notes(&app_handle, &recovery_notes);
     // add tray menu
     tray::build_tray(app.handle())?;
-    command::open_secc(app.handle().clone());
-
-    let app_handle = app.app_handle().clone();
     tauri::async_runtime::spawn(async move {
-        if let Err(e) = store::load_community_proxy_list(&app_handle).await {
-            eprintln!("initial config files error: {:?}", e);
+        // the network rules pick the profile first, secc is only opened when
+        // they didn't switch to one, which opened or closed it already
+        let outcome = network::start_watcher(&app_handle).await;
+        // querying the agent runs it, which shouldn't hold up the main thread
+        let opened = tauri::async_runtime::spawn_blocking(move || {
+            let agent_info = agent::agent_info(&app_handle);
+            println!(
+                "secc-agent {} {:?}",
+                agent_info.version, agent_info.capabilities
+            );
+            if outcome != Some(network::DecisionOutcome::Switched) {
+                command::open_secc(app_handle);
+            }
+        });
+        if let Err(e) = opened.await {
+            eprintln!("open secc error: {:?}", e);
         }
     });
+
+    rules::refresh::spawn_scheduler(app.handle());
+    group::spawn_health_checker(app.handle());
+    protocol::spawn_watcher(app.handle());
+    reconnect::spawn_resume_watcher(app.handle());
+    if let Err(e) = watch::spawn_watcher(app.handle()) {
+        eprintln!("watch store dir error: {:?}", e);
+    }
     Ok(())
 }
@@ -0,0 +1,246 @@
+//! Schema migrations of `config.json`.
+//!
+//! The store carries a `schema_version`, configs written before it existed
+//! are version 1. At startup the migrations between the stored and the
+//! current version run in order on the raw json, after a copy of the file was
+//! kept next to it.
+
+use anyhow::{Error, anyhow};
+use serde_json::{Map, Value, json};
+use tauri::AppHandle;
+use tauri_plugin_store::{StoreExt, resolve_store_path};
+
+use crate::{
+    rules::source::{RuleSource, RuleTarget},
+    server::TransportOptions,
+    store,
+};
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), Error>;
+
+/// The migration at index `i` moves a config from version `i + 1` to `i + 2`.
+const MIGRATIONS: [Migration; 2] = [rule_sources, server_transport];
+
+pub const CURRENT_SCHEMA_VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+/// Bring `config.json` to the current schema. A fresh store is stamped with
+/// the current version, a store written by a newer secc is left alone.
+pub fn migrate_store(app: &AppHandle) -> Result<(), Error> {
+    let store = app.store(store::CONFIG_PATH)?;
+    let mut config: Map<String, Value> = store.entries().into_iter().collect();
+    if config.is_empty() {
+        store.set(store::SCHEMA_VERSION, CURRENT_SCHEMA_VERSION);
+        return Ok(());
+    }
+    let version = schema_version(&config);
+    if version == CURRENT_SCHEMA_VERSION {
+        return Ok(());
+    }
+    if version > CURRENT_SCHEMA_VERSION {
+        eprintln!(
+            "{} has schema {}, newer than {}, leaving it as it is",
+            store::CONFIG_PATH,
+            version,
+            CURRENT_SCHEMA_VERSION
+        );
+        return Ok(());
+    }
+    backup(app, version)?;
+    migrate(&mut config)?;
+    println!(
+        "{} migrated from schema {} to {}",
+        store::CONFIG_PATH,
+        version,
+        CURRENT_SCHEMA_VERSION
+    );
+    store.clear();
+    for (key, value) in config {
+        store.set(key, value);
+    }
+    store.save()?;
+    Ok(())
+}
+
+/// Run the migrations a config is missing, returns the version it started at.
+pub fn migrate(config: &mut Map<String, Value>) -> Result<u64, Error> {
+    let version = schema_version(config);
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "{} has schema {}, this secc only knows up to {}",
+            store::CONFIG_PATH,
+            version,
+            CURRENT_SCHEMA_VERSION
+        ));
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate() {
+        let from = index as u64 + 1;
+        if version > from {
+            continue;
+        }
+        migration(config).map_err(|e| anyhow!("migrate schema {} error: {}", from, e))?;
+        config.insert(store::SCHEMA_VERSION.to_string(), json!(from + 1));
+    }
+    Ok(version)
+}
+
+pub fn schema_version(config: &Map<String, Value>) -> u64 {
+    config
+        .get(store::SCHEMA_VERSION)
+        .and_then(|x| x.as_u64())
+        .unwrap_or(1)
+}
+
+/// Keep a copy of `config.json` as `config.json.v<version>.bak`.
+fn backup(app: &AppHandle, version: u64) -> Result<(), Error> {
+    let path = resolve_store_path(app, store::CONFIG_PATH)?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let backup_path = resolve_store_path(app, format!("{}.v{}.bak", store::CONFIG_PATH, version))?;
+    std::fs::copy(path, backup_path)?;
+    Ok(())
+}
+
+/// 1 → 2: the single community `rules_url` became a list of rule sources.
+fn rule_sources(config: &mut Map<String, Value>) -> Result<(), Error> {
+    let rules_url = config.remove(store::COMMUNITY_RULES);
+    if config.contains_key(store::RULE_SOURCES) {
+        return Ok(());
+    }
+    let rules_url = rules_url
+        .as_ref()
+        .and_then(|x| x.as_str())
+        .unwrap_or(store::COMMUNITY_RULES_URL);
+    let community = RuleSource::new(store::COMMUNITY_SOURCE_ID, rules_url, RuleTarget::Proxy);
+    config.insert(store::RULE_SOURCES.to_string(), json!([community]));
+    Ok(())
+}
+
+/// 2 → 3: servers carry their transport options.
+fn server_transport(config: &mut Map<String, Value>) -> Result<(), Error> {
+    let Some(servers) = config.get_mut(store::SERVERS) else {
+        return Ok(());
+    };
+    let servers = servers
+        .as_array_mut()
+        .ok_or(anyhow!("`{}` must be a list", store::SERVERS))?;
+    for server in servers.iter_mut() {
+        let server = server
+            .as_object_mut()
+            .ok_or(anyhow!("servers must be objects"))?;
+        let transport = server.entry("transport").or_insert(Value::Null);
+        if transport.is_null() {
+            *transport = serde_json::to_value(TransportOptions::default())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        group::ServerGroup,
+        policy::Policy,
+        server::{AddrInfo, ServerInfo},
+        state::{AccessMode, BindMode, ProtocolMode},
+    };
+
+    fn fixture(content: &str) -> Map<String, Value> {
+        serde_json::from_str(content).unwrap()
+    }
+
+    fn get<T: serde::de::DeserializeOwned>(config: &Map<String, Value>, key: &str) -> T {
+        serde_json::from_value(config[key].clone()).unwrap()
+    }
+
+    /// Every key a current secc reads has to parse after migrating.
+    fn assert_current(config: &Map<String, Value>) {
+        assert_eq!(config[store::SCHEMA_VERSION], json!(CURRENT_SCHEMA_VERSION));
+        assert!(!config.contains_key(store::COMMUNITY_RULES));
+        let servers: Vec<ServerInfo> = get(config, store::SERVERS);
+        for server in servers {
+            server.transport.validate().unwrap();
+        }
+        let _: Vec<RuleSource> = get(config, store::RULE_SOURCES);
+        let _: AddrInfo = get(config, store::SOCKS_ADDR);
+        let _: AddrInfo = get(config, store::HTTP_ADDR);
+        let _: AccessMode = get(config, store::ACCESS_MODE);
+        let _: BindMode = get(config, store::BIND_MODE);
+        let _: ProtocolMode = get(config, store::PROTOCOL_MODE);
+        if config.contains_key(store::SERVER_GROUPS) {
+            let _: Vec<ServerGroup> = get(config, store::SERVER_GROUPS);
+        }
+        if config.contains_key(store::POLICIES) {
+            let _: Vec<Policy> = get(config, store::POLICIES);
+        }
+    }
+
+    #[test]
+    fn migrates_v1_with_rules_url() {
+        let mut config = fixture(include_str!("../tests/fixtures/config_v1.json"));
+        assert_eq!(migrate(&mut config).unwrap(), 1);
+        assert_current(&config);
+        let sources: Vec<RuleSource> = get(&config, store::RULE_SOURCES);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].id, store::COMMUNITY_SOURCE_ID);
+        assert_eq!(sources[0].url, "https://example.com/proxy-list.txt");
+        let servers: Vec<ServerInfo> = get(&config, store::SERVERS);
+        assert_eq!(servers[0].transport, TransportOptions::default());
+        assert_eq!(servers[0].tcp_port, None);
+    }
+
+    #[test]
+    fn migrates_v1_without_rules_url() {
+        let mut config = fixture(include_str!(
+            "../tests/fixtures/config_v1_no_rules_url.json"
+        ));
+        assert_eq!(migrate(&mut config).unwrap(), 1);
+        assert_current(&config);
+        let sources: Vec<RuleSource> = get(&config, store::RULE_SOURCES);
+        assert_eq!(sources[0].url, store::COMMUNITY_RULES_URL);
+    }
+
+    #[test]
+    fn migrates_unversioned_with_rule_sources() {
+        let mut config = fixture(include_str!(
+            "../tests/fixtures/config_v1_rule_sources.json"
+        ));
+        let sources_before = config[store::RULE_SOURCES].clone();
+        assert_eq!(migrate(&mut config).unwrap(), 1);
+        assert_current(&config);
+        // the sources configured back then are kept as they are
+        assert_eq!(config[store::RULE_SOURCES], sources_before);
+        let servers: Vec<ServerInfo> = get(&config, store::SERVERS);
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[1].transport.idle_timeout, 60);
+    }
+
+    #[test]
+    fn migrates_v2() {
+        let mut config = fixture(include_str!("../tests/fixtures/config_v2.json"));
+        assert_eq!(migrate(&mut config).unwrap(), 2);
+        assert_current(&config);
+    }
+
+    #[test]
+    fn keeps_current_config() {
+        let mut config = fixture(include_str!("../tests/fixtures/config_v3.json"));
+        let before = config.clone();
+        assert_eq!(migrate(&mut config).unwrap(), CURRENT_SCHEMA_VERSION);
+        assert_current(&config);
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn refuses_newer_schema() {
+        let mut config = fixture(include_str!("../tests/fixtures/config_v3.json"));
+        config.insert(
+            store::SCHEMA_VERSION.to_string(),
+            json!(CURRENT_SCHEMA_VERSION + 1),
+        );
+        let before = config.clone();
+        assert!(migrate(&mut config).is_err());
+        assert_eq!(config, before);
+    }
+}
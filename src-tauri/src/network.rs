@@ -0,0 +1,962 @@
+//! Network-aware profile switching.
+//!
+//! The current network is described by its SSID, the interface and gateway of
+//! the default route, the gateway's MAC and the DNS search domains. Whenever
+//! secc starts on or moves to another network, or another interface, the
+//! user's rules are evaluated in order and the profile of the first matching
+//! rule is switched to, or the fallback profile when none matches. On Linux changes are picked up from `ip monitor`, which
+//! listens on netlink; elsewhere, or when it isn't available, the network is
+//! polled. Changes that don't switch profiles still reconnect the agent.
+
+use std::{sync::Mutex, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::ShellExt;
+use tokio::sync::mpsc;
+
+use crate::{
+    profile::{self, Profile},
+    reconnect::{self, ReconnectReason},
+    rules::refresh::now_secs,
+    state::NetworkState,
+    store,
+};
+
+pub const NETWORK_DECISION_EVENT: &str = "network_decision";
+/// Poll interval without change notifications.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Poll interval next to change notifications, catches SSID changes that
+/// don't touch routes or addresses.
+const NOTIFIED_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Time a network gets to settle after a change notification, a reconnect
+/// sends a burst of them.
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+pub const MAX_DECISIONS: usize = 50;
+
+/// What secc knows about the network it is on. Fields that can't be found out
+/// are left empty.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NetworkInfo {
+    pub ssid: Option<String>,
+    pub interface: Option<String>,
+    pub gateway: Option<String>,
+    pub gateway_mac: Option<String>,
+    pub dns_suffixes: Vec<String>,
+}
+
+/// A rule matches when every condition it sets matches, a condition matches
+/// when any of its values does.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkRule {
+    pub name: String,
+    #[serde(default)]
+    pub ssids: Vec<String>,
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    #[serde(default)]
+    pub gateways: Vec<String>,
+    #[serde(default)]
+    pub gateway_macs: Vec<String>,
+    #[serde(default)]
+    pub dns_suffixes: Vec<String>,
+    /// Name of the profile switched to.
+    pub profile: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NetworkSettings {
+    pub enabled: bool,
+    #[serde(default)]
+    pub rules: Vec<NetworkRule>,
+    /// Profile for networks no rule matches, they are left alone without one.
+    #[serde(default)]
+    pub fallback_profile: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    Switched,
+    /// The profile is active and the configuration still is what it holds.
+    AlreadyActive,
+    /// No rule matched and there is no fallback profile.
+    NoMatch,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkDecision {
+    pub timestamp: u64,
+    pub network: NetworkInfo,
+    /// Name of the matching rule, none for the fallback.
+    pub rule: Option<String>,
+    pub profile: Option<String>,
+    pub outcome: DecisionOutcome,
+    pub error: Option<String>,
+}
+
+impl NetworkInfo {
+    /// What tells this network apart from others handing out the same
+    /// addresses: the gateway's MAC, else the SSID, else the gateway on its
+    /// interface. `None` without a default route.
+    pub fn id(&self) -> Option<String> {
+        if let Some(gateway_mac) = &self.gateway_mac {
+            return Some(format!("mac:{}", gateway_mac));
+        }
+        if let Some(ssid) = &self.ssid {
+            return Some(format!("ssid:{}", ssid));
+        }
+        match (&self.gateway, &self.interface) {
+            (Some(gateway), Some(interface)) => Some(format!("gateway:{}%{}", gateway, interface)),
+            _ => None,
+        }
+    }
+}
+
+impl NetworkRule {
+    pub fn matches(&self, info: &NetworkInfo) -> bool {
+        let any = |values: &[String], matches: &dyn Fn(&str) -> bool| {
+            values.is_empty() || values.iter().any(|x| matches(x))
+        };
+        any(&self.ssids, &|x| info.ssid.as_deref() == Some(x))
+            && any(&self.interfaces, &|x| info.interface.as_deref() == Some(x))
+            && any(&self.gateways, &|x| info.gateway.as_deref() == Some(x))
+            && any(&self.gateway_macs, &|x| {
+                info.gateway_mac.is_some() && normalize_mac(x) == info.gateway_mac
+            })
+            && any(&self.dns_suffixes, &|x| {
+                info.dns_suffixes
+                    .iter()
+                    .any(|suffix| dns_suffix_matches(suffix, x))
+            })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ssids.is_empty()
+            && self.interfaces.is_empty()
+            && self.gateways.is_empty()
+            && self.gateway_macs.is_empty()
+            && self.dns_suffixes.is_empty()
+    }
+}
+
+pub fn validate_settings(settings: &NetworkSettings, profiles: &[Profile]) -> Result<(), String> {
+    let known = |name: &str| profiles.iter().any(|x| x.name == name);
+    for (index, rule) in settings.rules.iter().enumerate() {
+        if rule.name.trim().is_empty() {
+            return Err("network rule name must not be empty".to_string());
+        }
+        if settings.rules[..index].iter().any(|x| x.name == rule.name) {
+            return Err(format!("network rule name `{}` is used twice", rule.name));
+        }
+        if rule.is_empty() {
+            return Err(format!("network rule `{}` has no conditions", rule.name));
+        }
+        if let Some(mac) = rule
+            .gateway_macs
+            .iter()
+            .find(|x| normalize_mac(x).is_none())
+        {
+            return Err(format!(
+                "network rule `{}` has an invalid gateway mac `{}`",
+                rule.name, mac
+            ));
+        }
+        if !known(&rule.profile) {
+            return Err(format!(
+                "network rule `{}` uses unknown profile `{}`",
+                rule.name, rule.profile
+            ));
+        }
+    }
+    if let Some(fallback) = &settings.fallback_profile
+        && !known(fallback)
+    {
+        return Err(format!("fallback profile `{}` is unknown", fallback));
+    }
+    Ok(())
+}
+
+/// Apply the rules to the network secc starts on, then watch for changes.
+/// Returns the outcome of that first evaluation, which runs before the agent
+/// is started so a profile switch doesn't race it.
+pub async fn start_watcher(app: &AppHandle) -> Option<DecisionOutcome> {
+    let info = detect(app).await;
+    println!("network is now {:?}", info);
+    set_current(app, &info);
+    let outcome = apply_rules(app, &info);
+    spawn_watcher(app, info);
+    outcome
+}
+
+fn spawn_watcher(app: &AppHandle, mut last: NetworkInfo) {
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    #[cfg(target_os = "linux")]
+    monitor_netlink(app, tx);
+    // no change notifications, rx closes and the network is polled
+    #[cfg(not(target_os = "linux"))]
+    drop(tx);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = match rx.is_closed() {
+                true => POLL_INTERVAL,
+                false => NOTIFIED_POLL_INTERVAL,
+            };
+            tokio::select! {
+                Some(()) = rx.recv() => {
+                    tokio::time::sleep(SETTLE_DELAY).await;
+                    while rx.try_recv().is_ok() {}
+                }
+                _ = tokio::time::sleep(interval) => {}
+            }
+            let info = detect(&app).await;
+            set_current(&app, &info);
+            if !is_change(&last, &info) {
+                last = info;
+                continue;
+            }
+            println!("network is now {:?}", info);
+            let outcome = apply_rules(&app, &info);
+            // a profile switch already applied everything afresh, and
+            // without a default route there is nothing to reconnect to
+            if outcome != Some(DecisionOutcome::Switched) && info.interface.is_some() {
+                reconnect::reconnect(&app, ReconnectReason::NetworkChanged).await;
+            }
+            last = info;
+        }
+    });
+}
+
+/// Whether the network is another one, or reached over another interface.
+/// Details that trickle in later, like the DNS search domains, aren't a
+/// change.
+fn is_change(last: &NetworkInfo, info: &NetworkInfo) -> bool {
+    last.id() != info.id() || last.interface != info.interface
+}
+
+fn set_current(app: &AppHandle, info: &NetworkInfo) {
+    let network_state = app.state::<Mutex<NetworkState>>();
+    let mut network_state = network_state.lock().unwrap();
+    network_state.set_current(info.clone());
+}
+
+/// Evaluate the rules against the current network right away, after the
+/// rules were edited.
+pub fn spawn_evaluation(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let info = detect(&app).await;
+        apply_rules(&app, &info);
+    });
+}
+
+/// Forward the changes `ip monitor` prints, it stops sending when the
+/// command is missing or exits.
+#[cfg(target_os = "linux")]
+fn monitor_netlink(app: &AppHandle, tx: mpsc::UnboundedSender<()>) {
+    use tauri_plugin_shell::process::CommandEvent;
+
+    let command = app
+        .shell()
+        .command("ip")
+        .args(["monitor", "link", "address", "route"]);
+    let (mut events, child) = match command.spawn() {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            eprintln!("ip monitor error, polling the network: {:?}", e);
+            return;
+        }
+    };
+    tauri::async_runtime::spawn(async move {
+        // dropping the child doesn't stop it, it is kept for the kill
+        let child = child;
+        while let Some(event) = events.recv().await {
+            match event {
+                CommandEvent::Stdout(_) if tx.send(()).is_err() => break,
+                CommandEvent::Terminated(_) | CommandEvent::Error(_) => break,
+                _ => {}
+            }
+        }
+        let _ = child.kill();
+        eprintln!("ip monitor ended, polling the network");
+    });
+}
+
+/// Switch to the profile the rules pick for a network, `None` when automatic
+/// switching is off.
+fn apply_rules(app: &AppHandle, info: &NetworkInfo) -> Option<DecisionOutcome> {
+    let settings = store::get_network_settings(app).unwrap_or_default();
+    if !settings.enabled {
+        return None;
+    }
+    let rule = settings.rules.iter().find(|x| x.matches(info));
+    let profile = match rule {
+        Some(rule) => Some(rule.profile.clone()),
+        None => settings.fallback_profile.clone(),
+    };
+    let (outcome, error) = match &profile {
+        Some(profile) => match switch(app, profile) {
+            Ok(true) => (DecisionOutcome::Switched, None),
+            Ok(false) => (DecisionOutcome::AlreadyActive, None),
+            Err(e) => (DecisionOutcome::Failed, Some(e.to_string())),
+        },
+        None => (DecisionOutcome::NoMatch, None),
+    };
+    let decision = NetworkDecision {
+        timestamp: now_secs(),
+        network: info.clone(),
+        rule: rule.map(|x| x.name.clone()),
+        profile,
+        outcome,
+        error,
+    };
+    println!(
+        "network decision: rule {:?}, profile {:?}, {:?}{}",
+        decision.rule,
+        decision.profile,
+        decision.outcome,
+        decision
+            .error
+            .as_ref()
+            .map(|e| format!(": {}", e))
+            .unwrap_or_default()
+    );
+    {
+        let network_state = app.state::<Mutex<NetworkState>>();
+        let mut network_state = network_state.lock().unwrap();
+        network_state.push(decision.clone());
+    }
+    app.emit(NETWORK_DECISION_EVENT, &decision).unwrap();
+    Some(outcome)
+}
+
+/// Switch to a profile unless it is active and unchanged, `false` when
+/// nothing had to be done.
+fn switch(app: &AppHandle, name: &str) -> Result<bool, anyhow::Error> {
+    let active = store::get_value_by_key(app, store::ACTIVE_PROFILE)?;
+    if active.as_deref() == Some(name) {
+        let profiles = store::get_profiles(app)?;
+        let stored = profiles.iter().find(|x| x.name == name);
+        let current = profile::capture(app, name)?;
+        if let Some(stored) = stored
+            && serde_json::to_value(stored)? == serde_json::to_value(&current)?
+        {
+            return Ok(false);
+        }
+    }
+    profile::switch(app, name)?;
+    Ok(true)
+}
+
+/// The latest decisions, newest first.
+pub fn decisions(app: &AppHandle) -> Vec<NetworkDecision> {
+    let network_state = app.state::<Mutex<NetworkState>>();
+    let network_state = network_state.lock().unwrap();
+    network_state.get().iter().cloned().collect()
+}
+
+/// The network as last detected by the watcher, `None` before the first
+/// detection.
+pub fn current(app: &AppHandle) -> Option<NetworkInfo> {
+    let network_state = app.state::<Mutex<NetworkState>>();
+    let network_state = network_state.lock().unwrap();
+    network_state.current().cloned()
+}
+
+/// Lowercase `aa:bb:cc:dd:ee:ff`, from any of the separators and paddings the
+/// platforms print.
+fn normalize_mac(mac: &str) -> Option<String> {
+    let octets: Vec<u8> = mac
+        .trim()
+        .split([':', '-'])
+        .map(|x| u8::from_str_radix(x, 16).ok())
+        .collect::<Option<_>>()?;
+    if octets.len() != 6 || octets.iter().all(|x| *x == 0) {
+        return None;
+    }
+    let octets: Vec<String> = octets.iter().map(|x| format!("{:02x}", x)).collect();
+    Some(octets.join(":"))
+}
+
+/// `corp.example.com` matches the suffixes `corp.example.com` and
+/// `example.com`, not `ample.com`.
+fn dns_suffix_matches(domain: &str, suffix: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let suffix = suffix.trim().trim_matches('.').to_lowercase();
+    !suffix.is_empty() && (domain == suffix || domain.ends_with(&format!(".{}", suffix)))
+}
+
+async fn run(app: &AppHandle, program: &str, args: &[&str]) -> Option<String> {
+    let output = app
+        .shell()
+        .command(program)
+        .args(args)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Value after the first `<key> :` or `<key>:` line.
+fn field(output: &str, key: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        let value = value.trim();
+        (name.trim() == key && !value.is_empty()).then(|| value.to_string())
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub async fn detect(app: &AppHandle) -> NetworkInfo {
+    let mut info = NetworkInfo::default();
+    let routes = std::fs::read_to_string("/proc/net/route").unwrap_or_default();
+    if let Some((interface, gateway)) = parse_proc_route(&routes) {
+        info.interface = Some(interface);
+        info.gateway = gateway;
+    }
+    if let Some(gateway) = &info.gateway {
+        let arp = std::fs::read_to_string("/proc/net/arp").unwrap_or_default();
+        info.gateway_mac = parse_proc_arp(&arp, gateway);
+    }
+    if let Some(interface) = &info.interface
+        && std::path::Path::new(&format!("/sys/class/net/{}/wireless", interface)).exists()
+    {
+        info.ssid = match run(app, "iwgetid", &["-r", interface]).await {
+            Some(ssid) if !ssid.trim().is_empty() => Some(ssid.trim().to_string()),
+            _ => run(app, "iw", &["dev", interface, "link"])
+                .await
+                .and_then(|x| field(&x, "SSID")),
+        };
+    }
+    let resolv = std::fs::read_to_string("/etc/resolv.conf").unwrap_or_default();
+    info.dns_suffixes = parse_resolv_conf(&resolv);
+    info
+}
+
+#[cfg(target_os = "macos")]
+pub async fn detect(app: &AppHandle) -> NetworkInfo {
+    let mut info = NetworkInfo::default();
+    if let Some(route) = run(app, "route", &["-n", "get", "default"]).await {
+        info.interface = field(&route, "interface");
+        info.gateway = field(&route, "gateway");
+    }
+    if let Some(gateway) = &info.gateway {
+        let arp = run(app, "arp", &["-n", gateway]).await.unwrap_or_default();
+        info.gateway_mac = parse_bsd_arp(&arp);
+    }
+    if let Some(interface) = &info.interface {
+        info.ssid = match run(app, "ipconfig", &["getsummary", interface]).await {
+            Some(summary) if field(&summary, "SSID").is_some() => field(&summary, "SSID"),
+            _ => run(app, "networksetup", &["-getairportnetwork", interface])
+                .await
+                .and_then(|x| field(&x, "Current Wi-Fi Network")),
+        };
+    }
+    let dns = run(app, "scutil", &["--dns"]).await.unwrap_or_default();
+    info.dns_suffixes = parse_scutil_dns(&dns);
+    info
+}
+
+#[cfg(target_os = "windows")]
+pub async fn detect(app: &AppHandle) -> NetworkInfo {
+    let mut info = NetworkInfo::default();
+    let wlan = run(app, "netsh", &["wlan", "show", "interfaces"]).await;
+    info.ssid = wlan.and_then(|x| field(&x, "SSID"));
+    let routes = run(app, "route", &["print", "-4", "0.0.0.0"])
+        .await
+        .unwrap_or_default();
+    if let Some((gateway, interface)) = parse_route_print(&routes) {
+        info.gateway = Some(gateway);
+        info.interface = Some(interface);
+    }
+    if let Some(gateway) = &info.gateway {
+        let arp = run(app, "arp", &["-a", gateway]).await.unwrap_or_default();
+        info.gateway_mac = parse_windows_arp(&arp, gateway);
+    }
+    let ipconfig = run(app, "ipconfig", &["/all"]).await.unwrap_or_default();
+    info.dns_suffixes = parse_ipconfig_suffixes(&ipconfig);
+    info
+}
+
+/// Interface and gateway of the default route with the lowest metric in
+/// `/proc/net/route`: Iface Destination Gateway Flags RefCnt Use Metric ...,
+/// addresses are little endian hex.
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_route(routes: &str) -> Option<(String, Option<String>)> {
+    let route = routes
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|x| x.len() > 6 && x[1] == "00000000")
+        .min_by_key(|x| x[6].parse::<u32>().unwrap_or(u32::MAX))?;
+    let gateway = u32::from_str_radix(route[2], 16)
+        .ok()
+        .filter(|x| *x != 0)
+        .map(|x| std::net::Ipv4Addr::from(x.to_le_bytes()).to_string());
+    Some((route[0].to_string(), gateway))
+}
+
+/// MAC of `ip` in `/proc/net/arp`: IP address, HW type, Flags, HW address,
+/// Mask, Device. Incomplete entries show an all-zero address.
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_arp(arp: &str, ip: &str) -> Option<String> {
+    arp.lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|x| x.len() > 3 && x[0] == ip)
+        .and_then(|x| normalize_mac(x[3]))
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn parse_resolv_conf(resolv: &str) -> Vec<String> {
+    let mut suffixes = Vec::new();
+    for line in resolv.lines() {
+        let mut words = line.split_whitespace();
+        if let Some("search" | "domain") = words.next() {
+            push_suffixes(&mut suffixes, words);
+        }
+    }
+    suffixes
+}
+
+/// `? (192.168.1.1) at a4:2b:b0:1:2:3 on en0 ifscope [ethernet]`
+#[cfg(any(target_os = "macos", test))]
+fn parse_bsd_arp(arp: &str) -> Option<String> {
+    arp.split_whitespace()
+        .skip_while(|x| *x != "at")
+        .nth(1)
+        .and_then(normalize_mac)
+}
+
+/// The `search domain[n] : ...` lines of every resolver.
+#[cfg(any(target_os = "macos", test))]
+fn parse_scutil_dns(dns: &str) -> Vec<String> {
+    let mut suffixes = Vec::new();
+    for line in dns.lines() {
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().starts_with("search domain")
+        {
+            push_suffixes(&mut suffixes, value.split_whitespace());
+        }
+    }
+    suffixes
+}
+
+/// Gateway and interface address of the default route with the lowest
+/// metric: Network Destination, Netmask, Gateway, Interface, Metric.
+#[cfg(any(target_os = "windows", test))]
+fn parse_route_print(routes: &str) -> Option<(String, String)> {
+    routes
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|x| x.len() == 5 && x[0] == "0.0.0.0" && x[1] == "0.0.0.0")
+        .min_by_key(|x| x[4].parse::<u32>().unwrap_or(u32::MAX))
+        .map(|x| (x[2].to_string(), x[3].to_string()))
+}
+
+/// MAC of `ip` in the `Internet Address  Physical Address  Type` table.
+#[cfg(any(target_os = "windows", test))]
+fn parse_windows_arp(arp: &str, ip: &str) -> Option<String> {
+    arp.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|x| x.len() > 1 && x[0] == ip)
+        .and_then(|x| normalize_mac(x[1]))
+}
+
+/// The DNS suffixes of `ipconfig /all`. The search list puts every further
+/// suffix on a line of its own, without a name.
+#[cfg(any(target_os = "windows", test))]
+fn parse_ipconfig_suffixes(ipconfig: &str) -> Vec<String> {
+    let mut suffixes = Vec::new();
+    let mut in_list = false;
+    for line in ipconfig.lines() {
+        match line.split_once(':') {
+            Some((name, value)) => {
+                let name = name.to_lowercase();
+                in_list = name.contains("dns suffix search list");
+                if name.contains("dns suffix") {
+                    push_suffixes(&mut suffixes, value.split_whitespace());
+                }
+            }
+            None if in_list && line.starts_with(' ') && !line.trim().is_empty() => {
+                push_suffixes(&mut suffixes, line.split_whitespace());
+            }
+            None => in_list = false,
+        }
+    }
+    suffixes
+}
+
+fn push_suffixes<'a>(suffixes: &mut Vec<String>, values: impl Iterator<Item = &'a str>) {
+    for value in values {
+        let value = value.trim_end_matches('.').to_lowercase();
+        if !value.is_empty() && !suffixes.contains(&value) {
+            suffixes.push(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> NetworkInfo {
+        NetworkInfo {
+            ssid: Some("Office".to_string()),
+            interface: Some("en0".to_string()),
+            gateway: Some("10.0.0.1".to_string()),
+            gateway_mac: Some("a4:2b:b0:01:02:03".to_string()),
+            dns_suffixes: vec!["corp.example.com".to_string()],
+        }
+    }
+
+    #[test]
+    fn only_another_network_or_interface_is_a_change() {
+        let mut later = info();
+        later.dns_suffixes.push("example.com".to_string());
+        assert!(!is_change(&info(), &later));
+        let mut wired = info();
+        wired.interface = Some("en1".to_string());
+        assert!(is_change(&info(), &wired));
+        let mut other = info();
+        other.gateway_mac = Some("a4:2b:b0:09:09:09".to_string());
+        assert!(is_change(&info(), &other));
+        assert!(is_change(&info(), &NetworkInfo::default()));
+    }
+
+    fn rule(name: &str, profile: &str) -> NetworkRule {
+        NetworkRule {
+            name: name.to_string(),
+            ssids: Vec::new(),
+            interfaces: Vec::new(),
+            gateways: Vec::new(),
+            gateway_macs: Vec::new(),
+            dns_suffixes: Vec::new(),
+            profile: profile.to_string(),
+        }
+    }
+
+    fn profile(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            enabled: true,
+            active_server: None,
+            access_mode: crate::state::AccessMode::Auto,
+            bind_mode: crate::state::BindMode::Socks,
+            protocol_mode: crate::state::ProtocolMode::Auto,
+            socks: crate::server::AddrInfo::new("127.0.0.1".to_string(), 1080),
+            http: crate::server::AddrInfo::new("127.0.0.1".to_string(), 1081),
+            rule_sources: Vec::new(),
+            policies: Vec::new(),
+        }
+    }
+
+    type Condition = fn(&mut NetworkRule);
+
+    #[test]
+    fn rules_match_when_every_set_condition_does() {
+        let strings = |values: &[&str]| values.iter().map(|x| x.to_string()).collect();
+        let cases: [(Condition, bool); 8] = [
+            (|x| x.ssids = vec!["Home".into(), "Office".into()], true),
+            (|x| x.ssids = vec!["office".into()], false),
+            (|x| x.gateway_macs = vec!["A4-2B-B0-1-2-3".into()], true),
+            (|x| x.gateway_macs = vec!["a4:2b:b0:01:02:04".into()], false),
+            (|x| x.dns_suffixes = vec!["example.com".into()], true),
+            (|x| x.dns_suffixes = vec!["ample.com".into()], false),
+            (
+                |x| {
+                    x.interfaces = vec!["en0".into()];
+                    x.gateways = vec!["10.0.0.1".into()];
+                },
+                true,
+            ),
+            (
+                |x| {
+                    x.interfaces = vec!["en0".into()];
+                    x.gateways = vec!["10.0.0.254".into()];
+                },
+                false,
+            ),
+        ];
+        for (index, (set, expected)) in cases.into_iter().enumerate() {
+            let mut network_rule = rule("rule", "profile");
+            set(&mut network_rule);
+            assert_eq!(network_rule.matches(&info()), expected, "case {}", index);
+        }
+        // a condition on a field that couldn't be found out never matches
+        let mut network_rule = rule("rule", "profile");
+        network_rule.ssids = strings(&["Office"]);
+        let wired = NetworkInfo {
+            ssid: None,
+            ..info()
+        };
+        assert!(!network_rule.matches(&wired));
+        network_rule.ssids.clear();
+        network_rule.gateway_macs = strings(&["a4:2b:b0:01:02:03"]);
+        let no_mac = NetworkInfo {
+            gateway_mac: None,
+            ..info()
+        };
+        assert!(!network_rule.matches(&no_mac));
+    }
+
+    #[test]
+    fn settings_are_validated() {
+        let profiles = [profile("Work"), profile("Home")];
+        let settings = |rules: Vec<NetworkRule>, fallback: Option<&str>| NetworkSettings {
+            enabled: true,
+            rules,
+            fallback_profile: fallback.map(|x| x.to_string()),
+        };
+        let mut office = rule("office", "Work");
+        office.ssids = vec!["Office".into()];
+        assert_eq!(
+            validate_settings(&settings(vec![office.clone()], Some("Home")), &profiles),
+            Ok(())
+        );
+        let mut bad_mac = office.clone();
+        bad_mac.gateway_macs = vec!["00:00:00:00:00:00".into()];
+        let mut unknown = office.clone();
+        unknown.profile = "Cafe".into();
+        let cases = [
+            (
+                settings(vec![rule(" ", "Work")], None),
+                "network rule name must not be empty",
+            ),
+            (
+                settings(vec![office.clone(), office.clone()], None),
+                "network rule name `office` is used twice",
+            ),
+            (
+                settings(vec![rule("empty", "Work")], None),
+                "network rule `empty` has no conditions",
+            ),
+            (
+                settings(vec![bad_mac], None),
+                "network rule `office` has an invalid gateway mac `00:00:00:00:00:00`",
+            ),
+            (
+                settings(vec![unknown], None),
+                "network rule `office` uses unknown profile `Cafe`",
+            ),
+            (
+                settings(Vec::new(), Some("Cafe")),
+                "fallback profile `Cafe` is unknown",
+            ),
+        ];
+        for (settings, message) in cases {
+            assert_eq!(
+                validate_settings(&settings, &profiles),
+                Err(message.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn macs_are_normalized() {
+        let cases = [
+            ("A4:2B:B0:01:02:03", Some("a4:2b:b0:01:02:03")),
+            ("a4-2b-b0-1-2-3", Some("a4:2b:b0:01:02:03")),
+            (" a4:2b:b0:1:2:3 ", Some("a4:2b:b0:01:02:03")),
+            ("00:00:00:00:00:00", None),
+            ("a4:2b:b0:01:02", None),
+            ("a4:2b:b0:01:02:zz", None),
+            ("(incomplete)", None),
+        ];
+        for (mac, expected) in cases {
+            assert_eq!(normalize_mac(mac).as_deref(), expected, "{}", mac);
+        }
+    }
+
+    #[test]
+    fn dns_suffixes_match_on_label_boundaries() {
+        let cases = [
+            ("corp.example.com", "corp.example.com", true),
+            ("corp.example.com.", ".Example.com", true),
+            ("corp.example.com", "ample.com", false),
+            ("corp.example.com", "", false),
+            ("example.com", "corp.example.com", false),
+        ];
+        for (domain, suffix, expected) in cases {
+            assert_eq!(
+                dns_suffix_matches(domain, suffix),
+                expected,
+                "{} {}",
+                domain,
+                suffix
+            );
+        }
+    }
+
+    #[test]
+    fn network_id_prefers_the_gateway_mac() {
+        assert_eq!(info().id().as_deref(), Some("mac:a4:2b:b0:01:02:03"));
+        let no_mac = NetworkInfo {
+            gateway_mac: None,
+            ..info()
+        };
+        assert_eq!(no_mac.id().as_deref(), Some("ssid:Office"));
+        let wired = NetworkInfo {
+            ssid: None,
+            ..no_mac
+        };
+        assert_eq!(wired.id().as_deref(), Some("gateway:10.0.0.1%en0"));
+        assert_eq!(NetworkInfo::default().id(), None);
+    }
+
+    #[test]
+    fn fields_of_colon_separated_output() {
+        // iw dev wlan0 link
+        let iw = "Connected to aa:bb:cc:dd:ee:ff (on wlan0)\n\tSSID: Office\n\tfreq: 5180\n";
+        assert_eq!(field(iw, "SSID").as_deref(), Some("Office"));
+        // netsh wlan show interfaces, BSSID holds colons too
+        let netsh = "    Name                   : Wi-Fi\n    State                  : connected\n    SSID                   : Office Guest\n    BSSID                  : aa:bb:cc:dd:ee:ff\n";
+        assert_eq!(field(netsh, "SSID").as_deref(), Some("Office Guest"));
+        assert_eq!(field(netsh, "BSSID").as_deref(), Some("aa:bb:cc:dd:ee:ff"));
+        // route -n get default
+        let route = "   route to: default\ndestination: default\n       mask: default\n    gateway: 192.168.1.1\n  interface: en0\n      flags: <UP,GATEWAY,DONE,STATIC,PRCLONING,GLOBAL>\n";
+        assert_eq!(field(route, "gateway").as_deref(), Some("192.168.1.1"));
+        assert_eq!(field(route, "interface").as_deref(), Some("en0"));
+        assert_eq!(field("  SSID : \n", "SSID"), None);
+        assert_eq!(field(route, "ssid"), None);
+    }
+
+    #[test]
+    fn linux_route_arp_and_resolv_conf() {
+        let routes =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+docker0\t000011AC\t00000000\t0001\t0\t0\t0\t0000FFFF\t0\t0\t0
+eth0\t00000000\tFE01A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+";
+        assert_eq!(
+            parse_proc_route(routes),
+            Some(("eth0".to_string(), Some("192.168.1.254".to_string())))
+        );
+        // a point to point default route has no gateway
+        let tun = "Iface\tDestination\tGateway\tFlags\tRefCnt\tUse\tMetric\tMask\ntun0\t00000000\t00000000\t0001\t0\t0\t0\t00000000\n";
+        assert_eq!(parse_proc_route(tun), Some(("tun0".to_string(), None)));
+        assert_eq!(
+            parse_proc_route(&routes[..routes.find('\n').unwrap()]),
+            None
+        );
+
+        let arp = "IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.254    0x1         0x2         a4:2b:b0:01:02:03     *        eth0
+192.168.1.1      0x1         0x0         00:00:00:00:00:00     *        wlan0
+";
+        assert_eq!(
+            parse_proc_arp(arp, "192.168.1.254").as_deref(),
+            Some("a4:2b:b0:01:02:03")
+        );
+        assert_eq!(parse_proc_arp(arp, "192.168.1.1"), None);
+        assert_eq!(parse_proc_arp(arp, "192.168.1.2"), None);
+
+        let resolv = "# Generated by NetworkManager
+search corp.example.com Example.com.
+nameserver 192.168.1.1
+domain corp.example.com
+options edns0
+";
+        assert_eq!(
+            parse_resolv_conf(resolv),
+            ["corp.example.com", "example.com"]
+        );
+    }
+
+    #[test]
+    fn macos_arp_and_scutil() {
+        let arp = "? (192.168.1.1) at a4:2b:b0:1:2:3 on en0 ifscope [ethernet]\n";
+        assert_eq!(parse_bsd_arp(arp).as_deref(), Some("a4:2b:b0:01:02:03"));
+        let incomplete = "? (192.168.1.1) at (incomplete) on en0 ifscope [ethernet]\n";
+        assert_eq!(parse_bsd_arp(incomplete), None);
+        assert_eq!(
+            parse_bsd_arp("192.168.1.1 (192.168.1.1) -- no entry\n"),
+            None
+        );
+
+        let dns = "DNS configuration
+
+resolver #1
+  search domain[0] : corp.example.com
+  search domain[1] : example.com
+  nameserver[0] : 192.168.1.1
+  if_index : 6 (en0)
+  reach    : 0x00020002 (Reachable,Directly Reachable Address)
+
+resolver #2
+  domain   : local
+  options  : mdns
+
+DNS configuration (for scoped queries)
+
+resolver #1
+  search domain[0] : corp.example.com
+  nameserver[0] : 192.168.1.1
+";
+        assert_eq!(parse_scutil_dns(dns), ["corp.example.com", "example.com"]);
+    }
+
+    #[test]
+    fn windows_route_arp_and_ipconfig() {
+        let routes = "===========================================================================
+Interface List
+ 12...00 15 5d 01 02 03 ......Intel(R) Ethernet Connection
+  1...........................Software Loopback Interface 1
+===========================================================================
+
+IPv4 Route Table
+===========================================================================
+Active Routes:
+Network Destination        Netmask          Gateway       Interface  Metric
+          0.0.0.0          0.0.0.0      192.168.1.1    192.168.1.23     35
+          0.0.0.0          0.0.0.0         10.0.0.1       10.0.0.42     25
+===========================================================================
+Persistent Routes:
+  None
+";
+        assert_eq!(
+            parse_route_print(routes),
+            Some(("10.0.0.1".to_string(), "10.0.0.42".to_string()))
+        );
+        assert_eq!(parse_route_print("Active Routes:\nNone\n"), None);
+
+        let arp = "
+Interface: 10.0.0.42 --- 0xc
+  Internet Address      Physical Address      Type
+  10.0.0.1              a4-2b-b0-01-02-03     dynamic
+  10.0.0.255            ff-ff-ff-ff-ff-ff     static
+";
+        assert_eq!(
+            parse_windows_arp(arp, "10.0.0.1").as_deref(),
+            Some("a4:2b:b0:01:02:03")
+        );
+        assert_eq!(parse_windows_arp(arp, "10.0.0.2"), None);
+
+        let ipconfig = "
+Windows IP Configuration
+
+   Host Name . . . . . . . . . . . . : DESKTOP-1
+   Primary Dns Suffix  . . . . . . . : corp.example.com
+   Node Type . . . . . . . . . . . . : Hybrid
+   DNS Suffix Search List. . . . . . : corp.example.com
+                                       example.com
+
+Ethernet adapter Ethernet:
+
+   Connection-specific DNS Suffix  . : home.lan
+   Description . . . . . . . . . . . : Intel(R) Ethernet Connection
+   DNS Servers . . . . . . . . . . . : 10.0.0.1
+                                       10.0.0.2
+";
+        assert_eq!(
+            parse_ipconfig_suffixes(ipconfig),
+            ["corp.example.com", "example.com", "home.lan"]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Policy routing: rule sets sending their matches to a specific server
+//! instead of the active one.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{
+    command,
+    group::{self, ServerGroup},
+    rules,
+    server::ServerInfo,
+    state::AccessMode,
+    store,
+};
+
+pub const POLICIES_DIR: &str = "policies";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Policy {
+    pub name: String,
+    /// Host of the server, or name of the server group, the matching
+    /// connections are sent to.
+    pub server: String,
+    pub enabled: bool,
+}
+
+impl Policy {
+    /// Store-relative path of the rule list of this policy.
+    pub fn rules_path(&self) -> String {
+        format!("{}/{}.txt", POLICIES_DIR, self.name)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RouteDecision {
+    pub host: String,
+    pub action: RouteTarget,
+    /// Host of the server the connection would be sent to. For load-balance
+    /// groups this is the first healthy member, the agent may pick another.
+    pub server: Option<String>,
+    /// Name of the server group the server was picked from.
+    pub group: Option<String>,
+    /// Name of the policy that picked the server.
+    pub policy: Option<String>,
+    /// The rule that matched, `None` when the access mode default applied.
+    pub matched_rule: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteTarget {
+    Proxy,
+    Direct,
+    Block,
+}
+
+pub fn validate_policies(
+    policies: &[Policy],
+    servers: &[ServerInfo],
+    groups: &[ServerGroup],
+) -> Result<(), String> {
+    for (index, policy) in policies.iter().enumerate() {
+        if policy.name.is_empty()
+            || !policy
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "policy name `{}` may only contain letters, digits, `-` and `_`",
+                policy.name
+            ));
+        }
+        if policies[..index].iter().any(|x| x.name == policy.name) {
+            return Err(format!("policy name `{}` is used twice", policy.name));
+        }
+        if !servers.iter().any(|x| x.host == policy.server)
+            && !groups.iter().any(|x| x.name == policy.server)
+        {
+            return Err(format!(
+                "policy `{}` targets unknown server `{}`",
+                policy.name, policy.server
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Decide where a connection to `host` would go. Block rules win, then direct
+/// rules, then the policies in order, then the proxy rules. Unmatched hosts
+/// follow the access mode; in global mode only block rules and policies are
+/// considered before the active server.
+pub fn lookup_route(app: &AppHandle, host: &str) -> Result<RouteDecision, anyhow::Error> {
+    let access_mode = command::get_access_mode(app.clone());
+    let active_server = store::get_value_by_key(app, store::ACTIVE_SERVER)?;
+    let active_server = active_server.filter(|x| !x.is_empty());
+    let groups = store::get_server_groups(app)?;
+    let decision = |action: RouteTarget, target: Option<String>, policy, matched_rule| {
+        let group = target
+            .as_ref()
+            .filter(|x| groups.iter().any(|group| group.name == **x))
+            .cloned();
+        let server = target.and_then(|x| {
+            let resolved = group::resolve_target(app, &x).into_iter().next();
+            resolved.map(|server| server.host)
+        });
+        RouteDecision {
+            host: host.to_string(),
+            action,
+            server,
+            group,
+            policy,
+            matched_rule,
+        }
+    };
+
+    let block_rules = store::get_rules(app, store::COMBINED_BLOCK_RULES_PATH)?;
+    if let Some(rule) = rules::find_match(&block_rules, host) {
+        return Ok(decision(
+            RouteTarget::Block,
+            None,
+            None,
+            Some(rule.to_string()),
+        ));
+    }
+    if access_mode == AccessMode::Auto {
+        let direct_rules = store::get_rules(app, store::COMBINED_DIRECT_RULES_PATH)?;
+        if let Some(rule) = rules::find_match(&direct_rules, host) {
+            return Ok(decision(
+                RouteTarget::Direct,
+                None,
+                None,
+                Some(rule.to_string()),
+            ));
+        }
+    }
+    for policy in store::get_policies(app)?.iter().filter(|x| x.enabled) {
+        let policy_rules = store::get_rules(app, &policy.rules_path()).unwrap_or_default();
+        if let Some(rule) = rules::find_match(&policy_rules, host) {
+            return Ok(decision(
+                RouteTarget::Proxy,
+                Some(policy.server.clone()),
+                Some(policy.name.clone()),
+                Some(rule.to_string()),
+            ));
+        }
+    }
+    match access_mode {
+        AccessMode::Proxy => Ok(decision(RouteTarget::Proxy, active_server, None, None)),
+        AccessMode::Auto => {
+            let proxy_rules = store::get_rules(app, store::PROXY_RULES_PATH)?;
+            match rules::find_match(&proxy_rules, host) {
+                Some(rule) => Ok(decision(
+                    RouteTarget::Proxy,
+                    active_server,
+                    None,
+                    Some(rule.to_string()),
+                )),
+                None => Ok(decision(RouteTarget::Direct, None, None, None)),
+            }
+        }
+    }
+}
+
+/// Rule paths and servers of the enabled policies, in order. Group targets
+/// resolve to the server the group currently picks.
+pub fn routes(app: &AppHandle) -> Vec<(String, ServerInfo)> {
+    let policies = store::get_policies(app).unwrap_or_default();
+    policies
+        .iter()
+        .filter(|x| x.enabled)
+        .filter_map(|policy| {
+            let server = group::resolve_target(app, &policy.server)
+                .into_iter()
+                .next();
+            if server.is_none() {
+                eprintln!("policy {} targets a missing server", policy.name);
+            }
+            server.map(|server| (policy.rules_path(), server))
+        })
+        .collect()
+}
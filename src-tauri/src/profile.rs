@@ -0,0 +1,185 @@
+//! Named profiles: a snapshot of the active server, the modes, the listen
+//! addresses and the enabled rule sets, switched as a whole.
+
+use std::sync::Mutex;
+
+use anyhow::{Error, anyhow};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    command,
+    group::ServerGroup,
+    policy::Policy,
+    rules::{refresh, source::RuleSource},
+    server::{AddrInfo, ServerInfo},
+    state::{AccessMode, AgentState, BindMode, ProtocolMode},
+    store,
+};
+
+pub const PROFILE_CHANGED_EVENT: &str = "profile_changed";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Profile {
+    pub name: String,
+    /// Whether secc proxies at all, a disabled profile goes direct.
+    pub enabled: bool,
+    /// Host of a server or name of a server group.
+    pub active_server: Option<String>,
+    pub access_mode: AccessMode,
+    pub bind_mode: BindMode,
+    pub protocol_mode: ProtocolMode,
+    pub socks: AddrInfo,
+    pub http: AddrInfo,
+    /// Ids of the rule sources enabled by the profile, the others are off.
+    #[serde(default)]
+    pub rule_sources: Vec<String>,
+    /// Names of the policies enabled by the profile, the others are off.
+    #[serde(default)]
+    pub policies: Vec<String>,
+}
+
+pub fn validate_profiles(
+    profiles: &[Profile],
+    servers: &[ServerInfo],
+    groups: &[ServerGroup],
+    sources: &[RuleSource],
+    policies: &[Policy],
+) -> Result<(), String> {
+    for (index, profile) in profiles.iter().enumerate() {
+        if profile.name.trim().is_empty() {
+            return Err("profile name must not be empty".to_string());
+        }
+        if profiles[..index].iter().any(|x| x.name == profile.name) {
+            return Err(format!("profile name `{}` is used twice", profile.name));
+        }
+        if let Some(active_server) = &profile.active_server
+            && !servers.iter().any(|x| &x.host == active_server)
+            && !groups.iter().any(|x| &x.name == active_server)
+        {
+            return Err(format!(
+                "profile `{}` uses unknown server `{}`",
+                profile.name, active_server
+            ));
+        }
+        if let Some(id) = profile
+            .rule_sources
+            .iter()
+            .find(|id| !sources.iter().any(|x| &x.id == *id))
+        {
+            return Err(format!(
+                "profile `{}` enables unknown rule source `{}`",
+                profile.name, id
+            ));
+        }
+        if let Some(name) = profile
+            .policies
+            .iter()
+            .find(|name| !policies.iter().any(|x| &x.name == *name))
+        {
+            return Err(format!(
+                "profile `{}` enables unknown policy `{}`",
+                profile.name, name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A profile holding the current configuration.
+pub fn capture(app: &AppHandle, name: &str) -> Result<Profile, Error> {
+    let agent_state = {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let agent_state = agent_state.lock().unwrap();
+        agent_state.get()
+    };
+    let active_server = store::get_value_by_key(app, store::ACTIVE_SERVER)?;
+    let sources = store::get_rule_sources(app)?;
+    let policies = store::get_policies(app)?;
+    Ok(Profile {
+        name: name.to_string(),
+        enabled: agent_state,
+        active_server: active_server.filter(|x| !x.is_empty()),
+        access_mode: command::get_access_mode(app.clone()),
+        bind_mode: command::get_bind_mode(app.clone()),
+        protocol_mode: command::get_protocol_mode(app.clone()),
+        socks: store::get_address(app, store::SOCKS_ADDR)?
+            .unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1080)),
+        http: store::get_address(app, store::HTTP_ADDR)?
+            .unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1081)),
+        rule_sources: sources
+            .into_iter()
+            .filter(|x| x.enabled)
+            .map(|x| x.id)
+            .collect(),
+        policies: policies
+            .into_iter()
+            .filter(|x| x.enabled)
+            .map(|x| x.name)
+            .collect(),
+    })
+}
+
+/// Switch to a profile. Everything is checked before anything is written,
+/// then the store is updated as a whole and the agent is brought in line once.
+pub fn switch(app: &AppHandle, name: &str) -> Result<(), Error> {
+    let profiles = store::get_profiles(app)?;
+    let profile = profiles
+        .into_iter()
+        .find(|x| x.name == name)
+        .ok_or(anyhow!("unknown profile `{}`", name))?;
+    let servers = store::get_servers(app)?.unwrap_or_default();
+    let groups = store::get_server_groups(app)?;
+    let mut sources = store::get_rule_sources(app)?;
+    let mut policies = store::get_policies(app)?;
+    validate_profiles(
+        std::slice::from_ref(&profile),
+        &servers,
+        &groups,
+        &sources,
+        &policies,
+    )
+    .map_err(Error::msg)?;
+    println!("switch to profile {}", profile.name);
+
+    for source in sources.iter_mut() {
+        source.enabled = profile.rule_sources.contains(&source.id);
+    }
+    for policy in policies.iter_mut() {
+        policy.enabled = profile.policies.contains(&policy.name);
+    }
+    store::set_rule_sources(app, &sources)?;
+    store::set_policies(app, &policies)?;
+    let active_server = profile.active_server.clone().unwrap_or_default();
+    store::set_str_config(app, store::ACTIVE_SERVER, &active_server)?;
+    store::set_str_config(app, store::ACCESS_MODE, &profile.access_mode.to_string())?;
+    store::set_str_config(app, store::BIND_MODE, &profile.bind_mode.to_string())?;
+    store::set_str_config(
+        app,
+        store::PROTOCOL_MODE,
+        &profile.protocol_mode.to_string(),
+    )?;
+    store::set_address(app, store::SOCKS_ADDR, profile.socks.clone())?;
+    store::set_address(app, store::HTTP_ADDR, profile.http.clone())?;
+    store::set_str_config(app, store::ACTIVE_PROFILE, &profile.name)?;
+    store::combine_proxy_rules(app)?;
+
+    // open_secc applies the whole config at once, restarting the agent at
+    // most once
+    match profile.enabled {
+        true => command::open_secc(app.clone()),
+        false => command::close_secc(app.clone()),
+    }
+    app.emit(PROFILE_CHANGED_EVENT, &profile)?;
+    app.emit("refresh_servers", ())?;
+    app.emit("refresh", ())?;
+
+    // newly enabled sources may never have been downloaded
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = refresh::refresh_sources(&app, false).await {
+            eprintln!("refresh rule sources error: {:?}", e);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,387 @@
+//! Auto protocol mode: start on QUIC, fall back to TCP on networks that drop
+//! UDP, and remember the outcome per server and network.
+//!
+//! A network where QUIC failed is checked again from time to time with a
+//! single packet to the server, next to the running agent; the agent is only
+//! restarted on QUIC once the server answers it. Restarts after network
+//! changes are left to the reconnect, which picks the protocol remembered for
+//! the new network.
+
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hasher, RandomState},
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_http::reqwest;
+use tokio::net::UdpSocket;
+
+use crate::{
+    command, group, network,
+    policy::{self, RouteTarget},
+    rules::refresh::{self, now_secs},
+    server::ServerInfo,
+    state::{AgentConfigState, AgentState, ProtocolMode, ProtocolState},
+    store,
+};
+
+pub const PROTOCOL_FALLBACK_EVENT: &str = "protocol_fallback";
+/// Fetched through the local listener to see whether the tunnel carries
+/// traffic. Only a url the rules send through the active server tells
+/// anything, the first one that is is used.
+const PROBE_URLS: [&str; 4] = [
+    "https://www.gstatic.com/generate_204",
+    "https://www.google.com/generate_204",
+    "https://cp.cloudflare.com/generate_204",
+    "https://connectivitycheck.gstatic.com/generate_204",
+];
+/// Time the agent gets to bring the tunnel up before it is probed.
+const PROBE_DELAY: Duration = Duration::from_secs(2);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Seconds between two checks whether QUIC works again on a network where it
+/// failed.
+const QUIC_RETRY_INTERVAL: u64 = 30 * 60;
+const WATCH_TICK: Duration = Duration::from_secs(60);
+/// Wait for an answer to a QUIC packet, UDP may lose it so it is sent again
+/// every time this runs out.
+const QUIC_PACKET_TIMEOUT: Duration = Duration::from_secs(1);
+const QUIC_PACKET_ATTEMPTS: usize = 3;
+/// Clients pad their first packet to this size, servers ignore shorter ones.
+const QUIC_INITIAL_SIZE: usize = 1200;
+/// A reserved version, servers answer it with a version negotiation packet.
+const QUIC_GREASE_VERSION: u32 = 0x1a2a_3a4a;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProtocolOutcome {
+    pub quic_ok: bool,
+    pub checked_at: u64,
+}
+
+/// Keyed by `<server host>@<network id>`.
+pub type ProtocolOutcomes = HashMap<String, ProtocolOutcome>;
+
+/// The protocol the agent should be started with. Outside auto mode this is
+/// the configured mode; in auto mode it is QUIC unless QUIC failed for the
+/// active server on the current network and didn't work again since.
+pub fn effective_protocol(app: &AppHandle) -> ProtocolMode {
+    let protocol_mode = command::get_protocol_mode(app.clone());
+    if protocol_mode != ProtocolMode::Auto {
+        return protocol_mode;
+    }
+    let Some(server) = group::active_servers(app).into_iter().next() else {
+        return ProtocolMode::Quic;
+    };
+    // nothing to fall back to
+    if server.tcp_port.is_none() {
+        return ProtocolMode::Quic;
+    }
+    let Some(key) = outcome_key(app, &server.host) else {
+        return ProtocolMode::Quic;
+    };
+    let outcomes = store::get_protocol_outcomes(app).unwrap_or_default();
+    match outcomes.get(&key) {
+        Some(outcome) if !outcome.quic_ok => ProtocolMode::Tcp,
+        _ => ProtocolMode::Quic,
+    }
+}
+
+/// Record what the agent was just started with and, in auto mode on QUIC,
+/// check in the background that the tunnel works.
+pub fn agent_started(app: &AppHandle) {
+    let protocol_mode = effective_protocol(app);
+    let key = group::active_servers(app)
+        .first()
+        .and_then(|server| outcome_key(app, &server.host));
+    {
+        let protocol_state = app.state::<Mutex<ProtocolState>>();
+        let mut protocol_state = protocol_state.lock().unwrap();
+        protocol_state.set(Some(protocol_mode), key.clone());
+    }
+    if command::get_protocol_mode(app.clone()) != ProtocolMode::Auto
+        || protocol_mode != ProtocolMode::Quic
+    {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(PROBE_DELAY).await;
+        verify_quic(&app, key).await;
+    });
+}
+
+/// `key` is the outcome key the agent was started for, `None` when the
+/// network wasn't known yet.
+async fn verify_quic(app: &AppHandle, key: Option<String>) {
+    // the agent is not running or secc is off
+    let Some(proxy) = refresh::tunnel_proxy(app) else {
+        return;
+    };
+    let Some(server) = group::active_servers(app).into_iter().next() else {
+        return;
+    };
+    let tunnel_ok = probe_tunnel(app, proxy).await;
+    // restarted on another server, network or protocol in the meantime
+    let (running_protocol, running_key) = running(app);
+    if running_protocol != Some(ProtocolMode::Quic) || running_key != key {
+        return;
+    }
+    let Some(key) = key.or_else(|| outcome_key(app, &server.host)) else {
+        return;
+    };
+    let quic_ok = match tunnel_ok {
+        true => true,
+        // only blame UDP when the server answers over TCP but not over QUIC
+        false => {
+            if group::probe_tcp(&server).await.healthy != Some(true) {
+                println!(
+                    "tunnel probe failed, {} is unreachable over tcp too",
+                    server.host
+                );
+                return;
+            }
+            if quic_reachable(&server).await {
+                println!("tunnel probe failed, {} answers over quic", server.host);
+                return;
+            }
+            false
+        }
+    };
+    set_outcome(app, &key, quic_ok);
+    if !quic_ok {
+        println!("quic failed for {}, falling back to tcp", key);
+        app.emit(PROTOCOL_FALLBACK_EVENT, ProtocolMode::Tcp)
+            .unwrap();
+        command::restart_agent(app);
+    }
+}
+
+/// In auto mode on a network where QUIC failed, check every
+/// `QUIC_RETRY_INTERVAL` whether the server answers over QUIC again, and
+/// only then restart the agent on QUIC.
+pub fn spawn_watcher(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(WATCH_TICK).await;
+            if command::get_protocol_mode(app.clone()) != ProtocolMode::Auto {
+                continue;
+            }
+            let agent_state = {
+                let agent_state = app.state::<Mutex<AgentState>>();
+                let agent_state = agent_state.lock().unwrap();
+                agent_state.get()
+            };
+            if !agent_state {
+                continue;
+            }
+            let (running_protocol, Some(key)) = running(&app) else {
+                continue;
+            };
+            if running_protocol != Some(ProtocolMode::Tcp) {
+                continue;
+            }
+            let outcomes = store::get_protocol_outcomes(&app).unwrap_or_default();
+            let due = outcomes
+                .get(&key)
+                .is_some_and(|x| !x.quic_ok && now_secs() >= x.checked_at + QUIC_RETRY_INTERVAL);
+            if !due {
+                continue;
+            }
+            let Some(server) = group::active_servers(&app).into_iter().next() else {
+                continue;
+            };
+            let quic_ok = quic_reachable(&server).await;
+            // switched server or network while waiting for the answer
+            if running(&app) != (Some(ProtocolMode::Tcp), Some(key.clone())) {
+                continue;
+            }
+            set_outcome(&app, &key, quic_ok);
+            if quic_ok {
+                println!("quic answers again for {}, restarting agent", key);
+                command::restart_agent(&app);
+            }
+        }
+    });
+}
+
+fn set_outcome(app: &AppHandle, key: &str, quic_ok: bool) {
+    let mut outcomes = store::get_protocol_outcomes(app).unwrap_or_default();
+    outcomes.insert(
+        key.to_string(),
+        ProtocolOutcome {
+            quic_ok,
+            checked_at: now_secs(),
+        },
+    );
+    store::set_protocol_outcomes(app, &outcomes).unwrap();
+}
+
+/// The protocol the running agent was started with, and the outcome key it
+/// was started for.
+pub fn running(app: &AppHandle) -> (Option<ProtocolMode>, Option<String>) {
+    let protocol_state = app.state::<Mutex<ProtocolState>>();
+    let protocol_state = protocol_state.lock().unwrap();
+    protocol_state.get()
+}
+
+fn outcome_key(app: &AppHandle, host: &str) -> Option<String> {
+    let network = network::current(app)?.id()?;
+    Some(format!("{}@{}", host, network))
+}
+
+/// Whether the tunnel of the running agent carries traffic. A request goes
+/// through `proxy` when the rules send one of the probe urls through the
+/// active server; otherwise all that can be checked is that the server
+/// answers over the protocol the agent runs on.
+pub async fn probe_tunnel(app: &AppHandle, proxy: reqwest::Proxy) -> bool {
+    if let Some(url) = tunnel_probe_url(app) {
+        return probe(proxy, url).await;
+    }
+    let protocol = {
+        let config_state = app.state::<Mutex<AgentConfigState>>();
+        let config_state = config_state.lock().unwrap();
+        config_state.get().map(|x| x.protocol)
+    };
+    let Some(server) = group::active_servers(app).into_iter().next() else {
+        return false;
+    };
+    match protocol {
+        Some(ProtocolMode::Quic) => quic_reachable(&server).await,
+        Some(_) => group::probe_tcp(&server).await.healthy == Some(true),
+        None => false,
+    }
+}
+
+fn tunnel_probe_url(app: &AppHandle) -> Option<&'static str> {
+    PROBE_URLS.into_iter().find(|url| {
+        let host = url.split('/').nth(2).unwrap_or_default();
+        policy::lookup_route(app, host)
+            .is_ok_and(|x| x.action == RouteTarget::Proxy && x.policy.is_none())
+    })
+}
+
+async fn probe(proxy: reqwest::Proxy, url: &str) -> bool {
+    let client = reqwest::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .proxy(proxy)
+        .build();
+    let Ok(client) = client else {
+        return false;
+    };
+    match client.get(url).send().await {
+        Ok(response) => response.status().is_success(),
+        Err(e) => {
+            println!("tunnel probe error: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Whether the server answers on its QUIC port, without going through the
+/// agent. The packet asks for a version no server speaks, which QUIC servers
+/// answer with a version negotiation packet (RFC 9000, section 6); a network
+/// dropping UDP leaves it unanswered.
+pub async fn quic_reachable(server: &ServerInfo) -> bool {
+    match quic_round_trip(server).await {
+        Ok(round_trip) => round_trip.is_some(),
+        Err(e) => {
+            println!("quic probe of {} error: {:?}", server.host, e);
+            false
+        }
+    }
+}
+
+/// Time between sending the version probe and the server's answer, `None`
+/// when every attempt went unanswered.
+pub async fn quic_round_trip(server: &ServerInfo) -> Result<Option<Duration>, anyhow::Error> {
+    let address = tokio::net::lookup_host((server.host.as_str(), server.quic_port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("{} has no address", server.host))?;
+    let local: SocketAddr = match address {
+        SocketAddr::V4(_) => "0.0.0.0:0".parse()?,
+        SocketAddr::V6(_) => "[::]:0".parse()?,
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(address).await?;
+    let connection_id = RandomState::new().build_hasher().finish().to_be_bytes();
+    let packet = version_probe_packet(&connection_id);
+    let mut answer = [0u8; 1500];
+    for _ in 0..QUIC_PACKET_ATTEMPTS {
+        let start = Instant::now();
+        socket.send(&packet).await?;
+        if let Ok(received) =
+            tokio::time::timeout(QUIC_PACKET_TIMEOUT, socket.recv(&mut answer)).await
+            && is_version_negotiation(&answer[..received?], &connection_id)
+        {
+            return Ok(Some(start.elapsed()));
+        }
+    }
+    Ok(None)
+}
+
+/// A long header packet of the reserved version, padded to the size of a
+/// first packet. `connection_id` is used as both connection ids.
+fn version_probe_packet(connection_id: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xc0];
+    packet.extend_from_slice(&QUIC_GREASE_VERSION.to_be_bytes());
+    for _ in 0..2 {
+        packet.push(connection_id.len() as u8);
+        packet.extend_from_slice(connection_id);
+    }
+    packet.resize(QUIC_INITIAL_SIZE, 0);
+    packet
+}
+
+/// A long header packet of version 0 sent back to `connection_id`.
+fn is_version_negotiation(packet: &[u8], connection_id: &[u8]) -> bool {
+    let Some(id_len) = packet.get(5) else {
+        return false;
+    };
+    packet[0] & 0x80 != 0
+        && packet[1..5] == [0, 0, 0, 0]
+        && packet.get(6..6 + *id_len as usize) == Some(connection_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    #[test]
+    fn probe_packet_is_a_padded_long_header() {
+        let packet = version_probe_packet(&ID);
+        assert_eq!(packet.len(), QUIC_INITIAL_SIZE);
+        assert_eq!(packet[..5], [0xc0, 0x1a, 0x2a, 0x3a, 0x4a]);
+        assert_eq!(packet[5], 8);
+        assert_eq!(packet[6..14], ID);
+        assert_eq!(packet[14], 8);
+        assert_eq!(packet[15..23], ID);
+        assert!(packet[23..].iter().all(|x| *x == 0));
+    }
+
+    #[test]
+    fn recognizes_version_negotiation() {
+        // header, destination id, source id, supported version 1
+        let mut answer = vec![0x80 | 0x2a, 0, 0, 0, 0, 8];
+        answer.extend_from_slice(&ID);
+        answer.push(8);
+        answer.extend_from_slice(&[9; 8]);
+        answer.extend_from_slice(&[0, 0, 0, 1]);
+        assert!(is_version_negotiation(&answer, &ID));
+        assert!(!is_version_negotiation(&answer, &[9; 8]));
+        let mut other_version = answer.clone();
+        other_version[4] = 1;
+        assert!(!is_version_negotiation(&other_version, &ID));
+        let mut short_header = answer.clone();
+        short_header[0] = 0x40;
+        assert!(!is_version_negotiation(&short_header, &ID));
+        assert!(!is_version_negotiation(&answer[..9], &ID));
+        assert!(!is_version_negotiation(&[], &ID));
+    }
+}
@@ -0,0 +1,127 @@
+//! Keep the system proxy and the agent working across network changes and
+//! sleep. Both can leave the system proxy reset by the OS or another tool,
+//! and the agent's QUIC connection dead.
+
+use std::{sync::Mutex, time::Duration};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    command, protocol,
+    rules::refresh::{self, now_secs},
+    shell,
+    state::{AgentState, ReconnectState},
+};
+
+pub const RECONNECTED_EVENT: &str = "reconnected";
+const RESUME_TICK: Duration = Duration::from_secs(5);
+/// Seconds the wall clock may run ahead of a tick before it counts as a
+/// sleep.
+const RESUME_GAP: u64 = 30;
+/// Seconds in which a second reconnect is skipped, a wake usually brings a
+/// network change along.
+const RECONNECT_COOLDOWN: u64 = 10;
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconnectReason {
+    NetworkChanged,
+    Resumed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Reconnected {
+    pub reason: ReconnectReason,
+    /// Whether the system proxy had been reset and was set again.
+    pub proxy_reapplied: bool,
+    /// Whether the agent was restarted, it is kept while its tunnel works.
+    pub agent_restarted: bool,
+}
+
+/// Reconnect after the machine woke up. Tokio's timers run on a clock that
+/// stops during a sleep while the wall clock doesn't, so a wake shows as a
+/// tick that took far too long.
+pub fn spawn_resume_watcher(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last_tick = now_secs();
+        loop {
+            tokio::time::sleep(RESUME_TICK).await;
+            let now = now_secs();
+            let elapsed = now.saturating_sub(last_tick);
+            last_tick = now;
+            if elapsed > RESUME_TICK.as_secs() + RESUME_GAP {
+                println!("woke up after about {} seconds", elapsed);
+                reconnect(&app, ReconnectReason::Resumed).await;
+            }
+        }
+    });
+}
+
+/// Put the system proxy back if it no longer points at secc and restart the
+/// agent on fresh connections when its tunnel didn't survive. Nothing happens
+/// while secc is off.
+pub async fn reconnect(app: &AppHandle, reason: ReconnectReason) {
+    let agent_state = {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let agent_state = agent_state.lock().unwrap();
+        agent_state.get()
+    };
+    if !agent_state {
+        return;
+    }
+    {
+        let reconnect_state = app.state::<Mutex<ReconnectState>>();
+        let mut reconnect_state = reconnect_state.lock().unwrap();
+        if now_secs() < reconnect_state.get() + RECONNECT_COOLDOWN {
+            println!("reconnected moments ago, skipping {:?}", reason);
+            return;
+        }
+        reconnect_state.set(now_secs());
+    }
+    let bind_mode = command::get_bind_mode(app.clone());
+    let proxy_applied = shell::is_proxy_applied(app, bind_mode).await;
+    let proxy_reapplied = proxy_applied == Some(false);
+    if proxy_reapplied {
+        println!("system proxy was reset, applying it again");
+    }
+    // where it can't be read back, applying it again is harmless
+    if proxy_applied != Some(true) {
+        shell::switch_to_bind_mode(app, bind_mode);
+    }
+    let agent_restarted = tunnel_lost(app).await;
+    if agent_restarted {
+        println!("reconnecting agent after {:?}", reason);
+        command::restart_agent(app);
+    } else {
+        println!("tunnel still works after {:?}, keeping the agent", reason);
+    }
+    if !agent_restarted && !proxy_reapplied {
+        return;
+    }
+    let reconnected = Reconnected {
+        reason,
+        proxy_reapplied,
+        agent_restarted,
+    };
+    if let Err(e) = app.emit(RECONNECTED_EVENT, reconnected) {
+        eprintln!("emit {} error: {:?}", RECONNECTED_EVENT, e);
+    }
+}
+
+/// Whether the agent needs new connections: it stopped, the network calls
+/// for another protocol, or its tunnel no longer carries traffic.
+async fn tunnel_lost(app: &AppHandle) -> bool {
+    if !shell::is_sidecar_running(app) {
+        return true;
+    }
+    let (running_protocol, _) = protocol::running(app);
+    if running_protocol != Some(protocol::effective_protocol(app)) {
+        return true;
+    }
+    let Some(proxy) = refresh::tunnel_proxy(app) else {
+        return true;
+    };
+    !protocol::probe_tunnel(app, proxy).await
+}
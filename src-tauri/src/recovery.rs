@@ -0,0 +1,239 @@
+//! Recovery of store files that can't be read at startup.
+//!
+//! A broken `config.json` is moved aside with a timestamp and replaced by the
+//! latest automatic backup that still checks out, or by the defaults when
+//! there is none. Broken rule lists are replaced by their latest history
+//! version. Backups of `config.json` are taken after every clean start.
+
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Error;
+use serde_json::Value;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_store::resolve_store_path;
+
+use crate::{
+    history, migrate,
+    rules::refresh::{now_secs, time_ago},
+    store::{self, BLOCK_RULES_PATH, CONFIG_PATH, CUSTOM_PROXY_RULES_PATH, DIRECT_RULES_PATH},
+};
+
+const BACKUPS_DIR: &str = "backups";
+const MAX_BACKUPS: usize = 5;
+
+/// Check the store files before they are loaded and recover the broken ones.
+/// Returns what was done, one line per file.
+pub fn recover_store(app: &AppHandle) -> Vec<String> {
+    let mut notes = Vec::new();
+    match recover_config(app) {
+        Ok(Some(note)) => notes.push(note),
+        Ok(None) => {}
+        Err(e) => eprintln!("recover {} error: {:?}", CONFIG_PATH, e),
+    }
+    for rule_path in [CUSTOM_PROXY_RULES_PATH, DIRECT_RULES_PATH, BLOCK_RULES_PATH] {
+        match recover_rules(app, rule_path) {
+            Ok(Some(note)) => notes.push(note),
+            Ok(None) => {}
+            Err(e) => eprintln!("recover {} error: {:?}", rule_path, e),
+        }
+    }
+    notes
+}
+
+/// Tell the user which files were recovered.
+pub fn show_notes(app: &AppHandle, notes: &[String]) {
+    if notes.is_empty() {
+        return;
+    }
+    let message = format!(
+        "Some settings files could not be read and were replaced:\n\n{}",
+        notes.join("\n")
+    );
+    app.dialog()
+        .message(message)
+        .title("Secc recovered its settings")
+        .kind(MessageDialogKind::Warning)
+        .show(|_| {});
+}
+
+fn recover_config(app: &AppHandle) -> Result<Option<String>, Error> {
+    let path = resolve_store_path(app, CONFIG_PATH)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let Err(reason) = check_config(&path) else {
+        return Ok(None);
+    };
+    eprintln!("{} is broken: {}", CONFIG_PATH, reason);
+    let moved = move_aside(&path)?;
+    let backup = latest_backup(app)?;
+    let restored = match backup {
+        Some(backup) => {
+            fs::copy(&backup, &path)?;
+            format!("restored from the backup of {}", backup_time(&backup))
+        }
+        // init_all fills in the defaults
+        None => "reset to the defaults".to_string(),
+    };
+    Ok(Some(format!(
+        "{} ({}) was moved to {} and {}.",
+        CONFIG_PATH,
+        reason,
+        file_name(&moved),
+        restored
+    )))
+}
+
+fn check_config(path: &Path) -> Result<(), String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    check_config_content(&content)
+}
+
+/// Check a config the way it will look once the migrations of `init_all`
+/// ran on it, an older schema isn't broken.
+fn check_config_content(content: &str) -> Result<(), String> {
+    let config: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let Value::Object(mut config) = config else {
+        return store::validate_config(&config);
+    };
+    if migrate::schema_version(&config) > migrate::CURRENT_SCHEMA_VERSION {
+        // written by a newer secc, which is left as it is
+        return Ok(());
+    }
+    migrate::migrate(&mut config).map_err(|e| e.to_string())?;
+    store::validate_config(&Value::Object(config))
+}
+
+fn recover_rules(app: &AppHandle, rule_path: &str) -> Result<Option<String>, Error> {
+    let path = resolve_store_path(app, rule_path)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let Err(e) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    eprintln!("{} is broken: {}", rule_path, e);
+    let moved = move_aside(&path)?;
+    let latest = history::list_versions(app, rule_path)
+        .ok()
+        .and_then(|versions| versions.first().map(|x| x.id));
+    let content = latest.and_then(|id| history::read_version(app, rule_path, id).ok());
+    let restored = match &content {
+        Some(_) => "restored from its history",
+        None => "emptied",
+    };
+    store::write_atomic(&path, content.unwrap_or_default().as_bytes())?;
+    Ok(Some(format!(
+        "{} ({}) was moved to {} and {}.",
+        rule_path,
+        e,
+        file_name(&moved),
+        restored
+    )))
+}
+
+/// Rename a broken file to `<name>.corrupt-<unix seconds>`.
+fn move_aside(path: &Path) -> Result<PathBuf, Error> {
+    let mut moved = path.as_os_str().to_owned();
+    moved.push(format!(".corrupt-{}", now_secs()));
+    let moved = PathBuf::from(moved);
+    fs::rename(path, &moved)?;
+    Ok(moved)
+}
+
+/// Keep a copy of `config.json` in the backups directory, unless it equals
+/// the latest one. Only the newest backups are kept.
+pub fn backup_config(app: &AppHandle) -> Result<(), Error> {
+    let path = resolve_store_path(app, CONFIG_PATH)?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let backups_dir = resolve_store_path(app, BACKUPS_DIR)?;
+    fs::create_dir_all(&backups_dir)?;
+    let content = fs::read(&path)?;
+    let mut backups = list_backups(&backups_dir)?;
+    if let Some(latest) = backups.first()
+        && fs::read(latest).ok().as_deref() == Some(content.as_slice())
+    {
+        return Ok(());
+    }
+    let backup = backups_dir.join(format!("{}.{}", CONFIG_PATH, now_secs()));
+    store::write_atomic(&backup, &content)?;
+    backups.insert(0, backup);
+    for old in backups.iter().skip(MAX_BACKUPS) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// The newest backup that passes the checks.
+fn latest_backup(app: &AppHandle) -> Result<Option<PathBuf>, Error> {
+    let backups_dir = resolve_store_path(app, BACKUPS_DIR)?;
+    if !backups_dir.exists() {
+        return Ok(None);
+    }
+    let backups = list_backups(&backups_dir)?;
+    Ok(backups.into_iter().find(|x| check_config(x).is_ok()))
+}
+
+/// Backups of `config.json`, newest first.
+fn list_backups(backups_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let prefix = format!("{}.", CONFIG_PATH);
+    let mut backups: Vec<(u64, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(backups_dir)? {
+        let path = entry?.path();
+        let time = file_name(&path)
+            .strip_prefix(&prefix)
+            .and_then(|x| x.parse().ok());
+        if let Some(time) = time {
+            backups.push((time, path));
+        }
+    }
+    backups.sort_by_key(|x| Reverse(x.0));
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+fn backup_time(backup: &Path) -> String {
+    let secs = file_name(backup)
+        .rsplit('.')
+        .next()
+        .and_then(|x| x.parse::<u64>().ok())
+        .unwrap_or_default();
+    time_ago(secs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_schemas_are_not_broken() {
+        for content in [
+            include_str!("../tests/fixtures/config_v1.json"),
+            include_str!("../tests/fixtures/config_v2.json"),
+            include_str!("../tests/fixtures/config_v3.json"),
+        ] {
+            check_config_content(content).unwrap();
+        }
+    }
+
+    #[test]
+    fn broken_configs_are_found() {
+        assert!(check_config_content("{\"servers\": ").is_err());
+        assert!(check_config_content("[]").is_err());
+        let v2 = include_str!("../tests/fixtures/config_v2.json");
+        let bad_host = v2.replace("\"host\": \"a.example.com\"", "\"host\": \"../x\"");
+        assert!(check_config_content(&bad_host).is_err());
+    }
+}
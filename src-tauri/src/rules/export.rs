@@ -0,0 +1,253 @@
+//! Exporters rendering the combined lists for clients that can't run Secc.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Rule, entries};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// A proxy auto-config script.
+    Pac,
+    /// Clash rule-provider files with the classical behavior.
+    Clash,
+    /// Surge rule sets.
+    Surge,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportFile {
+    pub name: String,
+    pub content: String,
+    /// Entries the format can't express, left out of the file.
+    pub skipped: Vec<String>,
+}
+
+impl ExportFile {
+    fn new(name: &str, (content, skipped): (String, Vec<String>)) -> Self {
+        Self {
+            name: name.to_string(),
+            content,
+            skipped,
+        }
+    }
+}
+
+/// Render the combined proxy and direct lists. `pac_proxy` is the PAC proxy
+/// string used for proxied hosts, e.g. `SOCKS5 127.0.0.1:1080`.
+pub fn export_rules(
+    format: ExportFormat,
+    proxy: &str,
+    direct: &str,
+    pac_proxy: &str,
+) -> Vec<ExportFile> {
+    let proxy = parse(proxy);
+    let direct = parse(direct);
+    match format {
+        ExportFormat::Pac => vec![ExportFile::new(
+            "secc.pac",
+            (render_pac(&proxy, &direct, pac_proxy), Vec::new()),
+        )],
+        ExportFormat::Clash => vec![
+            ExportFile::new("secc-proxy.yaml", render_clash(&proxy)),
+            ExportFile::new("secc-direct.yaml", render_clash(&direct)),
+        ],
+        ExportFormat::Surge => vec![
+            ExportFile::new("secc-proxy.list", render_surge(&proxy)),
+            ExportFile::new("secc-direct.list", render_surge(&direct)),
+        ],
+    }
+}
+
+fn parse(content: &str) -> Vec<Rule> {
+    entries(content)
+        .filter_map(|(_, entry)| Rule::parse(entry).ok())
+        .collect()
+}
+
+/// Surge and Clash share the classical rule syntax.
+fn classical(rule: &Rule) -> Option<String> {
+    match rule {
+        Rule::Domain(domain) => Some(format!("DOMAIN-SUFFIX,{}", domain)),
+        Rule::Full(domain) => Some(format!("DOMAIN,{}", domain)),
+        Rule::Keyword(keyword) => Some(format!("DOMAIN-KEYWORD,{}", keyword)),
+        Rule::Cidr(ip, prefix) if ip.is_ipv4() => {
+            Some(format!("IP-CIDR,{}/{},no-resolve", ip, prefix))
+        }
+        Rule::Cidr(ip, prefix) => Some(format!("IP-CIDR6,{}/{},no-resolve", ip, prefix)),
+        Rule::Regexp(_) => None,
+    }
+}
+
+fn render_clash(rules: &[Rule]) -> (String, Vec<String>) {
+    let mut content = String::from("# Generated by Secc\npayload:\n");
+    let mut skipped = Vec::new();
+    for rule in rules {
+        match classical(rule) {
+            Some(line) => content.push_str(&format!("  - {}\n", line)),
+            None => {
+                content.push_str(&format!("  # unsupported: {}\n", rule.key()));
+                skipped.push(rule.key());
+            }
+        }
+    }
+    (content, skipped)
+}
+
+fn render_surge(rules: &[Rule]) -> (String, Vec<String>) {
+    let mut content = String::from("# Generated by Secc\n");
+    let mut skipped = Vec::new();
+    for rule in rules {
+        match classical(rule) {
+            Some(line) => content.push_str(&format!("{}\n", line)),
+            None => {
+                content.push_str(&format!("# unsupported: {}\n", rule.key()));
+                skipped.push(rule.key());
+            }
+        }
+    }
+    (content, skipped)
+}
+
+fn render_pac(proxy: &[Rule], direct: &[Rule], pac_proxy: &str) -> String {
+    format!(
+        r#"// Generated by Secc
+var proxy = {};
+var direct = "DIRECT";
+
+var directRules = {};
+var proxyRules = {};
+
+function matchRules(rules, host) {{
+  if (rules.full.hasOwnProperty(host)) return true;
+  var suffix = host;
+  while (true) {{
+    if (rules.domains.hasOwnProperty(suffix)) return true;
+    var pos = suffix.indexOf(".");
+    if (pos < 0) break;
+    suffix = suffix.substring(pos + 1);
+  }}
+  for (var i = 0; i < rules.keywords.length; i++) {{
+    if (host.indexOf(rules.keywords[i]) >= 0) return true;
+  }}
+  for (var i = 0; i < rules.regexps.length; i++) {{
+    if (new RegExp(rules.regexps[i]).test(host)) return true;
+  }}
+  if (/^\d+\.\d+\.\d+\.\d+$/.test(host)) {{
+    for (var i = 0; i < rules.cidrs.length; i++) {{
+      if (isInNet(host, rules.cidrs[i][0], rules.cidrs[i][1])) return true;
+    }}
+  }}
+  // isInNet only understands IPv4, isInNetEx is missing in some browsers
+  if (host.indexOf(":") >= 0 && typeof isInNetEx === "function") {{
+    for (var i = 0; i < rules.cidrs6.length; i++) {{
+      if (isInNetEx(host, rules.cidrs6[i])) return true;
+    }}
+  }}
+  return false;
+}}
+
+function FindProxyForURL(url, host) {{
+  host = host.toLowerCase();
+  if (matchRules(directRules, host)) return direct;
+  if (matchRules(proxyRules, host)) return proxy;
+  return direct;
+}}
+"#,
+        serde_json::json!(pac_proxy),
+        pac_rules(direct),
+        pac_rules(proxy),
+    )
+}
+
+fn pac_rules(rules: &[Rule]) -> String {
+    let mut domains = serde_json::Map::new();
+    let mut full = serde_json::Map::new();
+    let mut keywords = Vec::new();
+    let mut regexps = Vec::new();
+    let mut cidrs = Vec::new();
+    let mut cidrs6 = Vec::new();
+    for rule in rules {
+        match rule {
+            Rule::Domain(domain) => {
+                domains.insert(domain.clone(), 1.into());
+            }
+            Rule::Full(domain) => {
+                full.insert(domain.clone(), 1.into());
+            }
+            Rule::Keyword(keyword) => keywords.push(keyword.clone()),
+            Rule::Regexp(regexp) => regexps.push(regexp.as_str().to_string()),
+            Rule::Cidr(std::net::IpAddr::V4(ip), prefix) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                let mask = std::net::Ipv4Addr::from(mask);
+                cidrs.push(serde_json::json!([ip.to_string(), mask.to_string()]));
+            }
+            Rule::Cidr(ip, prefix) => cidrs6.push(format!("{}/{}", ip, prefix)),
+        }
+    }
+    let rules = serde_json::json!({
+        "domains": domains,
+        "full": full,
+        "keywords": keywords,
+        "regexps": regexps,
+        "cidrs": cidrs,
+        "cidrs6": cidrs6,
+    });
+    serde_json::to_string_pretty(&rules).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROXY: &str = include_str!("testdata/proxy_list.txt");
+    const DIRECT: &str = include_str!("testdata/direct_list.txt");
+    const PAC_PROXY: &str = "SOCKS5 127.0.0.1:1080; SOCKS 127.0.0.1:1080";
+
+    // run with `UPDATE_GOLDEN=1` to rewrite the golden files after a format change
+    fn assert_golden(format: ExportFormat) {
+        for file in export_rules(format, PROXY, DIRECT, PAC_PROXY) {
+            let path = format!(
+                "{}/src/rules/testdata/{}",
+                env!("CARGO_MANIFEST_DIR"),
+                file.name
+            );
+            if std::env::var("UPDATE_GOLDEN").is_ok() {
+                std::fs::write(&path, &file.content).unwrap();
+            }
+            let golden = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(
+                file.content, golden,
+                "{} differs from golden file",
+                file.name
+            );
+        }
+    }
+
+    #[test]
+    fn pac_matches_golden() {
+        assert_golden(ExportFormat::Pac);
+    }
+
+    #[test]
+    fn clash_matches_golden() {
+        assert_golden(ExportFormat::Clash);
+    }
+
+    #[test]
+    fn surge_matches_golden() {
+        assert_golden(ExportFormat::Surge);
+    }
+
+    #[test]
+    fn unsupported_entries_are_reported() {
+        for format in [ExportFormat::Clash, ExportFormat::Surge] {
+            let files = export_rules(format, PROXY, DIRECT, PAC_PROXY);
+            assert_eq!(files[0].skipped, ["regexp:^ad[0-9]+\\.example\\.com$"]);
+            assert!(files[1].skipped.is_empty());
+        }
+        let pac = &export_rules(ExportFormat::Pac, PROXY, DIRECT, PAC_PROXY)[0];
+        assert!(pac.skipped.is_empty());
+        assert!(pac.content.contains("\"2001:67c:4e8::/48\""));
+    }
+}
@@ -0,0 +1,498 @@
+//! Importers translating rule lists of other tools into Secc's grammar.
+
+use std::{collections::HashSet, net::IpAddr};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Serialize;
+
+use super::{
+    Rule,
+    source::{RuleFormat, RuleTarget},
+};
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DroppedEntry {
+    /// 1-based line number, or the index of the rule for structured formats.
+    pub line: usize,
+    pub entry: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ImportReport {
+    pub proxy: Vec<String>,
+    pub direct: Vec<String>,
+    pub block: Vec<String>,
+    pub translated: usize,
+    pub dropped: Vec<DroppedEntry>,
+}
+
+impl ImportReport {
+    fn push(&mut self, target: RuleTarget, rule: Rule) {
+        let list = match target {
+            RuleTarget::Proxy => &mut self.proxy,
+            RuleTarget::Direct => &mut self.direct,
+            RuleTarget::Block => &mut self.block,
+        };
+        list.push(rule.key());
+        self.translated += 1;
+    }
+
+    fn drop(&mut self, line: usize, entry: &str, reason: &str) {
+        self.dropped.push(DroppedEntry {
+            line,
+            entry: entry.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn entries(&self, target: RuleTarget) -> &[String] {
+        match target {
+            RuleTarget::Proxy => &self.proxy,
+            RuleTarget::Direct => &self.direct,
+            RuleTarget::Block => &self.block,
+        }
+    }
+}
+
+/// Translate `content` written in `format`. Entries that carry no outcome of
+/// their own (plain lists, Clash rule-provider payloads) go to `target`.
+pub fn import_rules(
+    format: RuleFormat,
+    content: &str,
+    target: RuleTarget,
+) -> Result<ImportReport, String> {
+    match format {
+        RuleFormat::Plain => Ok(import_plain(content, target)),
+        RuleFormat::Gfwlist => Ok(import_gfwlist(content, target)),
+        RuleFormat::Clash => import_clash(content, target),
+        RuleFormat::SwitchyOmega => import_switchy_omega(content),
+    }
+}
+
+/// Append the imported entries missing from an existing rule list.
+pub fn append_rules(existing: &str, imported: &[String]) -> String {
+    let mut seen: HashSet<String> = super::entries(existing)
+        .filter_map(|(_, entry)| Rule::parse(entry).ok().map(|x| x.key()))
+        .collect();
+    let mut content = existing.trim_end().to_string();
+    for entry in imported {
+        if seen.insert(entry.clone()) {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(entry);
+        }
+    }
+    content.push('\n');
+    content
+}
+
+fn import_plain(content: &str, target: RuleTarget) -> ImportReport {
+    let mut report = ImportReport::default();
+    for (line, entry) in super::entries(content) {
+        match Rule::parse(entry) {
+            Ok(rule) => report.push(target, rule),
+            Err(e) => report.drop(line, entry, &e.to_string()),
+        }
+    }
+    report
+}
+
+/// AutoProxy / AdBlock Plus syntax, optionally base64 encoded as published by
+/// gfwlist. Matching entries go to `target`, `@@` exceptions to the opposite.
+fn import_gfwlist(content: &str, target: RuleTarget) -> ImportReport {
+    let compact: String = content.split_whitespace().collect();
+    let decoded = STANDARD
+        .decode(compact.as_bytes())
+        .ok()
+        .and_then(|x| String::from_utf8(x).ok());
+    let content = decoded.as_deref().unwrap_or(content);
+    let exception_target = match target {
+        RuleTarget::Direct => RuleTarget::Proxy,
+        _ => RuleTarget::Direct,
+    };
+
+    let mut report = ImportReport::default();
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let entry = line.trim();
+        if entry.is_empty() || entry.starts_with('!') || entry.starts_with('[') {
+            continue;
+        }
+        let (target, pattern) = match entry.strip_prefix("@@") {
+            Some(pattern) => (exception_target, pattern),
+            None => (target, entry),
+        };
+        if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            report.drop(
+                line_number,
+                entry,
+                "url regular expressions are not supported",
+            );
+            continue;
+        }
+        let rule = if let Some(domain) = pattern.strip_prefix("||") {
+            host_of(domain).map(Rule::parse)
+        } else if let Some(url) = pattern.strip_prefix('|') {
+            host_of(url).map(|x| match x.parse::<IpAddr>() {
+                Ok(_) => Rule::parse(x),
+                Err(_) => Rule::parse(&format!("full:{}", x)),
+            })
+        } else if let Some(domain) = pattern.strip_prefix('.') {
+            host_of(domain).map(Rule::parse)
+        } else {
+            host_of(pattern).map(Rule::parse)
+        };
+        match rule {
+            Some(Ok(rule)) => report.push(target, rule),
+            Some(Err(e)) => report.drop(line_number, entry, &e.to_string()),
+            None => report.drop(line_number, entry, "wildcard patterns are not supported"),
+        }
+    }
+    report
+}
+
+/// The host part of an AutoProxy pattern, `None` when it contains wildcards.
+fn host_of(pattern: &str) -> Option<&str> {
+    let pattern = pattern
+        .strip_prefix("http://")
+        .or_else(|| pattern.strip_prefix("https://"))
+        .unwrap_or(pattern);
+    let host = pattern
+        .split(['/', '^', ':', '?'])
+        .next()
+        .unwrap_or_default();
+    if host.contains('*') {
+        return None;
+    }
+    Some(host)
+}
+
+/// Clash configs (`rules:`) and rule-provider files (`payload:`).
+fn import_clash(content: &str, target: RuleTarget) -> Result<ImportReport, String> {
+    let yaml: serde_yaml::Value = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+    let mut report = ImportReport::default();
+    if let Some(rules) = yaml.get("rules").and_then(|x| x.as_sequence()) {
+        for (index, rule) in rules.iter().enumerate() {
+            let entry = rule.as_str().unwrap_or_default();
+            let mut fields = entry.split(',').map(str::trim);
+            let kind = fields.next().unwrap_or_default();
+            let value = fields.next().unwrap_or_default();
+            let policy = fields.next().unwrap_or_default();
+            let rule_target = match policy.to_uppercase().as_str() {
+                "DIRECT" => RuleTarget::Direct,
+                "REJECT" | "REJECT-DROP" => RuleTarget::Block,
+                _ => RuleTarget::Proxy,
+            };
+            match clash_rule(kind, value) {
+                Ok(rule) => report.push(rule_target, rule),
+                Err(reason) => report.drop(index + 1, entry, &reason),
+            }
+        }
+    }
+    if let Some(payload) = yaml.get("payload").and_then(|x| x.as_sequence()) {
+        for (index, item) in payload.iter().enumerate() {
+            let entry = item.as_str().unwrap_or_default();
+            let rule = match entry.split_once(',') {
+                // classical behavior
+                Some((kind, rest)) => {
+                    let value = rest.split(',').next().unwrap_or_default();
+                    clash_rule(kind.trim(), value.trim())
+                }
+                // domain and ipcidr behavior
+                None => {
+                    let rule = if let Some(domain) = entry.strip_prefix("+.") {
+                        Rule::parse(domain)
+                    } else if let Some(domain) = entry.strip_prefix("*.") {
+                        Rule::parse(domain)
+                    } else if let Some(domain) = entry.strip_prefix('.') {
+                        Rule::parse(domain)
+                    } else if entry.contains('/') || entry.parse::<IpAddr>().is_ok() {
+                        Rule::parse(entry)
+                    } else {
+                        Rule::parse(&format!("full:{}", entry))
+                    };
+                    rule.map_err(|e| e.to_string())
+                }
+            };
+            match rule {
+                Ok(rule) => report.push(target, rule),
+                Err(reason) => report.drop(index + 1, entry, &reason),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn clash_rule(kind: &str, value: &str) -> Result<Rule, String> {
+    let rule = match kind.to_uppercase().as_str() {
+        "DOMAIN" => Rule::parse(&format!("full:{}", value)),
+        "DOMAIN-SUFFIX" => Rule::parse(value),
+        "DOMAIN-KEYWORD" => Rule::parse(&format!("keyword:{}", value)),
+        "IP-CIDR" | "IP-CIDR6" => Rule::parse(&format!("cidr:{}", value)),
+        other => return Err(format!("unsupported rule type {}", other)),
+    };
+    rule.map_err(|e| e.to_string())
+}
+
+/// SwitchyOmega backups (`OmegaOptions.bak`). Switch profile rules using the
+/// `direct` profile go to the direct list, every other profile is proxied.
+fn import_switchy_omega(content: &str) -> Result<ImportReport, String> {
+    let options: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let profiles = options
+        .as_object()
+        .ok_or("expected a SwitchyOmega backup object")?;
+    let mut report = ImportReport::default();
+    for (name, profile) in profiles.iter().filter(|(name, _)| name.starts_with('+')) {
+        match profile["profileType"].as_str() {
+            Some("SwitchProfile") => {}
+            Some("RuleListProfile") => {
+                let target = omega_target(&profile["matchProfileName"]);
+                let rule_list = profile["ruleList"].as_str().unwrap_or_default();
+                let list = import_gfwlist(rule_list, target);
+                report.proxy.extend(list.proxy);
+                report.direct.extend(list.direct);
+                report.block.extend(list.block);
+                report.translated += list.translated;
+                report.dropped.extend(list.dropped);
+                continue;
+            }
+            _ => continue,
+        }
+        let rules = profile["rules"].as_array().cloned().unwrap_or_default();
+        for (index, rule) in rules.iter().enumerate() {
+            let target = omega_target(&rule["profileName"]);
+            let condition = &rule["condition"];
+            let condition_type = condition["conditionType"].as_str().unwrap_or_default();
+            let pattern = condition["pattern"].as_str().unwrap_or_default();
+            let entry = format!("{} {} ({})", condition_type, pattern, name);
+            let rule = match condition_type {
+                "HostWildcardCondition" => omega_wildcard(pattern),
+                "HostRegexCondition" => {
+                    Some(Rule::parse(&format!("regexp:{}", pattern)).map_err(|e| e.to_string()))
+                }
+                "KeywordCondition" => {
+                    Some(Rule::parse(&format!("keyword:{}", pattern)).map_err(|e| e.to_string()))
+                }
+                "IpCondition" => {
+                    let ip = condition["ip"].as_str().unwrap_or_default();
+                    // without a prefix length the condition matches the address alone
+                    let rule = match condition["prefixLength"].as_u64() {
+                        Some(prefix) => Rule::parse(&format!("{}/{}", ip, prefix)),
+                        None => Rule::parse(ip),
+                    };
+                    Some(rule.map_err(|e| e.to_string()))
+                }
+                _ => Some(Err(format!("unsupported condition {}", condition_type))),
+            };
+            match rule {
+                Some(Ok(rule)) => report.push(target, rule),
+                Some(Err(reason)) => report.drop(index + 1, &entry, &reason),
+                None => report.drop(index + 1, &entry, "wildcard patterns are not supported"),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn omega_target(profile_name: &serde_json::Value) -> RuleTarget {
+    match profile_name.as_str() {
+        Some("direct") => RuleTarget::Direct,
+        _ => RuleTarget::Proxy,
+    }
+}
+
+fn omega_wildcard(pattern: &str) -> Option<Result<Rule, String>> {
+    let rule = if let Some(domain) = pattern.strip_prefix("**.") {
+        Rule::parse(domain)
+    } else if let Some(domain) = pattern.strip_prefix("*.") {
+        Rule::parse(domain)
+    } else if pattern.contains('*') {
+        return None;
+    } else {
+        Rule::parse(&format!("full:{}", pattern))
+    };
+    Some(rule.map_err(|e| e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dropped(report: &ImportReport) -> Vec<(usize, &str)> {
+        report
+            .dropped
+            .iter()
+            .map(|x| (x.line, x.reason.as_str()))
+            .collect()
+    }
+
+    const GFWLIST: &str = "[AutoProxy 0.2.9]
+! comment
+||google.com
+|https://www.example.com/path
+|http://1.2.3.4/
+.twitter.com
+@@||cn.example.com
+/^https?:\\/\\/[^\\/]+blogspot\\.(.*)/
+*.wildcard.com
+||bad_host!.com
+";
+
+    #[test]
+    fn gfwlist_plain_and_base64() {
+        let encoded = STANDARD.encode(GFWLIST);
+        // published lists wrap the base64 text
+        let wrapped: String = encoded
+            .as_bytes()
+            .chunks(64)
+            .map(|x| format!("{}\n", std::str::from_utf8(x).unwrap()))
+            .collect();
+        for content in [GFWLIST, wrapped.as_str()] {
+            let report = import_rules(RuleFormat::Gfwlist, content, RuleTarget::Proxy).unwrap();
+            assert_eq!(
+                report.proxy,
+                [
+                    "google.com",
+                    "full:www.example.com",
+                    "1.2.3.4/32",
+                    "twitter.com"
+                ]
+            );
+            assert_eq!(report.direct, ["cn.example.com"]);
+            assert_eq!(report.translated, 5);
+            assert_eq!(
+                dropped(&report),
+                [
+                    (8, "url regular expressions are not supported"),
+                    (9, "wildcard patterns are not supported"),
+                    (10, "invalid domain: unexpected character `!`"),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn gfwlist_exceptions_flip_to_the_opposite_target() {
+        let content = "||proxy.example.com\n@@||direct.example.com\n";
+        let report = import_rules(RuleFormat::Gfwlist, content, RuleTarget::Direct).unwrap();
+        assert_eq!(report.direct, ["proxy.example.com"]);
+        assert_eq!(report.proxy, ["direct.example.com"]);
+        let report = import_rules(RuleFormat::Gfwlist, content, RuleTarget::Block).unwrap();
+        assert_eq!(report.block, ["proxy.example.com"]);
+        assert_eq!(report.direct, ["direct.example.com"]);
+    }
+
+    #[test]
+    fn clash_rules_use_their_policy() {
+        let content = "rules:
+  - DOMAIN-SUFFIX,google.com,Proxy
+  - DOMAIN,www.example.com,DIRECT
+  - DOMAIN-KEYWORD,ads,REJECT
+  - IP-CIDR,10.0.0.0/8,DIRECT,no-resolve
+  - IP-CIDR6,2001:db8::/32,Proxy
+  - GEOIP,CN,DIRECT
+  - MATCH,Proxy
+";
+        let report = import_rules(RuleFormat::Clash, content, RuleTarget::Direct).unwrap();
+        assert_eq!(report.proxy, ["google.com", "2001:db8::/32"]);
+        assert_eq!(report.direct, ["full:www.example.com", "10.0.0.0/8"]);
+        assert_eq!(report.block, ["keyword:ads"]);
+        assert_eq!(
+            dropped(&report),
+            [
+                (6, "unsupported rule type GEOIP"),
+                (7, "unsupported rule type MATCH")
+            ]
+        );
+    }
+
+    #[test]
+    fn clash_payload_goes_to_the_target() {
+        let cases = [
+            (
+                // domain behavior
+                "payload:\n  - '+.google.com'\n  - '.github.com'\n  - 'www.example.com'\n  - '*.*.bad'\n",
+                vec!["google.com", "github.com", "full:www.example.com"],
+                vec![(
+                    4,
+                    "invalid domain: wildcards are not supported, a bare domain already matches its subdomains",
+                )],
+            ),
+            (
+                // ipcidr behavior
+                "payload:\n  - 10.0.0.0/8\n  - 1.1.1.1\n  - 10.0.0.0/40\n",
+                vec!["10.0.0.0/8", "1.1.1.1/32"],
+                vec![(
+                    3,
+                    "invalid ip or cidr: prefix length must be between 0 and 32",
+                )],
+            ),
+            (
+                // classical behavior
+                "payload:\n  - DOMAIN-SUFFIX,google.com\n  - IP-CIDR,10.0.0.0/8,no-resolve\n  - PROCESS-NAME,curl\n",
+                vec!["google.com", "10.0.0.0/8"],
+                vec![(3, "unsupported rule type PROCESS-NAME")],
+            ),
+        ];
+        for (content, expected, expected_dropped) in cases {
+            let report = import_rules(RuleFormat::Clash, content, RuleTarget::Direct).unwrap();
+            assert_eq!(report.direct, expected, "{}", content);
+            assert!(report.proxy.is_empty());
+            assert_eq!(dropped(&report), expected_dropped, "{}", content);
+        }
+        assert!(import_rules(RuleFormat::Clash, "rules: [", RuleTarget::Proxy).is_err());
+    }
+
+    #[test]
+    fn switchy_omega_switch_and_rule_list_profiles() {
+        let content = r#"{
+            "schemaVersion": 2,
+            "+auto": {
+                "profileType": "SwitchProfile",
+                "rules": [
+                    {"condition": {"conditionType": "HostWildcardCondition", "pattern": "*.google.com"}, "profileName": "proxy"},
+                    {"condition": {"conditionType": "HostWildcardCondition", "pattern": "www.example.com"}, "profileName": "direct"},
+                    {"condition": {"conditionType": "HostWildcardCondition", "pattern": "ads*.com"}, "profileName": "proxy"},
+                    {"condition": {"conditionType": "KeywordCondition", "pattern": "tracker"}, "profileName": "proxy"},
+                    {"condition": {"conditionType": "HostRegexCondition", "pattern": "^cdn[0-9]+\\.net$"}, "profileName": "direct"},
+                    {"condition": {"conditionType": "IpCondition", "ip": "10.0.0.0", "prefixLength": 8}, "profileName": "direct"},
+                    {"condition": {"conditionType": "IpCondition", "ip": "192.168.1.1"}, "profileName": "direct"},
+                    {"condition": {"conditionType": "IpCondition", "ip": "fd00::1"}, "profileName": "direct"},
+                    {"condition": {"conditionType": "UrlWildcardCondition", "pattern": "*://x/*"}, "profileName": "proxy"}
+                ]
+            },
+            "+gfw": {
+                "profileType": "RuleListProfile",
+                "matchProfileName": "proxy",
+                "ruleList": "||twitter.com\n@@||cn.twitter.com\n"
+            },
+            "+proxy": {"profileType": "FixedProfile"}
+        }"#;
+        let report = import_rules(RuleFormat::SwitchyOmega, content, RuleTarget::Proxy).unwrap();
+        assert_eq!(
+            report.proxy,
+            ["google.com", "keyword:tracker", "twitter.com"]
+        );
+        assert_eq!(
+            report.direct,
+            [
+                "full:www.example.com",
+                "regexp:^cdn[0-9]+\\.net$",
+                "10.0.0.0/8",
+                "192.168.1.1/32",
+                "fd00::1/128",
+                "cn.twitter.com"
+            ]
+        );
+        assert_eq!(
+            dropped(&report),
+            [
+                (3, "wildcard patterns are not supported"),
+                (9, "unsupported condition UrlWildcardCondition")
+            ]
+        );
+        assert!(import_rules(RuleFormat::SwitchyOmega, "[]", RuleTarget::Proxy).is_err());
+    }
+}
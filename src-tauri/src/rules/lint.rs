@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::{Rule, RuleError, entries};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    InvalidDomain,
+    InvalidRegex,
+    InvalidCidr,
+    UnknownPrefix,
+    Duplicate,
+    Subsumed,
+    Conflict,
+    UnknownPolicy,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LintIssue {
+    /// 1-based line number in the linted text, `0` for issues with the list
+    /// as a whole.
+    pub line: usize,
+    pub entry: String,
+    pub kind: LintKind,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct LintReport {
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|x| x.severity == Severity::Error)
+    }
+}
+
+/// Lint a rule list. `others` are the name and content of the lists with a
+/// different outcome (the proxy and block lists when linting direct rules);
+/// entries found in one of them are reported as conflicts.
+///
+/// Invalid entries are errors and should block saving, duplicates and
+/// conflicts are warnings.
+pub fn lint_rules(content: &str, others: &[(&str, &str)]) -> LintReport {
+    let mut report = LintReport::default();
+    let mut rules: Vec<(usize, &str, Rule)> = Vec::new();
+    for (line, entry) in entries(content) {
+        match Rule::parse(entry) {
+            Ok(rule) => rules.push((line, entry, rule)),
+            Err(e) => {
+                let kind = match e {
+                    RuleError::InvalidDomain(_) => LintKind::InvalidDomain,
+                    RuleError::InvalidRegex(_) => LintKind::InvalidRegex,
+                    RuleError::InvalidCidr(_) => LintKind::InvalidCidr,
+                    RuleError::UnknownPrefix(_) => LintKind::UnknownPrefix,
+                };
+                report.issues.push(LintIssue {
+                    line,
+                    entry: entry.to_string(),
+                    kind,
+                    severity: Severity::Error,
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+
+    // exact duplicates, the first occurrence wins
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut unique: Vec<&(usize, &str, Rule)> = Vec::new();
+    for item in rules.iter() {
+        let (line, entry, rule) = item;
+        if let Some(first) = first_seen.get(&rule.key()) {
+            report.issues.push(LintIssue {
+                line: *line,
+                entry: entry.to_string(),
+                kind: LintKind::Duplicate,
+                severity: Severity::Warning,
+                message: format!("duplicate of line {}", first),
+            });
+        } else {
+            first_seen.insert(rule.key(), *line);
+            unique.push(item);
+        }
+    }
+
+    // entries already matched by a broader entry of the same list
+    let domains: HashMap<&str, usize> = unique
+        .iter()
+        .filter_map(|(line, _, rule)| match rule {
+            Rule::Domain(domain) => Some((domain.as_str(), *line)),
+            _ => None,
+        })
+        .collect();
+    let broad: Vec<&(usize, &str, Rule)> = unique
+        .iter()
+        .copied()
+        .filter(|(_, _, rule)| matches!(rule, Rule::Keyword(_) | Rule::Cidr(..)))
+        .collect();
+    for (line, entry, rule) in unique.iter() {
+        let mut covered_by = match rule {
+            Rule::Domain(domain) => parent_domains(domain)
+                .skip(1)
+                .find_map(|parent| domains.get(parent).map(|x| (*x, parent.to_string()))),
+            Rule::Full(domain) => parent_domains(domain)
+                .find_map(|parent| domains.get(parent).map(|x| (*x, parent.to_string()))),
+            _ => None,
+        };
+        if covered_by.is_none() {
+            covered_by = broad
+                .iter()
+                .find(|(other_line, _, other)| other_line != line && other.covers(rule))
+                .map(|(other_line, other_entry, _)| (*other_line, other_entry.to_string()));
+        }
+        if let Some((other_line, other_entry)) = covered_by {
+            report.issues.push(LintIssue {
+                line: *line,
+                entry: entry.to_string(),
+                kind: LintKind::Subsumed,
+                severity: Severity::Warning,
+                message: format!(
+                    "already covered by `{}` on line {}",
+                    other_entry, other_line
+                ),
+            });
+        }
+    }
+
+    // entries present in lists with a different outcome
+    for (name, other) in others {
+        let other: HashMap<String, usize> = entries(other)
+            .filter_map(|(line, entry)| Rule::parse(entry).ok().map(|rule| (rule.key(), line)))
+            .collect();
+        for (line, entry, rule) in unique.iter() {
+            if let Some(other_line) = other.get(&rule.key()) {
+                report.issues.push(LintIssue {
+                    line: *line,
+                    entry: entry.to_string(),
+                    kind: LintKind::Conflict,
+                    severity: Severity::Warning,
+                    message: format!("also listed on line {} of the {} rules", other_line, name),
+                });
+            }
+        }
+    }
+
+    report.issues.sort_by_key(|x| x.line);
+    report
+}
+
+/// `mail.google.com`, `google.com`, `com`
+fn parent_domains(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(domain), |x| {
+        x.split_once('.').map(|(_, parent)| parent)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(report: &LintReport) -> Vec<(usize, LintKind)> {
+        report.issues.iter().map(|x| (x.line, x.kind)).collect()
+    }
+
+    #[test]
+    fn invalid_entries_are_errors() {
+        let report = lint_rules(
+            "google.com\n*.example.com\nregexp:(\nip:300.0.0.1\nfoo:bar\n",
+            &[],
+        );
+        assert_eq!(
+            kinds(&report),
+            [
+                (2, LintKind::InvalidDomain),
+                (3, LintKind::InvalidRegex),
+                (4, LintKind::InvalidCidr),
+                (5, LintKind::UnknownPrefix),
+            ]
+        );
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn duplicates_compare_canonical_keys() {
+        let report = lint_rules(
+            "Google.com\n10.0.0.1\n# comment\ngoogle.com.\n10.0.0.1/32\n",
+            &[],
+        );
+        assert_eq!(
+            kinds(&report),
+            [(4, LintKind::Duplicate), (5, LintKind::Duplicate)]
+        );
+        assert_eq!(report.issues[0].message, "duplicate of line 1");
+        assert_eq!(report.issues[1].message, "duplicate of line 2");
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn subsumed_entries_name_the_broader_one() {
+        let list = "mail.google.com\ngoogle.com\nfull:www.google.com\nkeyword:ads\nads.example.com\n10.0.0.0/8\n10.1.0.0/16\n";
+        let report = lint_rules(list, &[]);
+        assert_eq!(
+            kinds(&report),
+            [
+                (1, LintKind::Subsumed),
+                (3, LintKind::Subsumed),
+                (5, LintKind::Subsumed),
+                (7, LintKind::Subsumed),
+            ]
+        );
+        assert_eq!(
+            report.issues[0].message,
+            "already covered by `google.com` on line 2"
+        );
+        assert_eq!(
+            report.issues[3].message,
+            "already covered by `10.0.0.0/8` on line 6"
+        );
+    }
+
+    #[test]
+    fn conflicts_with_other_lists() {
+        let report = lint_rules(
+            "google.com\n10.0.0.1\nexample.com\n",
+            &[
+                ("proxy", "# proxy\ngoogle.com\n"),
+                ("block", "10.0.0.1/32\n"),
+            ],
+        );
+        assert_eq!(
+            kinds(&report),
+            [(1, LintKind::Conflict), (2, LintKind::Conflict)]
+        );
+        assert_eq!(
+            report.issues[0].message,
+            "also listed on line 2 of the proxy rules"
+        );
+        assert_eq!(
+            report.issues[1].message,
+            "also listed on line 1 of the block rules"
+        );
+        assert!(!report.has_errors());
+    }
+}
@@ -0,0 +1,111 @@
+//! Build the combined lists handed to the agent.
+//!
+//! Precedence, highest first:
+//!
+//! 1. the user's block list (`block_list.txt`)
+//! 2. the user's direct list (`direct_list.txt`)
+//! 3. the user's proxy list (`custom_proxy_list.txt`)
+//! 4. the enabled rule sources, in the order they are configured
+//!
+//! Every entry is compared by its canonical form and is kept only where it is
+//! claimed first, so an entry never ends up in two combined lists and a later
+//! source can't override an earlier one or the user's own lists. Invalid lines
+//! and comments are dropped, the output order is stable for the same input.
+
+use std::collections::HashSet;
+
+use super::{Rule, entries, source::RuleTarget};
+
+#[derive(Debug, Default)]
+pub struct Combined {
+    pub proxy: String,
+    pub direct: String,
+    pub block: String,
+}
+
+pub fn merge_rules(
+    custom_proxy: &str,
+    custom_direct: &str,
+    custom_block: &str,
+    sources: &[(RuleTarget, String)],
+) -> Combined {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut combined = Combined::default();
+    let layers = [
+        (RuleTarget::Block, custom_block),
+        (RuleTarget::Direct, custom_direct),
+        (RuleTarget::Proxy, custom_proxy),
+    ]
+    .into_iter()
+    .chain(
+        sources
+            .iter()
+            .map(|(target, content)| (*target, content.as_str())),
+    );
+    for (target, content) in layers {
+        let output = match target {
+            RuleTarget::Proxy => &mut combined.proxy,
+            RuleTarget::Direct => &mut combined.direct,
+            RuleTarget::Block => &mut combined.block,
+        };
+        for (_, entry) in entries(content) {
+            if let Ok(rule) = Rule::parse(entry) {
+                let key = rule.key();
+                if seen.insert(key.clone()) {
+                    output.push_str(&key);
+                    output.push('\n');
+                }
+            }
+        }
+    }
+    combined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_lists_take_precedence_over_sources() {
+        let combined = merge_rules(
+            "shared.com\nproxy.com\n",
+            "shared.com\ndirect.com\n",
+            "shared.com\nblock.com\n",
+            &[
+                (
+                    RuleTarget::Direct,
+                    "proxy.com\nblock.com\nsource.com\n".to_string(),
+                ),
+                (RuleTarget::Proxy, "source.com\nlater.com\n".to_string()),
+            ],
+        );
+        assert_eq!(combined.block, "shared.com\nblock.com\n");
+        assert_eq!(combined.direct, "direct.com\nsource.com\n");
+        assert_eq!(combined.proxy, "proxy.com\nlater.com\n");
+    }
+
+    #[test]
+    fn output_follows_input_order() {
+        let sources = [
+            (RuleTarget::Proxy, "c.com\na.com\n".to_string()),
+            (RuleTarget::Proxy, "b.com\na.com\n".to_string()),
+        ];
+        let first = merge_rules("z.com\n", "", "", &sources);
+        let second = merge_rules("z.com\n", "", "", &sources);
+        assert_eq!(first.proxy, "z.com\nc.com\na.com\nb.com\n");
+        assert_eq!(first.proxy, second.proxy);
+    }
+
+    #[test]
+    fn entries_are_rewritten_to_their_canonical_key() {
+        let combined = merge_rules(
+            "# comment\n10.0.0.1/32\ndomain:Example.COM.\nnot a rule!\n",
+            "ip:fd00::1\n",
+            "10.0.0.1\n",
+            &[],
+        );
+        assert_eq!(combined.block, "10.0.0.1/32\n");
+        assert_eq!(combined.direct, "fd00::1/128\n");
+        assert_eq!(combined.proxy, "example.com\n");
+    }
+}
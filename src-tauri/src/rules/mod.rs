@@ -0,0 +1,387 @@
+pub mod export;
+pub mod import;
+pub mod lint;
+pub mod merge;
+pub mod refresh;
+pub mod source;
+
+use std::net::IpAddr;
+
+/// A single entry of a rule list (`direct_list.txt`, `custom_proxy_list.txt`, ...).
+///
+/// The grammar follows the v2ray-rules-dat lists used for the community rules:
+/// a bare domain matches the domain and all of its subdomains, `full:` matches
+/// the exact host, `keyword:` matches any host containing the keyword and
+/// `regexp:` matches the host against a regular expression. Bare IP addresses
+/// and CIDR blocks are matched against the destination address.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Domain(String),
+    Full(String),
+    Keyword(String),
+    Regexp(Pattern),
+    Cidr(IpAddr, u8),
+}
+
+/// A `regexp:` rule, compiled once when the entry is parsed. Patterns are
+/// compared by their source text.
+#[derive(Debug, Clone)]
+pub struct Pattern(regex::Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, RuleError> {
+        regex::Regex::new(pattern)
+            .map(Pattern)
+            .map_err(|e| RuleError::InvalidRegex(e.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleError {
+    InvalidDomain(String),
+    InvalidRegex(String),
+    InvalidCidr(String),
+    UnknownPrefix(String),
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::InvalidDomain(reason) => write!(f, "invalid domain: {}", reason),
+            RuleError::InvalidRegex(reason) => write!(f, "invalid regexp: {}", reason),
+            RuleError::InvalidCidr(reason) => write!(f, "invalid ip or cidr: {}", reason),
+            RuleError::UnknownPrefix(prefix) => write!(f, "unknown rule prefix `{}:`", prefix),
+        }
+    }
+}
+
+impl Rule {
+    /// Parse one trimmed, non-empty, non-comment line.
+    pub fn parse(entry: &str) -> Result<Self, RuleError> {
+        if let Some((prefix, value)) = entry.split_once(':') {
+            // IPv6 addresses contain colons, try them before treating the head as a prefix
+            if let Some(cidr) = parse_cidr(entry) {
+                return cidr;
+            }
+            let value = value.trim();
+            return match prefix.trim().to_lowercase().as_str() {
+                "domain" => validate_domain(value).map(Rule::Domain),
+                "full" => validate_domain(value).map(Rule::Full),
+                "keyword" => {
+                    if value.is_empty() || value.chars().any(char::is_whitespace) {
+                        Err(RuleError::InvalidDomain(
+                            "keyword must be a single non-empty word".to_string(),
+                        ))
+                    } else {
+                        Ok(Rule::Keyword(value.to_lowercase()))
+                    }
+                }
+                "regexp" => Pattern::new(value).map(Rule::Regexp),
+                "cidr" | "ip" => {
+                    parse_cidr(value).unwrap_or(Err(RuleError::InvalidCidr(value.to_string())))
+                }
+                other => Err(RuleError::UnknownPrefix(other.to_string())),
+            };
+        }
+        if let Some(cidr) = parse_cidr(entry) {
+            return cidr;
+        }
+        validate_domain(entry).map(Rule::Domain)
+    }
+
+    /// The canonical text of the rule, used to compare entries across lists.
+    pub fn key(&self) -> String {
+        match self {
+            Rule::Domain(domain) => domain.clone(),
+            Rule::Full(domain) => format!("full:{}", domain),
+            Rule::Keyword(keyword) => format!("keyword:{}", keyword),
+            Rule::Regexp(regexp) => format!("regexp:{}", regexp.as_str()),
+            Rule::Cidr(ip, prefix) => format!("{}/{}", ip, prefix),
+        }
+    }
+
+    /// Whether the rule matches a destination host name or IP address.
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        match self {
+            Rule::Domain(domain) => is_subdomain(&host, domain),
+            Rule::Full(domain) => host == *domain,
+            Rule::Keyword(keyword) => host.contains(keyword.as_str()),
+            Rule::Regexp(regexp) => regexp.0.is_match(&host),
+            Rule::Cidr(net, prefix) => host
+                .parse::<IpAddr>()
+                .map(|ip| in_network(&ip, net, *prefix))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Whether every host matched by `other` is also matched by `self`.
+    pub fn covers(&self, other: &Rule) -> bool {
+        match (self, other) {
+            (Rule::Domain(parent), Rule::Domain(child) | Rule::Full(child)) => {
+                is_subdomain(child, parent)
+            }
+            (Rule::Full(a), Rule::Full(b)) => a == b,
+            (Rule::Keyword(keyword), Rule::Domain(domain) | Rule::Full(domain)) => {
+                domain.contains(keyword.as_str())
+            }
+            (Rule::Keyword(a), Rule::Keyword(b)) => b.contains(a.as_str()),
+            (Rule::Regexp(a), Rule::Regexp(b)) => a == b,
+            (Rule::Cidr(net, prefix), Rule::Cidr(ip, ip_prefix)) => {
+                prefix <= ip_prefix && in_network(ip, net, *prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The first rule of a list matching `host`, as written in the list.
+pub fn find_match<'a>(content: &'a str, host: &str) -> Option<&'a str> {
+    entries(content)
+        .find(|(_, entry)| Rule::parse(entry).is_ok_and(|rule| rule.matches(host)))
+        .map(|(_, entry)| entry)
+}
+
+/// Iterate over the rules of a list, yielding the 1-based line number and the
+/// trimmed entry. Blank lines and `#` comments are skipped.
+pub fn entries(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn validate_domain(domain: &str) -> Result<String, RuleError> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    if domain.is_empty() {
+        return Err(RuleError::InvalidDomain("empty domain".to_string()));
+    }
+    if domain.starts_with("*.") {
+        return Err(RuleError::InvalidDomain(
+            "wildcards are not supported, a bare domain already matches its subdomains".to_string(),
+        ));
+    }
+    if domain.len() > 253 {
+        return Err(RuleError::InvalidDomain(
+            "longer than 253 characters".to_string(),
+        ));
+    }
+    for label in domain.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(RuleError::InvalidDomain(format!(
+                "label `{}` must be 1 to 63 characters",
+                label
+            )));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(RuleError::InvalidDomain(format!(
+                "label `{}` starts or ends with a hyphen",
+                label
+            )));
+        }
+        if let Some(c) = label
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+        {
+            return Err(RuleError::InvalidDomain(format!(
+                "unexpected character `{}`",
+                c
+            )));
+        }
+    }
+    Ok(domain)
+}
+
+fn parse_cidr(entry: &str) -> Option<Result<Rule, RuleError>> {
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (entry, None),
+    };
+    let ip: IpAddr = addr.parse().ok()?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => match prefix.parse::<u8>() {
+            Ok(prefix) if prefix <= max => prefix,
+            _ => {
+                return Some(Err(RuleError::InvalidCidr(format!(
+                    "prefix length must be between 0 and {}",
+                    max
+                ))));
+            }
+        },
+        None => max,
+    };
+    Some(Ok(Rule::Cidr(ip, prefix)))
+}
+
+fn is_subdomain(domain: &str, parent: &str) -> bool {
+    domain == parent
+        || (domain.len() > parent.len()
+            && domain.ends_with(parent)
+            && domain.as_bytes()[domain.len() - parent.len() - 1] == b'.')
+}
+
+fn in_network(ip: &IpAddr, net: &IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(*ip) & mask == u32::from(*net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(*ip) & mask == u128::from(*net) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(entry: &str) -> Rule {
+        Rule::parse(entry).unwrap()
+    }
+
+    #[test]
+    fn parses_prefixes_and_bare_entries() {
+        let cases = [
+            ("google.com", Rule::Domain("google.com".to_string())),
+            ("Google.COM.", Rule::Domain("google.com".to_string())),
+            ("domain:google.com", Rule::Domain("google.com".to_string())),
+            (
+                "full:www.google.com",
+                Rule::Full("www.google.com".to_string()),
+            ),
+            ("keyword:Google", Rule::Keyword("google".to_string())),
+            (
+                "regexp:^ad[0-9]+\\.",
+                Rule::Regexp(Pattern::new("^ad[0-9]+\\.").unwrap()),
+            ),
+            ("10.0.0.1", Rule::Cidr("10.0.0.1".parse().unwrap(), 32)),
+            ("10.0.0.0/8", Rule::Cidr("10.0.0.0".parse().unwrap(), 8)),
+            (
+                "ip:192.168.0.0/16",
+                Rule::Cidr("192.168.0.0".parse().unwrap(), 16),
+            ),
+            ("cidr:fd00::/8", Rule::Cidr("fd00::".parse().unwrap(), 8)),
+        ];
+        for (entry, expected) in cases {
+            assert_eq!(parse(entry), expected, "{}", entry);
+        }
+    }
+
+    #[test]
+    fn ipv6_colons_are_not_a_prefix() {
+        assert_eq!(parse("::1"), Rule::Cidr("::1".parse().unwrap(), 128));
+        assert_eq!(
+            parse("2001:db8::/32"),
+            Rule::Cidr("2001:db8::".parse().unwrap(), 32)
+        );
+        assert_eq!(
+            Rule::parse("2001:db8::/129"),
+            Err(RuleError::InvalidCidr(
+                "prefix length must be between 0 and 128".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let cases = [
+            ("*.google.com", "invalid domain"),
+            ("-bad.com", "invalid domain"),
+            ("bad..com", "invalid domain"),
+            ("sp ace.com", "invalid domain"),
+            ("keyword:two words", "invalid domain"),
+            ("regexp:(", "invalid regexp"),
+            ("ip:not-an-ip", "invalid ip or cidr"),
+            ("10.0.0.0/33", "invalid ip or cidr"),
+            ("geosite:cn", "unknown rule prefix"),
+        ];
+        for (entry, message) in cases {
+            let error = Rule::parse(entry).unwrap_err().to_string();
+            assert!(error.starts_with(message), "{}: {}", entry, error);
+        }
+    }
+
+    #[test]
+    fn key_is_canonical() {
+        assert_eq!(parse("10.0.0.1").key(), "10.0.0.1/32");
+        assert_eq!(parse("ip:10.0.0.0/8").key(), "10.0.0.0/8");
+        assert_eq!(parse("domain:Google.com").key(), "google.com");
+        assert_eq!(parse("full:google.com").key(), "full:google.com");
+        assert_eq!(parse("keyword:ads").key(), "keyword:ads");
+    }
+
+    #[test]
+    fn matches_hosts_and_addresses() {
+        let cases = [
+            ("google.com", "google.com", true),
+            ("google.com", "mail.google.com.", true),
+            ("google.com", "notgoogle.com", false),
+            ("full:google.com", "mail.google.com", false),
+            ("full:google.com", "GOOGLE.com", true),
+            ("keyword:goog", "www.googleapis.com", true),
+            ("regexp:^ad[0-9]+\\.", "ad12.example.com", true),
+            ("regexp:^ad[0-9]+\\.", "bad1.example.com", false),
+            ("10.0.0.0/8", "10.255.1.1", true),
+            ("10.0.0.0/8", "11.0.0.1", false),
+            ("10.0.0.0/8", "example.com", false),
+            ("0.0.0.0/0", "8.8.8.8", true),
+            ("0.0.0.0/0", "::1", false),
+            ("2001:db8::/32", "2001:db8:1::1", true),
+            ("2001:db8::/32", "2001:db9::1", false),
+        ];
+        for (entry, host, expected) in cases {
+            assert_eq!(parse(entry).matches(host), expected, "{} {}", entry, host);
+        }
+    }
+
+    #[test]
+    fn covers_narrower_rules() {
+        let cases = [
+            ("google.com", "mail.google.com", true),
+            ("google.com", "full:google.com", true),
+            ("mail.google.com", "google.com", false),
+            ("keyword:goog", "google.com", true),
+            ("keyword:goog", "keyword:google", true),
+            ("keyword:google", "keyword:goog", false),
+            ("10.0.0.0/8", "10.1.0.0/16", true),
+            ("10.0.0.0/8", "10.1.2.3", true),
+            ("10.1.0.0/16", "10.0.0.0/8", false),
+            ("10.0.0.0/8", "11.0.0.0/16", false),
+            ("fd00::/8", "fd12::/64", true),
+            ("fd00::/8", "10.0.0.0/8", false),
+            ("regexp:a", "regexp:a", true),
+            ("regexp:a", "a.com", false),
+        ];
+        for (rule, other, expected) in cases {
+            assert_eq!(
+                parse(rule).covers(&parse(other)),
+                expected,
+                "{} covers {}",
+                rule,
+                other
+            );
+        }
+    }
+
+    #[test]
+    fn finds_the_first_matching_entry() {
+        let list = "# comment\n\nnot a rule!\nkeyword:goog\ngoogle.com\n";
+        assert_eq!(find_match(list, "mail.google.com"), Some("keyword:goog"));
+        assert_eq!(find_match(list, "example.com"), None);
+    }
+}
@@ -0,0 +1,386 @@
+//! Download and refresh of the remote rule sources.
+//!
+//! Sources are fetched with conditional requests (`If-None-Match` /
+//! `If-Modified-Since`) and a response only replaces the cached copy once it
+//! passed the sanity checks, otherwise the last good copy stays in place.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Error, anyhow};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_http::reqwest::{self, StatusCode, header};
+
+use super::{
+    import,
+    source::{FetchVia, RuleSource},
+};
+use crate::{
+    command,
+    history::{self, HistorySource},
+    state::{AgentState, BindMode, RuleRefreshLock},
+    store,
+};
+
+pub const RULE_SOURCES_STATUS_EVENT: &str = "rule_sources_status";
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Wait before retrying a failed download, doubled on every further failure
+/// and capped at the refresh interval of the source.
+const RETRY_BACKOFF: u64 = 5 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceState {
+    #[default]
+    Pending,
+    Updated,
+    NotModified,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SourceStatus {
+    pub state: SourceState,
+    pub message: Option<String>,
+    /// Unix seconds of the last download attempt.
+    pub last_attempt: Option<u64>,
+    /// Unix seconds of the last time the cached copy was confirmed up to date.
+    pub last_update: Option<u64>,
+    /// Downloads failed in a row since the last successful one.
+    #[serde(default)]
+    pub failures: u32,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub entries: usize,
+    /// Whether the last successful download went through the local listener.
+    #[serde(default)]
+    pub through_tunnel: bool,
+}
+
+/// Refresh status of every source, keyed by source id.
+pub type SourceStatuses = HashMap<String, SourceStatus>;
+
+/// Refresh the enabled sources, `force` ignores their refresh interval.
+/// The combined lists are rebuilt when at least one source changed.
+pub async fn refresh_sources(app: &AppHandle, force: bool) -> Result<(), Error> {
+    let lock = app.state::<RuleRefreshLock>();
+    let _guard = lock.0.lock().await;
+
+    let sources = store::get_rule_sources(app)?;
+    let mut statuses = store::get_rule_sources_status(app)?;
+    let now = now_secs();
+    let mut changed = false;
+    for source in sources.iter().filter(|x| x.enabled) {
+        let mut status = statuses.remove(&source.id).unwrap_or_default();
+        if !force && !is_due(&status, source.refresh_interval, now) {
+            statuses.insert(source.id.clone(), status);
+            continue;
+        }
+        status.last_attempt = Some(now);
+        match fetch_source(app, source, &status).await {
+            Ok((
+                Fetched::Updated {
+                    entries,
+                    etag,
+                    last_modified,
+                },
+                through_tunnel,
+            )) => {
+                status.state = SourceState::Updated;
+                status.through_tunnel = through_tunnel;
+                status.message = None;
+                status.last_update = Some(now);
+                status.failures = 0;
+                status.etag = etag;
+                status.last_modified = last_modified;
+                status.entries = entries;
+                changed = true;
+            }
+            Ok((Fetched::NotModified, through_tunnel)) => {
+                status.state = SourceState::NotModified;
+                status.through_tunnel = through_tunnel;
+                status.message = None;
+                status.last_update = Some(now);
+                status.failures = 0;
+            }
+            Err(e) => {
+                eprintln!("refresh rule source {} error: {:?}", source.id, e);
+                status.state = SourceState::Failed;
+                status.message = Some(e.to_string());
+                status.failures = status.failures.saturating_add(1);
+            }
+        }
+        statuses.insert(source.id.clone(), status);
+        store::set_rule_sources_status(app, &statuses)?;
+        app.emit(RULE_SOURCES_STATUS_EVENT, &statuses)?;
+    }
+    store::set_rule_sources_status(app, &statuses)?;
+
+    if changed {
+        store::combine_proxy_rules(app)?;
+        command::reload_agent(app);
+    }
+    Ok(())
+}
+
+/// Whether a source should be downloaded again. The interval runs from the
+/// last successful download, failed downloads are retried sooner.
+fn is_due(status: &SourceStatus, refresh_interval: u64, now: u64) -> bool {
+    if refresh_interval == 0 {
+        // only refreshed on demand, unless it was never downloaded
+        return status.last_attempt.is_none();
+    }
+    let next = match (status.last_update, status.last_attempt) {
+        (Some(last_update), _) if status.failures == 0 => last_update + refresh_interval,
+        (_, Some(last_attempt)) => {
+            let doublings = status.failures.clamp(1, 16) - 1;
+            let backoff = RETRY_BACKOFF.saturating_mul(1 << doublings);
+            last_attempt + backoff.min(refresh_interval)
+        }
+        _ => return true,
+    };
+    now >= next
+}
+
+/// Periodically refresh the sources whose interval has elapsed.
+pub fn spawn_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = refresh_sources(&app, false).await {
+                eprintln!("scheduled rule refresh error: {:?}", e);
+            }
+            tokio::time::sleep(SCHEDULER_TICK).await;
+        }
+    });
+}
+
+enum Fetched {
+    Updated {
+        entries: usize,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+    NotModified,
+}
+
+/// Download a source directly or through the running agent, as configured by
+/// its `fetch_via`. Returns whether the tunnel was used.
+async fn fetch_source(
+    app: &AppHandle,
+    source: &RuleSource,
+    status: &SourceStatus,
+) -> Result<(Fetched, bool), Error> {
+    let tunnel = tunnel_proxy(app);
+    match (source.fetch_via, tunnel) {
+        (FetchVia::Tunnel, Some(proxy)) => {
+            let fetched = refresh_source(app, source, status, Some(proxy)).await?;
+            Ok((fetched, true))
+        }
+        (FetchVia::Fallback, Some(proxy)) => {
+            match refresh_source(app, source, status, None).await {
+                Ok(fetched) => Ok((fetched, false)),
+                Err(e) => {
+                    eprintln!(
+                        "direct download of rule source {} failed, retrying through secc: {:?}",
+                        source.id, e
+                    );
+                    let fetched = refresh_source(app, source, status, Some(proxy)).await?;
+                    Ok((fetched, true))
+                }
+            }
+        }
+        _ => {
+            let fetched = refresh_source(app, source, status, None).await?;
+            Ok((fetched, false))
+        }
+    }
+}
+
+/// The local listener matching the current bind mode, while the agent runs.
+pub fn tunnel_proxy(app: &AppHandle) -> Option<reqwest::Proxy> {
+    let agent_state = {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let agent_state = agent_state.lock().unwrap();
+        agent_state.get()
+    };
+    if !agent_state {
+        return None;
+    }
+    let (addr_key, scheme) = match command::get_bind_mode(app.clone()) {
+        // socks5h resolves the host name on the remote side
+        BindMode::Socks => (store::SOCKS_ADDR, "socks5h"),
+        BindMode::Http => (store::HTTP_ADDR, "http"),
+    };
+    let addr = store::get_address(app, addr_key).ok().flatten()?;
+    reqwest::Proxy::all(format!("{}://{}:{}", scheme, addr.host, addr.port)).ok()
+}
+
+async fn refresh_source(
+    app: &AppHandle,
+    source: &RuleSource,
+    status: &SourceStatus,
+    proxy: Option<reqwest::Proxy>,
+) -> Result<Fetched, Error> {
+    let cache_path = store::get_config_path(app, &source.cache_path())?;
+    let has_cache = std::fs::metadata(&cache_path)
+        .map(|x| x.len() > 0)
+        .unwrap_or(false);
+
+    let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT);
+    // never pick up the system proxy, it may point at secc itself
+    let client = match proxy {
+        Some(proxy) => client.proxy(proxy),
+        None => client.no_proxy(),
+    };
+    let client = client.build()?;
+    let mut request = client.get(&source.url);
+    // without a cached copy a 304 would leave us with nothing
+    if has_cache {
+        if let Some(etag) = &status.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &status.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!("unexpected status {}", response.status()));
+    }
+    let header_value = |name: header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.to_string())
+    };
+    let content_type = header_value(header::CONTENT_TYPE).unwrap_or_default();
+    let etag = header_value(header::ETAG);
+    let last_modified = header_value(header::LAST_MODIFIED);
+    if content_type.starts_with("text/html") {
+        // captive portals and error pages
+        return Err(anyhow!("unexpected content type {}", content_type));
+    }
+    let body = response.bytes().await?;
+
+    if let Some(checksum_url) = &source.checksum_url {
+        let expected = client
+            .get(checksum_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let expected = expected
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let actual = Sha256::digest(&body)
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect::<String>();
+        if expected != actual {
+            return Err(anyhow!("checksum mismatch, expected {}", expected));
+        }
+    }
+
+    let body = String::from_utf8(body.to_vec())?;
+    // a source only feeds its own list, entries for the other outcome are left out
+    let report = import::import_rules(source.format, &body, source.target).map_err(Error::msg)?;
+    let imported = report.entries(source.target);
+    let entries = imported.len();
+    if entries < source.min_entries {
+        return Err(anyhow!(
+            "only {} valid entries, expected at least {}",
+            entries,
+            source.min_entries
+        ));
+    }
+
+    let content: String = imported.iter().map(|x| format!("{}\n", x)).collect();
+    history::record(
+        app,
+        &source.cache_path(),
+        &content,
+        HistorySource::CommunityRefresh,
+    );
+    store::write_atomic(&cache_path, content.as_bytes())?;
+    Ok(Fetched::Updated {
+        entries,
+        etag,
+        last_modified,
+    })
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+/// How long ago a unix time was, in words.
+pub fn time_ago(time: u64) -> String {
+    let ago = now_secs().saturating_sub(time);
+    match ago {
+        0..60 => "less than a minute ago".to_string(),
+        60..3600 => format!("{} minutes ago", ago / 60),
+        3600..86400 => format!("{} hours ago", ago / 3600),
+        _ => format!("{} days ago", ago / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn status(last_attempt: u64, last_update: Option<u64>, failures: u32) -> SourceStatus {
+        SourceStatus {
+            last_attempt: Some(last_attempt),
+            last_update,
+            failures,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn never_downloaded_sources_are_due() {
+        assert!(is_due(&SourceStatus::default(), DAY, 0));
+        assert!(is_due(&SourceStatus::default(), 0, 0));
+    }
+
+    #[test]
+    fn interval_runs_from_the_last_success() {
+        let status = status(1000, Some(1000), 0);
+        assert!(!is_due(&status, DAY, 1000 + DAY - 1));
+        assert!(is_due(&status, DAY, 1000 + DAY));
+        assert!(!is_due(&status, 0, 1000 + 365 * DAY));
+    }
+
+    #[test]
+    fn failures_are_retried_with_a_capped_backoff() {
+        let first = status(1000, None, 1);
+        assert!(!is_due(&first, DAY, 1000 + RETRY_BACKOFF - 1));
+        assert!(is_due(&first, DAY, 1000 + RETRY_BACKOFF));
+        // a failure after a success doesn't wait for the full interval
+        let third = status(5000, Some(1000), 3);
+        assert!(!is_due(&third, DAY, 5000 + 4 * RETRY_BACKOFF - 1));
+        assert!(is_due(&third, DAY, 5000 + 4 * RETRY_BACKOFF));
+        let many = status(5000, Some(1000), 40);
+        assert!(is_due(&many, DAY, 5000 + DAY));
+        assert!(is_due(&status(1000, None, 3), 600, 1600));
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+pub const RULE_SOURCES_DIR: &str = "rule_sources";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleTarget {
+    Proxy,
+    Direct,
+    /// Connections are refused by the local listener.
+    Block,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleFormat {
+    /// One rule per line, the same grammar as the local rule lists.
+    Plain,
+    /// AutoProxy syntax, plain or base64 encoded.
+    Gfwlist,
+    /// Clash config `rules:` or rule-provider `payload:`.
+    Clash,
+    /// SwitchyOmega options backup.
+    SwitchyOmega,
+}
+
+/// How a source is downloaded.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchVia {
+    /// Always connect to the source directly.
+    Direct,
+    /// Go through the local listener while the agent is running.
+    Tunnel,
+    /// Try directly first and retry through the local listener on failure.
+    #[default]
+    Fallback,
+}
+
+/// A remote rule list layered on top of the user's own lists.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RuleSource {
+    pub id: String,
+    pub url: String,
+    pub target: RuleTarget,
+    pub enabled: bool,
+    /// Seconds between two downloads, `0` only refreshes on demand.
+    pub refresh_interval: u64,
+    pub format: RuleFormat,
+    /// Downloads with fewer valid entries are rejected.
+    #[serde(default = "default_min_entries")]
+    pub min_entries: usize,
+    /// Optional url of a sha256 checksum of the list.
+    #[serde(default)]
+    pub checksum_url: Option<String>,
+    #[serde(default)]
+    pub fetch_via: FetchVia,
+}
+
+fn default_min_entries() -> usize {
+    1
+}
+
+impl RuleSource {
+    pub fn new(id: &str, url: &str, target: RuleTarget) -> Self {
+        Self {
+            id: id.to_string(),
+            url: url.to_string(),
+            target,
+            enabled: true,
+            refresh_interval: 24 * 60 * 60,
+            format: RuleFormat::Plain,
+            min_entries: default_min_entries(),
+            checksum_url: None,
+            fetch_via: FetchVia::default(),
+        }
+    }
+
+    /// Store-relative path of the downloaded copy of this source.
+    pub fn cache_path(&self) -> String {
+        format!("{}/{}.txt", RULE_SOURCES_DIR, self.id)
+    }
+}
+
+/// Check a complete source list before it is saved.
+pub fn validate_sources(sources: &[RuleSource]) -> Result<(), String> {
+    for (index, source) in sources.iter().enumerate() {
+        if source.id.is_empty()
+            || !source
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "source id `{}` may only contain letters, digits, `-` and `_`",
+                source.id
+            ));
+        }
+        if sources[..index].iter().any(|x| x.id == source.id) {
+            return Err(format!("source id `{}` is used twice", source.id));
+        }
+        let urls = std::iter::once(&source.url).chain(source.checksum_url.iter());
+        for url in urls {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err(format!(
+                    "source `{}` must use http or https urls",
+                    source.id
+                ));
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,37 +1,118 @@
+use std::net::IpAddr;
+
 use serde::{Deserialize, Serialize};
 
+use crate::state::ProtocolMode;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerInfo {
     pub host: String,
     pub quic_port: u16,
+    #[serde(default)]
     pub tcp_port: Option<u16>,
+    #[serde(default)]
     pub cert: String,
+    #[serde(default)]
     pub cert_key: String,
+    #[serde(default)]
     pub alias: Option<String>,
+    #[serde(default)]
+    pub transport: TransportOptions,
 }
 
-impl ServerInfo {
-    pub fn from_json(json: &serde_json::Value) -> Self {
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CongestionControl {
+    Bbr,
+    Cubic,
+}
+
+/// Advanced connection settings of a server, handed to the agent.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct TransportOptions {
+    pub congestion_control: CongestionControl,
+    /// Seconds without traffic before a QUIC connection is closed.
+    pub idle_timeout: u32,
+    /// Seconds between QUIC keep-alive packets, 0 disables them.
+    pub keep_alive_interval: u32,
+    pub max_concurrent_streams: u32,
+    pub mtu_discovery: bool,
+    /// Idle TCP connections kept open to the server.
+    pub tcp_pool_size: u32,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
         Self {
-            host: json["host"].as_str().unwrap().to_string(),
-            quic_port: json["quic_port"].as_u64().unwrap() as u16,
-            tcp_port: json["tcp_port"].as_u64().map(|port| port as u16),
-            cert: json["cert"].as_str().unwrap().to_string(),
-            cert_key: json["cert_key"].as_str().unwrap().to_string(),
-            alias: json["alias"].as_str().map(|alias| alias.to_string()),
+            congestion_control: CongestionControl::Bbr,
+            idle_timeout: 30,
+            keep_alive_interval: 10,
+            max_concurrent_streams: 100,
+            mtu_discovery: true,
+            tcp_pool_size: 4,
         }
     }
+}
 
-    pub fn from_json_array(json: &serde_json::Value) -> Vec<Self> {
-        let mut result = Vec::new();
-        for item in json.as_array().unwrap() {
-            result.push(Self::from_json(item));
+impl TransportOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=600).contains(&self.idle_timeout) {
+            return Err("idle timeout must be between 1 and 600 seconds".to_string());
+        }
+        if self.keep_alive_interval >= self.idle_timeout {
+            return Err("keep-alive interval must be shorter than the idle timeout".to_string());
         }
-        result
+        if !(1..=1024).contains(&self.max_concurrent_streams) {
+            return Err("max concurrent streams must be between 1 and 1024".to_string());
+        }
+        if !(1..=64).contains(&self.tcp_pool_size) {
+            return Err("tcp pool size must be between 1 and 64".to_string());
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ServerInfo {
+    /// Check a server before it is stored.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_host(&self.host)?;
+        self.transport.validate()
+    }
+
+    /// `host:port` of the server for a protocol, `None` when the server has no
+    /// port for it. Auto mode starts on QUIC.
+    pub fn address(&self, protocol_mode: &ProtocolMode) -> Option<String> {
+        match protocol_mode {
+            ProtocolMode::Quic | ProtocolMode::Auto => {
+                Some(format!("{}:{}", self.host, self.quic_port))
+            }
+            ProtocolMode::Tcp => self
+                .tcp_port
+                .map(|tcp_port| format!("{}:{}", self.host, tcp_port)),
+        }
+    }
+}
+
+/// A server host is an IP address or a host name.
+pub fn validate_host(host: &str) -> Result<(), String> {
+    if host.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+    let valid_label = |label: &str| {
+        (1..=63).contains(&label.len())
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    if host.len() <= 253 && host.split('.').all(valid_label) {
+        Ok(())
+    } else {
+        Err(format!("`{}` is not a host name or ip address", host))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct AddrInfo {
     pub host: String,
     pub port: u16,
@@ -41,13 +122,6 @@ impl AddrInfo {
     pub fn new(host: String, port: u16) -> Self {
         Self { host, port }
     }
-
-    pub fn from_json(json: &serde_json::Value) -> Self {
-        Self {
-            host: json["host"].as_str().unwrap().to_string(),
-            port: json["port"].as_u64().unwrap() as u16,
-        }
-    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -72,3 +146,51 @@ impl ListenConfigOption {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store;
+
+    #[test]
+    fn hosts_are_names_or_addresses() {
+        for host in [
+            "a.example",
+            "vpn-1.example.com",
+            "localhost",
+            "10.0.0.1",
+            "fd00::1",
+        ] {
+            assert_eq!(validate_host(host), Ok(()), "{}", host);
+        }
+        for host in [
+            "",
+            "../../x",
+            "a/b",
+            "a..example",
+            "-a.example",
+            "a.example:443",
+            "a b",
+        ] {
+            assert!(validate_host(host).is_err(), "{}", host);
+        }
+    }
+
+    #[test]
+    fn cert_files_are_named_by_a_hash() {
+        for host in ["a.example", "fd00::1"] {
+            let path = store::server_cert_path(host);
+            let name = path.strip_prefix("certs/").unwrap();
+            assert_eq!(name.len(), 16 + ".pem".len());
+            assert!(
+                name.trim_end_matches(".pem")
+                    .chars()
+                    .all(|c| c.is_ascii_hexdigit())
+            );
+        }
+        assert_ne!(
+            store::server_cert_path("a.example"),
+            store::server_cert_path("b.example")
+        );
+    }
+}
@@ -7,9 +7,15 @@ mod windows;
 
 use std::sync::Mutex;
 
-use crate::state::SidecarState;
+use crate::agent;
+use crate::connection;
+use crate::state::{AgentConfigState, BindMode, ConnectionStatus, SidecarChild, SidecarState};
 use sysinfo::{Pid, System};
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::CommandEvent;
+
+pub const AGENT_INCOMPATIBLE_EVENT: &str = "agent_incompatible";
 
 pub fn switch_to_socks(app: &AppHandle) {
     #[cfg(target_os = "macos")]
@@ -27,6 +33,26 @@ pub fn switch_to_http(app: &AppHandle) {
     #[cfg(target_os = "windows")]
     windows::switch_to_http(app);
 }
+/// Point the system proxy at the listener of the bind mode.
+pub fn switch_to_bind_mode(app: &AppHandle, bind_mode: BindMode) {
+    match bind_mode {
+        BindMode::Socks => switch_to_socks(app),
+        BindMode::Http => switch_to_http(app),
+    }
+}
+
+/// Whether the system proxy still points at secc, `None` where it can't be
+/// read back.
+pub async fn is_proxy_applied(app: &AppHandle, bind_mode: BindMode) -> Option<bool> {
+    #[cfg(target_os = "macos")]
+    return Some(macos::is_proxy_applied(app, bind_mode).await);
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (app, bind_mode);
+        None
+    }
+}
+
 pub fn switch_to_direct(app: &AppHandle) {
     #[cfg(target_os = "macos")]
     macos::switch_to_direct(app);
@@ -35,22 +61,139 @@ pub fn switch_to_direct(app: &AppHandle) {
     #[cfg(target_os = "windows")]
     windows::switch_to_direct(app);
 }
+
+/// Start the agent with a freshly written config file.
 pub fn call_sidecar(app: &AppHandle) {
-    #[cfg(target_os = "macos")]
-    macos::call_sidecar(app);
-    #[cfg(target_os = "linux")]
-    linux::call_sidecar(app);
-    #[cfg(target_os = "windows")]
-    windows::call_sidecar(app);
+    let info = agent::agent_info(app);
+    if !info.compatible {
+        let message = info.message.unwrap_or_default();
+        app.emit(AGENT_INCOMPATIBLE_EVENT, &message).unwrap();
+        connection::set(app, ConnectionStatus::Error);
+        return;
+    }
+    let (config, config_path) = match agent::write_config(app) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("write agent config error: {:?}", e);
+            connection::set(app, ConnectionStatus::Error);
+            return;
+        }
+    };
+    let sidecar_command = app
+        .shell()
+        .sidecar("secc-agent")
+        .unwrap()
+        .env("RUST_LOG", "INFO")
+        .args(["--config", config_path.to_string_lossy().as_ref()]);
+    let (mut rx, child) = sidecar_command.spawn().unwrap();
+    let pid = child.pid();
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    let log = String::from_utf8_lossy(&line);
+                    app_handle.emit("secc-agent-log", log).unwrap();
+                }
+                CommandEvent::Stderr(line) => {
+                    let log = String::from_utf8_lossy(&line);
+                    app_handle.emit("secc-agent-log", log).unwrap();
+                }
+                CommandEvent::Error(error) => {
+                    app_handle.emit("secc-agent-log", error).unwrap();
+                }
+                CommandEvent::Terminated(_) => {
+                    app_handle.emit("secc-agent-log", "Terminated").unwrap();
+                    // killed agents are forgotten first, this one exited on
+                    // its own
+                    let current = {
+                        let sidecar_state = app_handle.state::<Mutex<SidecarState>>();
+                        let sidecar_state = sidecar_state.lock().unwrap();
+                        sidecar_state.get() == pid
+                    };
+                    if current {
+                        connection::set(&app_handle, ConnectionStatus::Error);
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    {
+        let sidecar_state = app.state::<Mutex<SidecarState>>();
+        let mut sidecar_state = sidecar_state.lock().unwrap();
+        sidecar_state.set(pid);
+    }
+    {
+        let child_state = app.state::<Mutex<SidecarChild>>();
+        let mut child_state = child_state.lock().unwrap();
+        child_state.set(Some(child));
+    }
+    {
+        let config_state = app.state::<Mutex<AgentConfigState>>();
+        let mut config_state = config_state.lock().unwrap();
+        config_state.set(Some(config));
+    }
+    connection::agent_started(app);
 }
 
-pub fn kill_sidecar(app: &AppHandle) {
+/// Ask the running agent to re-read its config and rule lists. `false` when
+/// it can't reload or the command couldn't be sent.
+pub fn reload_sidecar(app: &AppHandle) -> bool {
+    if !agent::agent_info(app).has_capability(agent::CAPABILITY_RELOAD) {
+        return false;
+    }
+    let config = match agent::write_config(app) {
+        Ok((config, _)) => config,
+        Err(e) => {
+            eprintln!("write agent config error: {:?}", e);
+            return false;
+        }
+    };
+    let sent = {
+        let child_state = app.state::<Mutex<SidecarChild>>();
+        let mut child_state = child_state.lock().unwrap();
+        match child_state.get_mut() {
+            Some(child) => match child.write(b"reload\n") {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("reload agent error: {:?}", e);
+                    false
+                }
+            },
+            None => false,
+        }
+    };
+    if sent {
+        let config_state = app.state::<Mutex<AgentConfigState>>();
+        let mut config_state = config_state.lock().unwrap();
+        config_state.set(Some(config));
+    }
+    sent
+}
+
+pub fn is_sidecar_running(app: &AppHandle) -> bool {
     let sidecar_state = app.state::<Mutex<SidecarState>>();
     let sidecar_state = sidecar_state.lock().unwrap();
     let pid = sidecar_state.get();
     let sys = System::new_all();
+    pid != 0 && sys.process(Pid::from_u32(pid)).is_some()
+}
+
+pub fn kill_sidecar(app: &AppHandle) {
+    let sidecar_state = app.state::<Mutex<SidecarState>>();
+    let mut sidecar_state = sidecar_state.lock().unwrap();
+    let pid = sidecar_state.get();
+    sidecar_state.set(0);
+    let sys = System::new_all();
     let pid = Pid::from_u32(pid);
     if let Some(process) = sys.process(pid) {
         process.kill();
     }
+    let child_state = app.state::<Mutex<SidecarChild>>();
+    let mut child_state = child_state.lock().unwrap();
+    child_state.set(None);
+    let config_state = app.state::<Mutex<AgentConfigState>>();
+    let mut config_state = config_state.lock().unwrap();
+    config_state.set(None);
 }
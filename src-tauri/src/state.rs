@@ -1,5 +1,17 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
+use tauri_plugin_shell::process::CommandChild;
+
+use crate::{
+    agent::{AgentConfig, AgentInfo},
+    group::ServerHealth,
+    network::{MAX_DECISIONS, NetworkDecision, NetworkInfo},
+};
 
 #[derive(Debug)]
 pub struct SidecarState(u32);
@@ -16,6 +28,19 @@ impl SidecarState {
     }
 }
 
+/// Handle of the running agent, control commands are written to its stdin.
+#[derive(Default)]
+pub struct SidecarChild(Option<CommandChild>);
+
+impl SidecarChild {
+    pub fn set(&mut self, child: Option<CommandChild>) {
+        self.0 = child;
+    }
+    pub fn get_mut(&mut self) -> Option<&mut CommandChild> {
+        self.0.as_mut()
+    }
+}
+
 #[derive(Default)]
 pub struct AgentState(bool);
 
@@ -28,7 +53,140 @@ impl AgentState {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Display, PartialEq, EnumString)]
+/// Config the running agent was started with.
+#[derive(Default)]
+pub struct AgentConfigState(Option<AgentConfig>);
+
+impl AgentConfigState {
+    pub fn set(&mut self, config: Option<AgentConfig>) {
+        self.0 = config;
+    }
+    pub fn get(&self) -> Option<&AgentConfig> {
+        self.0.as_ref()
+    }
+}
+
+/// Version and capabilities of the bundled agent, once queried.
+#[derive(Default)]
+pub struct AgentInfoState(Option<AgentInfo>);
+
+impl AgentInfoState {
+    pub fn set(&mut self, info: Option<AgentInfo>) {
+        self.0 = info;
+    }
+    pub fn get(&self) -> Option<&AgentInfo> {
+        self.0.as_ref()
+    }
+}
+
+/// Last health check result of every grouped server, by host.
+#[derive(Default)]
+pub struct HealthState(HashMap<String, ServerHealth>);
+
+impl HealthState {
+    pub fn set(&mut self, health: HashMap<String, ServerHealth>) {
+        self.0 = health;
+    }
+    pub fn get(&self) -> &HashMap<String, ServerHealth> {
+        &self.0
+    }
+}
+
+/// Protocol and network key the agent was last started with.
+#[derive(Default)]
+pub struct ProtocolState(Option<ProtocolMode>, Option<String>);
+
+impl ProtocolState {
+    pub fn set(&mut self, protocol_mode: Option<ProtocolMode>, key: Option<String>) {
+        self.0 = protocol_mode;
+        self.1 = key;
+    }
+    pub fn get(&self) -> (Option<ProtocolMode>, Option<String>) {
+        (self.0, self.1.clone())
+    }
+}
+
+/// Latest network switching decisions, newest first, and the network secc
+/// is on.
+#[derive(Default)]
+pub struct NetworkState(VecDeque<NetworkDecision>, Option<NetworkInfo>);
+
+impl NetworkState {
+    pub fn push(&mut self, decision: NetworkDecision) {
+        self.0.push_front(decision);
+        self.0.truncate(MAX_DECISIONS);
+    }
+    pub fn get(&self) -> &VecDeque<NetworkDecision> {
+        &self.0
+    }
+    pub fn set_current(&mut self, info: NetworkInfo) {
+        self.1 = Some(info);
+    }
+    pub fn current(&self) -> Option<&NetworkInfo> {
+        self.1.as_ref()
+    }
+}
+
+/// Unix seconds of the last reconnect after a network change or a wake.
+#[derive(Default)]
+pub struct ReconnectState(u64);
+
+impl ReconnectState {
+    pub fn set(&mut self, time: u64) {
+        self.0 = time;
+    }
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ConnectionStatus {
+    #[default]
+    Off,
+    Connecting,
+    Connected,
+    Error,
+}
+
+/// Status of the connection, with a counter of its changes so a late probe
+/// result for an earlier agent start is dropped.
+#[derive(Default)]
+pub struct ConnectionState(ConnectionStatus, u64);
+
+impl ConnectionState {
+    /// Returns the generation of the new status.
+    pub fn set(&mut self, status: ConnectionStatus) -> u64 {
+        self.0 = status;
+        self.1 += 1;
+        self.1
+    }
+    pub fn get(&self) -> ConnectionStatus {
+        self.0
+    }
+    pub fn generation(&self) -> u64 {
+        self.1
+    }
+}
+
+/// Hashes of the rule files as secc last wrote them, by canonical path.
+#[derive(Default)]
+pub struct WrittenFiles(HashMap<PathBuf, Vec<u8>>);
+
+impl WrittenFiles {
+    pub fn set(&mut self, path: PathBuf, hash: Vec<u8>) {
+        self.0.insert(path, hash);
+    }
+    pub fn get(&self, path: &Path) -> Option<&Vec<u8>> {
+        self.0.get(path)
+    }
+}
+
+/// Serializes rule source refreshes from the scheduler and the UI.
+#[derive(Default)]
+pub struct RuleRefreshLock(pub tokio::sync::Mutex<()>);
+
+#[derive(Debug, Serialize, Deserialize, Display, PartialEq, EnumString, Clone, Copy)]
 pub enum AccessMode {
     #[serde(rename = "auto")]
     #[strum(serialize = "auto")]
@@ -38,7 +196,7 @@ pub enum AccessMode {
     Proxy,
 }
 
-#[derive(Debug, Serialize, Deserialize, Display, PartialEq, EnumString)]
+#[derive(Debug, Serialize, Deserialize, Display, PartialEq, EnumString, Clone, Copy)]
 pub enum BindMode {
     #[serde(rename = "socks")]
     #[strum(serialize = "socks")]
@@ -48,8 +206,12 @@ pub enum BindMode {
     Http,
 }
 
-#[derive(Debug, Serialize, Deserialize, Display, PartialEq, EnumString)]
+#[derive(Debug, Serialize, Deserialize, Display, PartialEq, EnumString, Clone, Copy)]
 pub enum ProtocolMode {
+    /// QUIC, falling back to TCP where QUIC fails.
+    #[serde(rename = "auto")]
+    #[strum(serialize = "auto")]
+    Auto,
     #[serde(rename = "quic")]
     #[strum(serialize = "quic")]
     Quic,
@@ -1,19 +1,32 @@
 use std::{
     fs::{File, OpenOptions},
     io::{BufReader, Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
 use tauri::AppHandle;
-use tauri_plugin_http::reqwest;
 use tauri_plugin_store::{StoreExt, resolve_store_path};
 
 use crate::{
+    group::ServerGroup,
+    history::{self, HistorySource},
+    migrate,
+    network::NetworkSettings,
+    policy::{POLICIES_DIR, Policy},
+    profile::Profile,
+    protocol::ProtocolOutcomes,
+    rules::{
+        merge,
+        refresh::SourceStatuses,
+        source::{RULE_SOURCES_DIR, RuleSource, RuleTarget},
+    },
     server::{AddrInfo, ServerInfo},
     state::{AccessMode, BindMode, ProtocolMode},
+    watch,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -31,21 +44,36 @@ pub const ACCESS_MODE: &str = "access_mode";
 pub const BIND_MODE: &str = "bind_mode";
 pub const PROTOCOL_MODE: &str = "protocol_mode";
 pub const COMMUNITY_RULES: &str = "rules_url";
+pub const RULE_SOURCES: &str = "rule_sources";
+pub const RULE_SOURCES_STATUS: &str = "rule_sources_status";
+pub const COMMUNITY_SOURCE_ID: &str = "community";
+pub const POLICIES: &str = "policies";
+pub const SERVER_GROUPS: &str = "server_groups";
+pub const PROTOCOL_OUTCOMES: &str = "protocol_outcomes";
+pub const SCHEMA_VERSION: &str = "schema_version";
+pub const PROFILES: &str = "profiles";
+pub const ACTIVE_PROFILE: &str = "active_profile";
+pub const NETWORK_SETTINGS: &str = "network_settings";
 
 pub const PROXY_RULES_PATH: &str = "proxy_list.txt";
 pub const CUSTOM_PROXY_RULES_PATH: &str = "custom_proxy_list.txt";
 pub const COMMUNITY_PROXY_RULES_PATH: &str = "community_proxy_list.txt";
 pub const DIRECT_RULES_PATH: &str = "direct_list.txt";
+pub const COMBINED_DIRECT_RULES_PATH: &str = "combined_direct_list.txt";
+pub const BLOCK_RULES_PATH: &str = "block_list.txt";
+pub const COMBINED_BLOCK_RULES_PATH: &str = "combined_block_list.txt";
 pub const COMMUNITY_RULES_URL: &str =
     "https://cdn.jsdelivr.net/gh/Loyalsoldier/v2ray-rules-dat@release/proxy-list.txt"; // https://raw.githubusercontent.com/Loyalsoldier/v2ray-rules-dat/release/proxy-list.txt
 pub const CERT_PATH: &str = "cert.pem";
 pub const CERT_KEY_PATH: &str = "cert.key.pem";
+pub const SERVER_CERTS_DIR: &str = "certs";
 
 pub fn init_all(app: &AppHandle) -> Result<(), Error> {
     let path = resolve_store_path(app, "")?;
     if !std::path::Path::new(&path).exists() {
         std::fs::create_dir_all(&path)?;
     }
+    migrate::migrate_store(app)?;
     let bind_socks_addr = get_address(app, SOCKS_ADDR)?;
     if bind_socks_addr.is_none() {
         set_address(
@@ -80,31 +108,66 @@ pub fn init_all(app: &AppHandle) -> Result<(), Error> {
     if protocol_mode.is_none() {
         set_str_config(app, PROTOCOL_MODE, ProtocolMode::Tcp.to_string().as_str())?;
     }
-    let rules_url = get_value_by_key(app, COMMUNITY_RULES)?;
-    if rules_url.is_none() {
-        set_str_config(app, COMMUNITY_RULES, COMMUNITY_RULES_URL)?;
+    let rule_sources = app.store(CONFIG_PATH)?.get(RULE_SOURCES);
+    if rule_sources.is_none() {
+        let community =
+            RuleSource::new(COMMUNITY_SOURCE_ID, COMMUNITY_RULES_URL, RuleTarget::Proxy);
+        set_rule_sources(app, &[community])?;
     }
 
     init_rules_files(app)?;
     init_cert_files(app)?;
+    combine_proxy_rules(app)?;
 
     Ok(())
 }
 
-pub async fn load_community_proxy_list(app: &AppHandle) -> Result<(), Error> {
-    let url = get_value_by_key(app, COMMUNITY_RULES)?;
-    let req_url = match url {
-        Some(url) => url,
-        None => COMMUNITY_RULES_URL.to_string(),
-    };
-    let response = reqwest::get(req_url).await?;
-    let byte_data = response.bytes().await?;
-    let path = resolve_store_path(app, COMMUNITY_PROXY_RULES_PATH)?;
-    let mut file = File::create(&path)?;
-    file.write_all(&byte_data)?;
-    file.flush()?;
+pub fn get_rule_sources(app: &AppHandle) -> Result<Vec<RuleSource>, Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let data = store.get(RULE_SOURCES);
+    if let Some(data) = data {
+        let sources = serde_json::from_value(data)?;
+        return Ok(sources);
+    }
+    Ok(Vec::new())
+}
 
-    combine_proxy_rules(app)?;
+pub fn set_rule_sources(app: &AppHandle, sources: &[RuleSource]) -> Result<(), Error> {
+    let store = app.store(CONFIG_PATH)?;
+    store.set(RULE_SOURCES, json!(sources));
+    init_rule_source_files(app)?;
+    Ok(())
+}
+
+pub fn get_rule_sources_status(app: &AppHandle) -> Result<SourceStatuses, Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let data = store.get(RULE_SOURCES_STATUS);
+    if let Some(data) = data {
+        let statuses = serde_json::from_value(data)?;
+        return Ok(statuses);
+    }
+    Ok(SourceStatuses::new())
+}
+
+pub fn set_rule_sources_status(app: &AppHandle, statuses: &SourceStatuses) -> Result<(), Error> {
+    let store = app.store(CONFIG_PATH)?;
+    store.set(RULE_SOURCES_STATUS, json!(statuses));
+    Ok(())
+}
+
+pub fn get_protocol_outcomes(app: &AppHandle) -> Result<ProtocolOutcomes, Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let data = store.get(PROTOCOL_OUTCOMES);
+    if let Some(data) = data {
+        let outcomes = serde_json::from_value(data)?;
+        return Ok(outcomes);
+    }
+    Ok(ProtocolOutcomes::new())
+}
+
+pub fn set_protocol_outcomes(app: &AppHandle, outcomes: &ProtocolOutcomes) -> Result<(), Error> {
+    let store = app.store(CONFIG_PATH)?;
+    store.set(PROTOCOL_OUTCOMES, json!(outcomes));
     Ok(())
 }
 
@@ -124,11 +187,133 @@ pub fn init_rules_files(app: &AppHandle) -> Result<(), Error> {
     if !std::path::Path::new(&custom_proxy_path).exists() {
         File::create(custom_proxy_path)?;
     }
-    let community_proxy_path = resolve_store_path(app, COMMUNITY_PROXY_RULES_PATH)?;
-    if !std::path::Path::new(&community_proxy_path).exists() {
-        File::create(community_proxy_path)?;
+    let combined_direct_path = resolve_store_path(app, COMBINED_DIRECT_RULES_PATH)?;
+    if !std::path::Path::new(&combined_direct_path).exists() {
+        File::create(combined_direct_path)?;
+    }
+    let block_path = resolve_store_path(app, BLOCK_RULES_PATH)?;
+    if !std::path::Path::new(&block_path).exists() {
+        File::create(block_path)?;
     }
+    let combined_block_path = resolve_store_path(app, COMBINED_BLOCK_RULES_PATH)?;
+    if !std::path::Path::new(&combined_block_path).exists() {
+        File::create(combined_block_path)?;
+    }
+    init_rule_source_files(app)?;
+    init_policy_files(app)?;
+
+    Ok(())
+}
 
+pub fn init_rule_source_files(app: &AppHandle) -> Result<(), Error> {
+    let sources_dir = resolve_store_path(app, RULE_SOURCES_DIR)?;
+    if !std::path::Path::new(&sources_dir).exists() {
+        std::fs::create_dir_all(&sources_dir)?;
+    }
+    for source in get_rule_sources(app)? {
+        let source_path = resolve_store_path(app, source.cache_path())?;
+        if std::path::Path::new(&source_path).exists() {
+            continue;
+        }
+        // keep the list downloaded by older versions until the first refresh
+        let legacy_path = resolve_store_path(app, COMMUNITY_PROXY_RULES_PATH)?;
+        if source.id == COMMUNITY_SOURCE_ID && std::path::Path::new(&legacy_path).exists() {
+            std::fs::rename(legacy_path, source_path)?;
+        } else {
+            File::create(source_path)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn get_policies(app: &AppHandle) -> Result<Vec<Policy>, Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let data = store.get(POLICIES);
+    if let Some(data) = data {
+        let policies = serde_json::from_value(data)?;
+        return Ok(policies);
+    }
+    Ok(Vec::new())
+}
+
+pub fn set_policies(app: &AppHandle, policies: &[Policy]) -> Result<(), Error> {
+    let removed: Vec<Policy> = get_policies(app)?
+        .into_iter()
+        .filter(|x| !policies.iter().any(|policy| policy.name == x.name))
+        .collect();
+    let store = app.store(CONFIG_PATH)?;
+    store.set(POLICIES, json!(policies));
+    // a removed policy takes its rules along, adding the name again starts empty
+    for policy in removed {
+        let rules_path = resolve_store_path(app, policy.rules_path())?;
+        if rules_path.exists() {
+            std::fs::remove_file(rules_path)?;
+        }
+        history::forget(app, &policy.rules_path())?;
+    }
+    init_policy_files(app)?;
+    Ok(())
+}
+
+pub fn get_server_groups(app: &AppHandle) -> Result<Vec<ServerGroup>, Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let data = store.get(SERVER_GROUPS);
+    if let Some(data) = data {
+        let groups = serde_json::from_value(data)?;
+        return Ok(groups);
+    }
+    Ok(Vec::new())
+}
+
+pub fn set_server_groups(app: &AppHandle, groups: &[ServerGroup]) -> Result<(), Error> {
+    let store = app.store(CONFIG_PATH)?;
+    store.set(SERVER_GROUPS, json!(groups));
+    Ok(())
+}
+
+pub fn get_profiles(app: &AppHandle) -> Result<Vec<Profile>, Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let data = store.get(PROFILES);
+    if let Some(data) = data {
+        let profiles = serde_json::from_value(data)?;
+        return Ok(profiles);
+    }
+    Ok(Vec::new())
+}
+
+pub fn set_profiles(app: &AppHandle, profiles: &[Profile]) -> Result<(), Error> {
+    let store = app.store(CONFIG_PATH)?;
+    store.set(PROFILES, json!(profiles));
+    Ok(())
+}
+
+pub fn get_network_settings(app: &AppHandle) -> Result<NetworkSettings, Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let data = store.get(NETWORK_SETTINGS);
+    if let Some(data) = data {
+        let settings = serde_json::from_value(data)?;
+        return Ok(settings);
+    }
+    Ok(NetworkSettings::default())
+}
+
+pub fn set_network_settings(app: &AppHandle, settings: &NetworkSettings) -> Result<(), Error> {
+    let store = app.store(CONFIG_PATH)?;
+    store.set(NETWORK_SETTINGS, json!(settings));
+    Ok(())
+}
+
+pub fn init_policy_files(app: &AppHandle) -> Result<(), Error> {
+    let policies_dir = resolve_store_path(app, POLICIES_DIR)?;
+    if !std::path::Path::new(&policies_dir).exists() {
+        std::fs::create_dir_all(&policies_dir)?;
+    }
+    for policy in get_policies(app)? {
+        let rules_path = resolve_store_path(app, policy.rules_path())?;
+        if !std::path::Path::new(&rules_path).exists() {
+            File::create(rules_path)?;
+        }
+    }
     Ok(())
 }
 
@@ -144,6 +329,61 @@ pub fn init_cert_files(app: &AppHandle) -> Result<(), Error> {
     Ok(())
 }
 
+/// Check that the values secc reads from a `config.json` have the expected
+/// shape.
+pub fn validate_config(config: &Value) -> Result<(), String> {
+    let config = config
+        .as_object()
+        .ok_or(format!("{} must hold a json object", CONFIG_PATH))?;
+    let check = |key: &str, result: Result<(), serde_json::Error>| {
+        result.map_err(|e| format!("`{}` in {} is invalid: {}", key, CONFIG_PATH, e))
+    };
+    for (key, value) in config {
+        let value = value.clone();
+        match key.as_str() {
+            SERVERS => match serde_json::from_value::<Vec<ServerInfo>>(value) {
+                Ok(servers) => {
+                    for server in servers.iter() {
+                        server.validate().map_err(|e| {
+                            format!("`{}` in {} is invalid: {}", key, CONFIG_PATH, e)
+                        })?;
+                    }
+                }
+                Err(e) => check(key, Err(e))?,
+            },
+            SERVER_GROUPS => check(
+                key,
+                serde_json::from_value::<Vec<ServerGroup>>(value).map(|_| ()),
+            )?,
+            POLICIES => check(
+                key,
+                serde_json::from_value::<Vec<Policy>>(value).map(|_| ()),
+            )?,
+            RULE_SOURCES => check(
+                key,
+                serde_json::from_value::<Vec<RuleSource>>(value).map(|_| ()),
+            )?,
+            PROFILES => check(
+                key,
+                serde_json::from_value::<Vec<Profile>>(value).map(|_| ()),
+            )?,
+            NETWORK_SETTINGS => check(
+                key,
+                serde_json::from_value::<NetworkSettings>(value).map(|_| ()),
+            )?,
+            SOCKS_ADDR | HTTP_ADDR => {
+                check(key, serde_json::from_value::<AddrInfo>(value).map(|_| ()))?
+            }
+            ACTIVE_SERVER | ACTIVE_PROFILE | ACCESS_MODE | BIND_MODE | PROTOCOL_MODE => {
+                check(key, serde_json::from_value::<String>(value).map(|_| ()))?
+            }
+            SCHEMA_VERSION => check(key, serde_json::from_value::<u64>(value).map(|_| ()))?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 pub fn get_config_path(app: &AppHandle, sub_path: &str) -> Result<PathBuf, Error> {
     let path = resolve_store_path(app, sub_path)?;
     Ok(path)
@@ -159,7 +399,7 @@ pub fn get_address(app: &AppHandle, config_key: &str) -> Result<Option<AddrInfo>
     let store = app.store(CONFIG_PATH)?;
     let data = store.get(config_key);
     if let Some(data) = data {
-        let config = AddrInfo::from_json(&data);
+        let config = serde_json::from_value(data)?;
         return Ok(Some(config));
     }
     Ok(None)
@@ -169,7 +409,7 @@ pub fn get_servers(app: &AppHandle) -> Result<Option<Vec<ServerInfo>>, Error> {
     let store = app.store(CONFIG_PATH)?;
     let data = store.get(SERVERS);
     if let Some(data) = data {
-        let config = ServerInfo::from_json_array(&data);
+        let config = serde_json::from_value(data)?;
         return Ok(Some(config));
     }
     Ok(None)
@@ -179,7 +419,7 @@ pub fn get_server(app: &AppHandle, host: &str) -> Result<Option<ServerInfo>, Err
     let store = app.store(CONFIG_PATH)?;
     let data = store.get(SERVERS);
     if let Some(data) = data {
-        let config = ServerInfo::from_json_array(&data);
+        let config: Vec<ServerInfo> = serde_json::from_value(data)?;
         let server = config.iter().find(|x| x.host == host);
         if let Some(server) = server {
             return Ok(Some(server.clone()));
@@ -188,17 +428,30 @@ pub fn get_server(app: &AppHandle, host: &str) -> Result<Option<ServerInfo>, Err
     Ok(None)
 }
 
+/// Replace the server list, keeping the new list in its history.
+pub fn set_servers(
+    app: &AppHandle,
+    servers: &[ServerInfo],
+    source: HistorySource,
+) -> Result<(), Error> {
+    let store = app.store(CONFIG_PATH)?;
+    let content = serde_json::to_string_pretty(servers)?;
+    history::record(app, history::SERVERS_SUBJECT, &content, source);
+    store.set(SERVERS, json!(servers));
+    Ok(())
+}
+
 pub fn add_server(app: &AppHandle, server: ServerInfo) -> Result<Option<()>, Error> {
     let store = app.store(CONFIG_PATH)?;
     let data = store.get(SERVERS);
     if let Some(data) = data {
-        let mut config = ServerInfo::from_json_array(&data);
+        let mut config: Vec<ServerInfo> = serde_json::from_value(data)?;
         config.push(server);
-        store.set(SERVERS, json!(config));
+        set_servers(app, &config, HistorySource::Ui)?;
         return Ok(Some(()));
     }
     let servers = vec![server];
-    store.set(SERVERS, json!(servers));
+    set_servers(app, &servers, HistorySource::Ui)?;
     Ok(None)
 }
 
@@ -206,11 +459,11 @@ pub fn update_server(app: &AppHandle, server: ServerInfo) -> Result<Option<()>,
     let store = app.store(CONFIG_PATH)?;
     let data = store.get(SERVERS);
     if let Some(data) = data {
-        let mut config = ServerInfo::from_json_array(&data);
+        let mut config: Vec<ServerInfo> = serde_json::from_value(data)?;
         let index = config.iter().position(|x| x.host == server.host);
         if let Some(index) = index {
             config[index] = server;
-            store.set(SERVERS, json!(config));
+            set_servers(app, &config, HistorySource::Ui)?;
             return Ok(Some(()));
         }
     }
@@ -221,11 +474,11 @@ pub fn delete_server(app: &AppHandle, host: &str) -> Result<Option<()>, Error> {
     let store = app.store(CONFIG_PATH)?;
     let data = store.get(SERVERS);
     if let Some(data) = data {
-        let mut config = ServerInfo::from_json_array(&data);
+        let mut config: Vec<ServerInfo> = serde_json::from_value(data)?;
         let index = config.iter().position(|x| x.host == host);
         if let Some(index) = index {
             config.remove(index);
-            store.set(SERVERS, json!(config));
+            set_servers(app, &config, HistorySource::Ui)?;
             return Ok(Some(()));
         }
     }
@@ -241,19 +494,22 @@ pub fn set_str_config(app: &AppHandle, mode: &str, mode_value: &str) -> Result<(
 pub fn get_value_by_key(app: &AppHandle, key: &str) -> Result<Option<String>, Error> {
     let store = app.store(CONFIG_PATH)?;
     let data = store.get(key);
-    if let Some(data) = data {
-        let mode_value = data.as_str().unwrap().to_string();
-        return Ok(Some(mode_value));
-    }
-    Ok(None)
+    Ok(data.and_then(|x| x.as_str().map(|x| x.to_string())))
 }
 
-pub fn set_rules(app: &AppHandle, rule_path: &str, rules: &str) -> Result<(), Error> {
+pub fn set_rules(
+    app: &AppHandle,
+    rule_path: &str,
+    rules: &str,
+    source: HistorySource,
+) -> Result<(), Error> {
+    history::record(app, rule_path, rules, source);
     let path = resolve_store_path(app, rule_path)?;
+    watch::record_write(app, &path, rules.as_bytes());
     let mut file = File::create(path)?;
     file.write_all(rules.as_bytes())?;
     file.flush()?;
-    if rule_path == CUSTOM_PROXY_RULES_PATH {
+    if [CUSTOM_PROXY_RULES_PATH, DIRECT_RULES_PATH, BLOCK_RULES_PATH].contains(&rule_path) {
         combine_proxy_rules(app)?;
     }
 
@@ -278,32 +534,36 @@ pub fn _append_direct_rules(app: &AppHandle, rules: &str) -> Result<(), Error> {
 }
 
 pub fn combine_proxy_rules(app: &AppHandle) -> Result<(), Error> {
-    let custom_proxy_path = resolve_store_path(app, CUSTOM_PROXY_RULES_PATH)?;
-    let community_proxy_path = resolve_store_path(app, COMMUNITY_PROXY_RULES_PATH)?;
-    let combine_proxy_path = resolve_store_path(app, PROXY_RULES_PATH)?;
-
-    // Read contents of the first file
-    let mut custom_file = File::open(custom_proxy_path)?;
-    let mut custom_content = String::new();
-    custom_file.read_to_string(&mut custom_content)?;
-
-    // Read contents of the second file
-    let mut community_file = File::open(community_proxy_path)?;
-    let mut community_content = String::new();
-    community_file.read_to_string(&mut community_content)?;
+    let custom_proxy = get_rules(app, CUSTOM_PROXY_RULES_PATH)?;
+    let custom_direct = get_rules(app, DIRECT_RULES_PATH)?;
+    let custom_block = get_rules(app, BLOCK_RULES_PATH)?;
+    let mut sources = Vec::new();
+    for source in get_rule_sources(app)?.iter().filter(|x| x.enabled) {
+        // a source that has never been downloaded contributes nothing
+        let content = get_rules(app, &source.cache_path()).unwrap_or_default();
+        sources.push((source.target, content));
+    }
+    let combined = merge::merge_rules(&custom_proxy, &custom_direct, &custom_block, &sources);
 
-    // Combine contents with a newline separator
-    let combined_content = format!(
-        "{}\n{}",
-        custom_content.trim_end(),
-        community_content.trim_end()
-    );
+    let combine_proxy_path = resolve_store_path(app, PROXY_RULES_PATH)?;
+    write_atomic(&combine_proxy_path, combined.proxy.as_bytes())?;
+    let combine_direct_path = resolve_store_path(app, COMBINED_DIRECT_RULES_PATH)?;
+    write_atomic(&combine_direct_path, combined.direct.as_bytes())?;
+    let combine_block_path = resolve_store_path(app, COMBINED_BLOCK_RULES_PATH)?;
+    write_atomic(&combine_block_path, combined.block.as_bytes())?;
 
-    // Write the combined content to the output file
-    let mut output_file = File::create(combine_proxy_path)?;
-    output_file.write_all(combined_content.as_bytes())?;
-    output_file.flush()?;
+    Ok(())
+}
 
+// write to a sibling temp file and rename it over the target, so readers never
+// see a partially written file
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
     Ok(())
 }
 
@@ -314,6 +574,27 @@ pub fn set_cert(app: &AppHandle, cert_sub_path: &str, cert: &str) -> Result<(),
     Ok(())
 }
 
+/// Store-relative path of the cert of a server. Every server has its own,
+/// servers used by policies run next to the active one. Files are named by a
+/// hash of the host, hosts aren't safe file names (`:` of IPv6 on Windows).
+pub fn server_cert_path(host: &str) -> String {
+    let hash: String = Sha256::digest(host.as_bytes())[..8]
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect();
+    format!("{}/{}.pem", SERVER_CERTS_DIR, hash)
+}
+
+pub fn set_server_cert(app: &AppHandle, server: &ServerInfo) -> Result<PathBuf, Error> {
+    let certs_dir = resolve_store_path(app, SERVER_CERTS_DIR)?;
+    if !std::path::Path::new(&certs_dir).exists() {
+        std::fs::create_dir_all(&certs_dir)?;
+    }
+    let cert_sub_path = server_cert_path(&server.host);
+    set_cert(app, &cert_sub_path, &server.cert)?;
+    get_config_path(app, &cert_sub_path)
+}
+
 pub fn get_cert(app: &AppHandle, cert_sub_path: &str) -> Result<String, Error> {
     let cert_path = resolve_store_path(app, cert_sub_path)?;
     let mut cert_file = File::open(cert_path)?;
@@ -1,61 +1,241 @@
 use std::sync::Mutex;
 
 use crate::command::{self};
-use crate::state::{self, AccessMode, AgentState, BindMode};
+use crate::connection;
+use crate::group;
+use crate::profile;
+use crate::rules::refresh::time_ago;
+use crate::server::AddrInfo;
+use crate::state::{AccessMode, AgentState, BindMode, ConnectionStatus, HealthState, ProtocolMode};
+use crate::store;
 use anyhow::{Error, Ok};
 use tauri::menu::PredefinedMenuItem;
-use tauri::tray::TrayIconEvent;
+use tauri::tray::{MouseButtonState, TrayIconEvent};
 use tauri::{
-    AppHandle,
+    AppHandle, Wry,
     image::Image,
-    menu::{CheckMenuItem, MenuBuilder, MenuItem},
+    menu::{CheckMenuItem, MenuBuilder, MenuItem, Submenu, SubmenuBuilder},
     tray::TrayIconBuilder,
 };
-use tauri::{Emitter, Manager};
+use tauri::{Emitter, Listener, Manager, Theme};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 
 pub const APP_TRAY_ID: &str = "secc-tray";
+const SERVER_ITEM_PREFIX: &str = "server:";
+const GROUP_ITEM_PREFIX: &str = "group:";
+const PROFILE_ITEM_PREFIX: &str = "profile:";
 
-pub fn change_tray_icon(app: &AppHandle, active: bool) -> Result<(), Error> {
-    let icon_bytes = {
-        match active {
-            true => Some(include_bytes!("../icons/tray-icon-active.png").to_vec()),
-            false => Some(include_bytes!("../icons/tray-icon-inactive.png").to_vec()),
+/// Marks drawn over the tray icon. Their shapes differ as well as their
+/// colors, macOS draws template icons in a single color.
+#[derive(Debug, Clone, Copy)]
+enum Badge {
+    /// A ring.
+    Connecting,
+    /// A dot.
+    Error,
+    /// A square.
+    Global,
+}
+
+impl Badge {
+    fn color(self) -> [u8; 4] {
+        match self {
+            Badge::Connecting => [245, 166, 35, 255],
+            Badge::Error => [229, 57, 53, 255],
+            Badge::Global => [10, 156, 253, 255],
         }
+    }
+}
+
+/// Pick the tray icon for the platform, the theme, the connection status and
+/// the access mode.
+pub fn update_tray_icon(app: &AppHandle) -> Result<(), Error> {
+    let Some(tray) = app.tray_by_id(APP_TRAY_ID) else {
+        return Ok(());
     };
-    if let Some(tray) = app.tray_by_id(APP_TRAY_ID) {
-        if let Some(icon_bytes) = icon_bytes {
-            tray.set_icon(Some(Image::from_bytes(&icon_bytes)?))?;
-            tray.set_icon_as_template(true)?;
+    let status = connection::get(app);
+    let active = matches!(
+        status,
+        ConnectionStatus::Connecting | ConnectionStatus::Connected
+    );
+    let badge = match status {
+        ConnectionStatus::Connecting => Some(Badge::Connecting),
+        ConnectionStatus::Error => Some(Badge::Error),
+        ConnectionStatus::Connected
+            if command::get_access_mode(app.clone()) == AccessMode::Proxy =>
+        {
+            Some(Badge::Global)
         }
-    }
+        _ => None,
+    };
+    let icon = Image::from_bytes(base_icon(app, active))?;
+    let icon = match badge {
+        Some(badge) => draw_badge(&icon, badge),
+        None => icon,
+    };
+    tray.set_icon(Some(icon))?;
+    // template icons follow the menu bar on macOS, other trays show the
+    // icon as it is
+    tray.set_icon_as_template(cfg!(target_os = "macos"))?;
     Ok(())
 }
 
+#[cfg(target_os = "macos")]
+fn base_icon(_app: &AppHandle, active: bool) -> &'static [u8] {
+    match active {
+        true => include_bytes!("../icons/tray-icon-active.png"),
+        false => include_bytes!("../icons/tray-icon-inactive.png"),
+    }
+}
+
+/// The light icons are drawn dark for light panels and the other way round,
+/// the panel is assumed to follow the system theme.
+#[cfg(not(target_os = "macos"))]
+fn base_icon(app: &AppHandle, active: bool) -> &'static [u8] {
+    let theme = app
+        .get_webview_window("main")
+        .and_then(|window| window.theme().ok());
+    match (theme == Some(Theme::Light), active) {
+        (true, true) => include_bytes!("../icons/tray-icon-light-active.png"),
+        (true, false) => include_bytes!("../icons/tray-icon-light-inactive.png"),
+        (false, true) => include_bytes!("../icons/tray-icon-dark-active.png"),
+        (false, false) => include_bytes!("../icons/tray-icon-dark-inactive.png"),
+    }
+}
+
+/// Draw a badge into the bottom right corner, with a transparent gap between
+/// it and the icon.
+fn draw_badge(icon: &Image, badge: Badge) -> Image<'static> {
+    let (width, height) = (icon.width() as i64, icon.height() as i64);
+    let mut rgba = icon.rgba().to_vec();
+    let radius = width.min(height) / 4;
+    let gap = (radius / 4).max(1);
+    let (center_x, center_y) = (width - radius - gap, height - radius - gap);
+    for y in (center_y - radius - gap).max(0)..height {
+        for x in (center_x - radius - gap).max(0)..width {
+            let (dx, dy) = (x - center_x, y - center_y);
+            let distance = match badge {
+                Badge::Global => dx.abs().max(dy.abs()),
+                _ => ((dx * dx + dy * dy) as f64).sqrt().round() as i64,
+            };
+            let inside = match badge {
+                Badge::Connecting => distance <= radius && distance > radius * 2 / 3,
+                _ => distance <= radius,
+            };
+            let index = ((y * width + x) * 4) as usize;
+            if inside {
+                rgba[index..index + 4].copy_from_slice(&badge.color());
+            } else if distance <= radius + gap {
+                rgba[index..index + 4].copy_from_slice(&[0, 0, 0, 0]);
+            }
+        }
+    }
+    Image::new_owned(rgba, width as u32, height as u32)
+}
+
+/// Items whose state follows the store. They are synced whenever secc
+/// changes modes, servers or profiles, instead of tracking every click. On
+/// Linux these events are the only path, tray icon events aren't emitted
+/// there; elsewhere the menu is synced again when the icon is pressed.
+#[derive(Clone)]
+struct TrayMenu {
+    auto_model: CheckMenuItem<Wry>,
+    proxy_model: CheckMenuItem<Wry>,
+    direct_model: CheckMenuItem<Wry>,
+    socks_model: CheckMenuItem<Wry>,
+    http_model: CheckMenuItem<Wry>,
+    auto_protocol: CheckMenuItem<Wry>,
+    quic_protocol: CheckMenuItem<Wry>,
+    tcp_protocol: CheckMenuItem<Wry>,
+    servers_menu: Submenu<Wry>,
+    groups_menu: Submenu<Wry>,
+    profiles_menu: Submenu<Wry>,
+    rules_updated: MenuItem<Wry>,
+}
+
+impl TrayMenu {
+    /// Derive every checked state and label from the store.
+    fn sync(&self, app: &AppHandle) -> Result<(), Error> {
+        let agent_state = {
+            let agent_state = app.state::<Mutex<AgentState>>();
+            let agent_state = agent_state.lock().unwrap();
+            agent_state.get()
+        };
+        let access_mode = command::get_access_mode(app.clone());
+        set_choice(
+            &self.auto_model,
+            agent_state && access_mode == AccessMode::Auto,
+        )?;
+        set_choice(
+            &self.proxy_model,
+            agent_state && access_mode == AccessMode::Proxy,
+        )?;
+        set_choice(&self.direct_model, !agent_state)?;
+        let bind_mode = command::get_bind_mode(app.clone());
+        set_choice(&self.socks_model, bind_mode == BindMode::Socks)?;
+        set_choice(&self.http_model, bind_mode == BindMode::Http)?;
+        let protocol_mode = command::get_protocol_mode(app.clone());
+        set_choice(&self.auto_protocol, protocol_mode == ProtocolMode::Auto)?;
+        set_choice(&self.quic_protocol, protocol_mode == ProtocolMode::Quic)?;
+        set_choice(&self.tcp_protocol, protocol_mode == ProtocolMode::Tcp)?;
+        refresh_servers_menu(app, &self.servers_menu)?;
+        refresh_groups_menu(app, &self.groups_menu)?;
+        refresh_profiles_menu(app, &self.profiles_menu)?;
+        self.rules_updated.set_text(rules_updated_label(app)?)?;
+        Ok(())
+    }
+}
+
 pub fn build_tray(app: &AppHandle) -> Result<(), Error> {
-    let icon_bytes = include_bytes!("../icons/tray-icon-inactive.png");
     let setting = MenuItem::with_id(app, "setting", "Settings", true, None::<&str>)?;
     let quit = PredefinedMenuItem::quit(app, Some("Quit"))?;
-    let auto_model =
-        CheckMenuItem::with_id(app, "auto_model", "Auto Model", false, true, None::<&str>)?;
-    let proxy_model = CheckMenuItem::with_id(
+    let choice =
+        |id: &str, text: &str| CheckMenuItem::with_id(app, id, text, true, false, None::<&str>);
+    let auto_model = choice("auto_model", "Auto Model")?;
+    let proxy_model = choice("proxy_model", "Global Model")?;
+    let direct_model = choice("direct_model", "Direct Model")?;
+    let socks_model = choice("socks_model", "Socks")?;
+    let http_model = choice("http_model", "Http")?;
+    let auto_protocol = choice("auto_protocol", "Auto")?;
+    let quic_protocol = choice("quic_protocol", "Quic")?;
+    let tcp_protocol = choice("tcp_protocol", "Tcp")?;
+    let protocol_menu = SubmenuBuilder::with_id(app, "protocol", "Protocol")
+        .item(&auto_protocol)
+        .item(&quic_protocol)
+        .item(&tcp_protocol)
+        .build()?;
+    let servers_menu = SubmenuBuilder::with_id(app, "servers", "Servers").build()?;
+    let groups_menu = SubmenuBuilder::with_id(app, "server_groups", "Server Groups").build()?;
+    let profiles_menu = SubmenuBuilder::with_id(app, "profiles", "Profiles").build()?;
+    let rules_updated = MenuItem::with_id(app, "rules_updated", "", false, None::<&str>)?;
+    let refresh_rules = MenuItem::with_id(
         app,
-        "proxy_model",
-        "Global Model",
+        "refresh_rules",
+        "Refresh Rule Sources Now",
         true,
-        false,
         None::<&str>,
     )?;
-    let direct_model = CheckMenuItem::with_id(
+    let edit_rules = MenuItem::with_id(app, "edit_rules", "Edit Rules...", true, None::<&str>)?;
+    let rules_menu = SubmenuBuilder::with_id(app, "rules", "Rules")
+        .item(&rules_updated)
+        .separator()
+        .item(&refresh_rules)
+        .item(&edit_rules)
+        .build()?;
+    let copy_proxy_env = MenuItem::with_id(
         app,
-        "direct_model",
-        "Direct Model",
+        "copy_proxy_env",
+        "Copy Proxy Environment Command",
+        true,
+        None::<&str>,
+    )?;
+    let check_health = MenuItem::with_id(
+        app,
+        "check_health",
+        "Check Server Health",
         true,
-        false,
         None::<&str>,
     )?;
-    let socks_model =
-        CheckMenuItem::with_id(app, "socks_model", "Socks", false, true, None::<&str>)?;
-    let http_model = CheckMenuItem::with_id(app, "http_model", "Http", true, false, None::<&str>)?;
     let menu = MenuBuilder::new(app)
         .item(&auto_model)
         .item(&proxy_model)
@@ -63,181 +243,349 @@ pub fn build_tray(app: &AppHandle) -> Result<(), Error> {
         .separator()
         .item(&socks_model)
         .item(&http_model)
+        .item(&protocol_menu)
+        .separator()
+        .item(&servers_menu)
+        .item(&groups_menu)
+        .item(&profiles_menu)
+        .item(&rules_menu)
+        .separator()
+        .item(&copy_proxy_env)
+        .item(&check_health)
         .separator()
         .item(&setting)
         .item(&quit)
         .build()?;
 
-    let auto_model_clone = auto_model.clone();
-    let proxy_model_clone = proxy_model.clone();
-    let direct_model_clone = direct_model.clone();
-    let socks_model_clone = socks_model.clone();
-    let http_model_clone = http_model.clone();
+    let tray_menu = TrayMenu {
+        auto_model,
+        proxy_model,
+        direct_model,
+        socks_model,
+        http_model,
+        auto_protocol,
+        quic_protocol,
+        tcp_protocol,
+        servers_menu,
+        groups_menu,
+        profiles_menu,
+        rules_updated,
+    };
+    tray_menu.sync(app)?;
+    for event in [
+        command::MODES_CHANGED_EVENT,
+        "refresh_servers",
+        profile::PROFILE_CHANGED_EVENT,
+        // the group submenus show the health of their members
+        group::GROUP_HEALTH_EVENT,
+    ] {
+        let app_handle = app.clone();
+        let tray_menu = tray_menu.clone();
+        app.listen(event, move |_| {
+            if let Err(e) = tray_menu.sync(&app_handle) {
+                eprintln!("sync tray menu error: {:?}", e);
+            }
+        });
+    }
+
+    let click_tray_menu = tray_menu.clone();
     TrayIconBuilder::with_id(APP_TRAY_ID)
-        .icon_as_template(true)
+        .icon_as_template(cfg!(target_os = "macos"))
         .tooltip("Secure Connect")
         .on_tray_icon_event(move |tray_icon, event| {
+            // Click fires for both the press and the release of any button,
+            // sync once on the press, before the menu shows
             if let TrayIconEvent::Click {
-                id: _,
-                position: _,
-                rect: _,
-                button: _,
-                button_state: _,
+                button_state: MouseButtonState::Down,
+                ..
             } = event
+                && let Err(e) = click_tray_menu.sync(tray_icon.app_handle())
             {
-                let app_handle = tray_icon.app_handle().clone();
-                let agent_state = {
-                    let agent_state = app_handle.state::<Mutex<AgentState>>();
-                    let agent_state = agent_state.lock().unwrap();
-                    agent_state.get()
-                };
-
-                if !agent_state {
-                    toggle_model(
-                        &auto_model_clone,
-                        &proxy_model_clone,
-                        &direct_model_clone,
-                        "direct_model",
-                    );
-                } else {
-                    let access_mode = command::get_access_mode(app_handle.clone());
-                    match access_mode {
-                        AccessMode::Auto => {
-                            toggle_model(
-                                &auto_model_clone,
-                                &proxy_model_clone,
-                                &direct_model_clone,
-                                "auto_model",
-                            );
-                        }
-                        AccessMode::Proxy => {
-                            toggle_model(
-                                &auto_model_clone,
-                                &proxy_model_clone,
-                                &direct_model_clone,
-                                "proxy_model",
-                            );
-                        }
-                    }
+                eprintln!("sync tray menu error: {:?}", e);
+            }
+        })
+        .on_menu_event(move |app, event| {
+            match event.id.as_ref() {
+                "auto_model" => {
+                    println!("auto model menu item was clicked");
+                    command::switch_access_mode(app.clone(), AccessMode::Auto);
+                    open_if_closed(app);
                 }
-
-                let bind_mode = command::get_bind_mode(app_handle);
-                match bind_mode {
-                    BindMode::Socks => {
-                        toggle_protocol(&socks_model_clone, &http_model_clone, "socks_model");
+                "proxy_model" => {
+                    println!("proxy model menu item was clicked");
+                    command::switch_access_mode(app.clone(), AccessMode::Proxy);
+                    open_if_closed(app);
+                }
+                "direct_model" => {
+                    println!("direct model menu item was clicked");
+                    command::close_secc(app.clone());
+                }
+                "socks_model" => {
+                    println!("socks proxy model menu item was clicked");
+                    command::switch_bind_mode(app.clone(), BindMode::Socks);
+                }
+                "http_model" => {
+                    println!("http proxy model menu item was clicked");
+                    command::switch_bind_mode(app.clone(), BindMode::Http);
+                }
+                "auto_protocol" => {
+                    println!("auto protocol menu item was clicked");
+                    command::switch_protocol_mode(app.clone(), ProtocolMode::Auto);
+                }
+                "quic_protocol" => {
+                    println!("quic protocol menu item was clicked");
+                    command::switch_protocol_mode(app.clone(), ProtocolMode::Quic);
+                }
+                "tcp_protocol" => {
+                    println!("tcp protocol menu item was clicked");
+                    command::switch_protocol_mode(app.clone(), ProtocolMode::Tcp);
+                }
+                "refresh_rules" => {
+                    println!("refresh rules menu item was clicked");
+                    command::refresh_rule_sources(app.clone());
+                }
+                "edit_rules" => {
+                    println!("edit rules menu item was clicked");
+                    show_main_window(app);
+                    app.emit("navigate", "/proxy-rules").unwrap();
+                }
+                "copy_proxy_env" => {
+                    println!("copy proxy env menu item was clicked");
+                    let copied = proxy_env_command(app)
+                        .and_then(|x| app.clipboard().write_text(x).map_err(Error::from));
+                    if let Err(e) = copied {
+                        eprintln!("copy proxy env command error: {:?}", e);
                     }
-                    BindMode::Http => {
-                        toggle_protocol(&socks_model_clone, &http_model_clone, "http_model");
+                }
+                "check_health" => {
+                    println!("check health menu item was clicked");
+                    command::check_group_health(app.clone());
+                }
+                "setting" => {
+                    println!("setting menu item was clicked");
+                    open_main_window(app);
+                }
+                "quit" => {
+                    println!("quit menu item was clicked");
+                    app.exit(0);
+                }
+                id if id.starts_with(SERVER_ITEM_PREFIX) => {
+                    let host = &id[SERVER_ITEM_PREFIX.len()..];
+                    println!("server {} menu item was clicked", host);
+                    select_server(app, host);
+                }
+                id if id.starts_with(GROUP_ITEM_PREFIX) => {
+                    let name = &id[GROUP_ITEM_PREFIX.len()..];
+                    println!("server group {} menu item was clicked", name);
+                    select_server(app, name);
+                }
+                id if id.starts_with(PROFILE_ITEM_PREFIX) => {
+                    let name = &id[PROFILE_ITEM_PREFIX.len()..];
+                    println!("profile {} menu item was clicked", name);
+                    if let Err(e) = profile::switch(app, name) {
+                        eprintln!("switch profile error: {:?}", e);
                     }
                 }
+                _ => {
+                    println!("menu item {:?} not handled", event.id);
+                }
             }
-        })
-        .on_menu_event(move |app, event| match event.id.as_ref() {
-            "auto_model" => {
-                println!("auto model menu item was clicked");
-                toggle_model(&auto_model, &proxy_model, &direct_model, "auto_model");
-                command::switch_access_mode(app.clone(), state::AccessMode::Auto);
-            }
-            "proxy_model" => {
-                println!("socks model menu item was clicked");
-                toggle_model(&auto_model, &proxy_model, &direct_model, "proxy_model");
-                command::switch_access_mode(app.clone(), state::AccessMode::Proxy);
-            }
-            "direct_model" => {
-                println!("direct model menu item was clicked");
-                toggle_model(&auto_model, &proxy_model, &direct_model, "direct_model");
-                command::close_secc(app.clone());
-            }
-            "socks_model" => {
-                println!("socks proxy model menu item was clicked");
-                toggle_protocol(&socks_model, &http_model, "socks_model");
-                command::switch_bind_mode(app.clone(), state::BindMode::Socks);
-            }
-            "http_model" => {
-                println!("http proxy model menu item was clicked");
-                toggle_protocol(&socks_model, &http_model, "http_model");
-                command::switch_bind_mode(app.clone(), state::BindMode::Http);
-            }
-            "setting" => {
-                println!("setting menu item was clicked");
-                open_main_window(app);
-            }
-            "quit" => {
-                println!("quit menu item was clicked");
-                app.exit(0);
-            }
-            _ => {
-                println!("menu item {:?} not handled", event.id);
+            // a click toggles a check item on its own, put back what the
+            // store says
+            if let Err(e) = tray_menu.sync(app) {
+                eprintln!("sync tray menu error: {:?}", e);
             }
         })
         .menu(&menu)
-        .icon(Image::from_bytes(icon_bytes)?)
+        .icon(Image::from_bytes(base_icon(app, false))?)
         .build(app)?;
+    update_tray_icon(app)?;
 
     Ok(())
 }
 
-fn open_main_window(app: &AppHandle) {
-    #[cfg(target_os = "macos")]
-    {
-        tauri::AppHandle::show(app).unwrap();
-        app.emit("refresh", ()).unwrap();
+/// A checked choice can't be clicked again to uncheck it.
+fn set_choice(item: &CheckMenuItem<Wry>, checked: bool) -> Result<(), Error> {
+    item.set_checked(checked)?;
+    item.set_enabled(!checked)?;
+    Ok(())
+}
+
+/// Picking a mode while secc is off turns it on, when there is a server.
+fn open_if_closed(app: &AppHandle) {
+    let agent_state = {
+        let agent_state = app.state::<Mutex<AgentState>>();
+        let agent_state = agent_state.lock().unwrap();
+        agent_state.get()
+    };
+    if agent_state {
+        return;
+    }
+    match command::get_active_server(app.clone()) {
+        Some(server) if !server.is_empty() => command::open_secc(app.clone()),
+        _ => println!("no active server, secc stays off"),
     }
 }
 
-fn toggle_protocol<R: tauri::Runtime>(
-    socks_model: &CheckMenuItem<R>,
-    http_model: &CheckMenuItem<R>,
-    selected_id: &str,
-) {
-    match selected_id {
-        "socks_model" => {
-            socks_model.set_checked(true).unwrap();
-            socks_model.set_enabled(false).unwrap();
-            http_model.set_checked(false).unwrap();
-            http_model.set_enabled(true).unwrap();
-        }
-        "http_model" => {
-            http_model.set_checked(true).unwrap();
-            http_model.set_enabled(false).unwrap();
-            socks_model.set_checked(false).unwrap();
-            socks_model.set_enabled(true).unwrap();
-        }
-        _ => {}
+/// When the rule sources were last confirmed up to date.
+fn rules_updated_label(app: &AppHandle) -> Result<String, Error> {
+    let sources = store::get_rule_sources(app)?;
+    let statuses = store::get_rule_sources_status(app)?;
+    let last_update = sources
+        .iter()
+        .filter(|x| x.enabled)
+        .filter_map(|x| statuses.get(&x.id).and_then(|status| status.last_update))
+        .max();
+    Ok(match last_update {
+        Some(last_update) => format!("Updated {}", time_ago(last_update)),
+        None => "Never updated".to_string(),
+    })
+}
+
+/// Shell command pointing command line tools at the local listeners.
+fn proxy_env_command(app: &AppHandle) -> Result<String, Error> {
+    let socks = store::get_address(app, store::SOCKS_ADDR)?
+        .unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1080));
+    let http = store::get_address(app, store::HTTP_ADDR)?
+        .unwrap_or(AddrInfo::new("127.0.0.1".to_string(), 1081));
+    let http = format!("http://{}:{}", http.host, http.port);
+    let socks = format!("socks5://{}:{}", socks.host, socks.port);
+    #[cfg(target_os = "windows")]
+    return Ok(format!(
+        "$env:HTTP_PROXY=\"{0}\"; $env:HTTPS_PROXY=\"{0}\"; $env:ALL_PROXY=\"{1}\"",
+        http, socks
+    ));
+    #[cfg(not(target_os = "windows"))]
+    Ok(format!(
+        "export http_proxy={0} https_proxy={0} all_proxy={1}",
+        http, socks
+    ))
+}
+
+/// Make a server or server group the active target and connect through it,
+/// as picking it in the main window does.
+fn select_server(app: &AppHandle, host: &str) {
+    command::active_server(app.clone(), host);
+    command::open_secc(app.clone());
+    app.emit("refresh_servers", ()).unwrap();
+}
+
+/// Rebuild the servers submenu: one item per server, checked when it is the
+/// active target, with its latency when a health check measured it.
+fn refresh_servers_menu(app: &AppHandle, servers_menu: &Submenu<Wry>) -> Result<(), Error> {
+    for item in servers_menu.items()? {
+        servers_menu.remove(&item)?;
     }
+    let servers = store::get_servers(app)?.unwrap_or_default();
+    let active = store::get_value_by_key(app, store::ACTIVE_SERVER)?;
+    let health = app.state::<Mutex<HealthState>>();
+    let health = health.lock().unwrap();
+    for server in servers.iter() {
+        let name = server.alias.as_deref().unwrap_or(&server.host);
+        let label = match health.get().get(&server.host) {
+            Some(server_health) if server_health.healthy == Some(false) => {
+                format!("{} (down)", name)
+            }
+            Some(server_health) if server_health.latency.is_some() => format!(
+                "{} ({} ms)",
+                name,
+                server_health.latency.unwrap_or_default()
+            ),
+            _ => name.to_string(),
+        };
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("{}{}", SERVER_ITEM_PREFIX, server.host),
+            label,
+            true,
+            active.as_deref() == Some(server.host.as_str()),
+            None::<&str>,
+        )?;
+        servers_menu.append(&item)?;
+    }
+    servers_menu.set_enabled(!servers.is_empty())?;
+    Ok(())
 }
 
-fn toggle_model<R: tauri::Runtime>(
-    auto_model: &CheckMenuItem<R>,
-    proxy_model: &CheckMenuItem<R>,
-    direct_model: &CheckMenuItem<R>,
-    selected_id: &str,
-) {
-    match selected_id {
-        "auto_model" => {
-            auto_model.set_checked(true).unwrap();
-            auto_model.set_enabled(false).unwrap();
-            proxy_model.set_checked(false).unwrap();
-            proxy_model.set_enabled(true).unwrap();
-            direct_model.set_checked(false).unwrap();
-            direct_model.set_enabled(true).unwrap();
-        }
-        "proxy_model" => {
-            proxy_model.set_checked(true).unwrap();
-            proxy_model.set_enabled(false).unwrap();
-            auto_model.set_checked(false).unwrap();
-            auto_model.set_enabled(true).unwrap();
-            direct_model.set_checked(false).unwrap();
-            direct_model.set_enabled(true).unwrap();
-        }
-        "direct_model" => {
-            direct_model.set_checked(true).unwrap();
-            direct_model.set_enabled(false).unwrap();
-            auto_model.set_checked(false).unwrap();
-            auto_model.set_enabled(true).unwrap();
-            proxy_model.set_checked(false).unwrap();
-            proxy_model.set_enabled(true).unwrap();
-        }
-        _ => {}
+/// Rebuild the server groups submenu: one item per group, checked when it is
+/// the active target, labelled with the server it currently picks.
+fn refresh_groups_menu(app: &AppHandle, groups_menu: &Submenu<Wry>) -> Result<(), Error> {
+    for item in groups_menu.items()? {
+        groups_menu.remove(&item)?;
+    }
+    let groups = store::get_server_groups(app)?;
+    let servers = store::get_servers(app)?.unwrap_or_default();
+    let active = store::get_value_by_key(app, store::ACTIVE_SERVER)?;
+    let health = app.state::<Mutex<HealthState>>();
+    let health = health.lock().unwrap();
+    for server_group in groups.iter() {
+        let selected = server_group.select(&servers, health.get());
+        let label = match selected.first() {
+            Some(server) => match health.get().get(&server.host) {
+                Some(server_health) if server_health.healthy == Some(false) => {
+                    format!("{} (all servers down)", server_group.name)
+                }
+                Some(server_health) if server_health.latency.is_some() => format!(
+                    "{} ({}, {} ms)",
+                    server_group.name,
+                    server.alias.as_deref().unwrap_or(&server.host),
+                    server_health.latency.unwrap_or_default()
+                ),
+                _ => format!(
+                    "{} ({})",
+                    server_group.name,
+                    server.alias.as_deref().unwrap_or(&server.host)
+                ),
+            },
+            None => format!("{} (no servers)", server_group.name),
+        };
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("{}{}", GROUP_ITEM_PREFIX, server_group.name),
+            label,
+            true,
+            active.as_deref() == Some(server_group.name.as_str()),
+            None::<&str>,
+        )?;
+        groups_menu.append(&item)?;
+    }
+    groups_menu.set_enabled(!groups.is_empty())?;
+    Ok(())
+}
+
+/// Rebuild the profiles submenu, checking the active profile.
+fn refresh_profiles_menu(app: &AppHandle, profiles_menu: &Submenu<Wry>) -> Result<(), Error> {
+    for item in profiles_menu.items()? {
+        profiles_menu.remove(&item)?;
+    }
+    let profiles = store::get_profiles(app)?;
+    let active = store::get_value_by_key(app, store::ACTIVE_PROFILE)?;
+    for profile in profiles.iter() {
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("{}{}", PROFILE_ITEM_PREFIX, profile.name),
+            &profile.name,
+            true,
+            active.as_deref() == Some(profile.name.as_str()),
+            None::<&str>,
+        )?;
+        profiles_menu.append(&item)?;
+    }
+    profiles_menu.set_enabled(!profiles.is_empty())?;
+    Ok(())
+}
+
+fn open_main_window(app: &AppHandle) {
+    show_main_window(app);
+    #[cfg(target_os = "macos")]
+    app.emit("refresh", ()).unwrap();
+}
+
+fn show_main_window(app: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    tauri::AppHandle::show(app).unwrap();
+    if let Some(window) = app.get_webview_window("main") {
+        window.show().unwrap();
+        window.set_focus().unwrap();
     }
 }
@@ -0,0 +1,184 @@
+//! Watch the store directory for edits made outside secc.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::{Error, anyhow};
+use notify_debouncer_mini::{
+    DebounceEventResult, Debouncer, new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::{
+    command,
+    history::{self, HistorySource},
+    policy::POLICIES_DIR,
+    rules::lint::{self, LintReport},
+    state::WrittenFiles,
+    store::{self, BLOCK_RULES_PATH, CONFIG_PATH, CUSTOM_PROXY_RULES_PATH, DIRECT_RULES_PATH},
+};
+
+pub const REFRESH_RULES_EVENT: &str = "refresh_rules";
+pub const RULES_INVALID_EVENT: &str = "rules_invalid";
+pub const CONFIG_INVALID_EVENT: &str = "config_invalid";
+/// Editors write in bursts, wait for this much quiet before reacting.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct InvalidRules {
+    pub path: String,
+    pub report: LintReport,
+}
+
+/// Remember a write made by secc itself, so the watcher doesn't take it for an
+/// external edit.
+pub fn record_write(app: &AppHandle, path: &Path, content: &[u8]) {
+    let written = app.state::<Mutex<WrittenFiles>>();
+    let mut written = written.lock().unwrap();
+    written.set(canonical(path), hash(content));
+}
+
+// events carry the canonical path, the file itself may not exist yet
+fn canonical(path: &Path) -> PathBuf {
+    let dir = path.parent().and_then(|x| x.canonicalize().ok());
+    match (dir, path.file_name()) {
+        (Some(dir), Some(file_name)) => dir.join(file_name),
+        _ => path.to_path_buf(),
+    }
+}
+
+pub fn spawn_watcher(app: &AppHandle) -> Result<(), Error> {
+    let config_path = store::get_config_path(app, CONFIG_PATH)?;
+    let dir = config_path
+        .parent()
+        .ok_or(anyhow!("store path has no directory"))?
+        .canonicalize()?;
+    let handler_app = app.clone();
+    let handler_dir = dir.clone();
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| match result {
+        Ok(events) => {
+            let mut paths: Vec<PathBuf> = events.into_iter().map(|x| x.path).collect();
+            paths.dedup();
+            for path in paths {
+                if let Err(e) = handle_change(&handler_app, &handler_dir, &path) {
+                    eprintln!("handle change of {:?} error: {:?}", path, e);
+                }
+            }
+        }
+        Err(e) => eprintln!("watch store dir error: {:?}", e),
+    })?;
+    debouncer.watcher().watch(&dir, RecursiveMode::Recursive)?;
+    app.manage(FileWatcher {
+        _debouncer: Mutex::new(debouncer),
+    });
+    Ok(())
+}
+
+/// Keeps the watcher alive for the lifetime of the app.
+struct FileWatcher {
+    _debouncer: Mutex<Debouncer<RecommendedWatcher>>,
+}
+
+fn handle_change(app: &AppHandle, dir: &Path, path: &Path) -> Result<(), Error> {
+    let Ok(sub_path) = path.strip_prefix(dir) else {
+        return Ok(());
+    };
+    let sub_path = sub_path.to_string_lossy().replace('\\', "/");
+    if sub_path == CONFIG_PATH {
+        return handle_config_change(app, path);
+    }
+    let user_lists = [CUSTOM_PROXY_RULES_PATH, DIRECT_RULES_PATH, BLOCK_RULES_PATH];
+    let is_policy_list =
+        sub_path.starts_with(&format!("{}/", POLICIES_DIR)) && sub_path.ends_with(".txt");
+    if !user_lists.contains(&sub_path.as_str()) && !is_policy_list {
+        // generated files, caches and certs
+        return Ok(());
+    }
+    handle_rules_change(app, path, &sub_path)
+}
+
+fn handle_rules_change(app: &AppHandle, path: &Path, sub_path: &str) -> Result<(), Error> {
+    // removed or replaced in the meantime, the next event carries the new file
+    let Ok(content) = std::fs::read(path) else {
+        return Ok(());
+    };
+    {
+        let written = app.state::<Mutex<WrittenFiles>>();
+        let mut written = written.lock().unwrap();
+        if written.get(path) == Some(&hash(&content)) {
+            return Ok(());
+        }
+        written.set(path.to_path_buf(), hash(&content));
+    }
+    println!("{} was changed outside secc", sub_path);
+    let content = String::from_utf8_lossy(&content);
+    history::record(app, sub_path, &content, HistorySource::External);
+    let report = lint::lint_rules(&content, &[]);
+    if report.has_errors() {
+        // invalid entries are dropped when combining, tell the user which
+        let invalid = InvalidRules {
+            path: sub_path.to_string(),
+            report,
+        };
+        app.emit(RULES_INVALID_EVENT, &invalid)?;
+    }
+    store::combine_proxy_rules(app)?;
+    command::reload_agent(app);
+    app.emit(REFRESH_RULES_EVENT, sub_path)?;
+    Ok(())
+}
+
+fn handle_config_change(app: &AppHandle, path: &Path) -> Result<(), Error> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Ok(());
+    };
+    let store = app.store(CONFIG_PATH)?;
+    let entries: Map<String, Value> = store.entries().into_iter().collect();
+    let on_disk = match serde_json::from_str::<Value>(&content) {
+        Ok(on_disk) => on_disk,
+        Err(e) => {
+            app.emit(
+                CONFIG_INVALID_EVENT,
+                format!("{} is not valid json: {}", CONFIG_PATH, e),
+            )?;
+            return Ok(());
+        }
+    };
+    // the store saving itself
+    if on_disk == Value::Object(entries) {
+        return Ok(());
+    }
+    if let Err(message) = store::validate_config(&on_disk) {
+        app.emit(CONFIG_INVALID_EVENT, &message)?;
+        return Ok(());
+    }
+    println!("{} was changed outside secc", CONFIG_PATH);
+    if let Some(servers) = on_disk.get(store::SERVERS) {
+        let content = serde_json::to_string_pretty(servers)?;
+        history::record(
+            app,
+            history::SERVERS_SUBJECT,
+            &content,
+            HistorySource::External,
+        );
+    }
+    store.reload()?;
+    store::init_rules_files(app)?;
+    store::combine_proxy_rules(app)?;
+    command::apply_agent_config(app);
+    app.emit("refresh_servers", ())?;
+    app.emit("refresh", ())?;
+    Ok(())
+}
+
+fn hash(content: &[u8]) -> Vec<u8> {
+    Sha256::digest(content).to_vec()
+}
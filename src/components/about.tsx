@@ -0,0 +1,63 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { invoke } from '@tauri-apps/api/core';
+import { Info } from 'lucide-react';
+import { useEffect, useState } from 'react';
+
+export default function About() {
+  const [diagnostics, setDiagnostics] = useState<Diagnostics>();
+
+  const loadDiagnostics = async () => {
+    let diagnostics = await invoke<Diagnostics>('get_diagnostics');
+    setDiagnostics(diagnostics);
+  };
+
+  useEffect(() => {
+    loadDiagnostics();
+  }, []);
+
+  const rows = [
+    ['Secc', diagnostics?.app_version],
+    ['Platform', diagnostics && `${diagnostics.os} ${diagnostics.arch}`],
+    ['Agent', diagnostics?.agent.version || 'unknown'],
+    ['Agent Running', diagnostics?.agent_running ? 'yes' : 'no'],
+    [
+      'Capabilities',
+      diagnostics?.agent.capabilities.length
+        ? diagnostics.agent.capabilities.join(', ')
+        : 'none reported',
+    ],
+    ['Agent Config', diagnostics?.config_path],
+  ];
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center space-x-2">
+          <Info /> <div>About</div>
+        </CardTitle>
+        <CardDescription>Versions and diagnostics</CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-2">
+        {diagnostics && !diagnostics.agent.compatible && (
+          <div className="p-4 rounded-md border border-red-500 text-red-500 text-sm">
+            {diagnostics.agent.message}
+          </div>
+        )}
+        <div className="p-4 rounded-md border space-y-1 text-sm">
+          {rows.map(([name, value]) => (
+            <div key={name} className="flex space-x-4">
+              <div className="w-32 font-medium">{name}</div>
+              <div className="flex-1 break-all">{value}</div>
+            </div>
+          ))}
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
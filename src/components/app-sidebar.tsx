@@ -1,4 +1,12 @@
-import { Gauge, Route, ScrollText, Server, Settings, Zap } from 'lucide-react';
+import {
+  Ban,
+  Gauge,
+  Route,
+  ScrollText,
+  Server,
+  Settings,
+  Zap,
+} from 'lucide-react';
 
 import {
   Sidebar,
@@ -56,6 +64,11 @@ export function AppSidebar() {
       url: '/proxy-rules',
       icon: Route,
     },
+    {
+      title: 'Block Rules',
+      url: '/block-rules',
+      icon: Ban,
+    },
     {
       title: 'Settings',
       url: '/settings',
@@ -64,6 +77,8 @@ export function AppSidebar() {
   ];
 
   const [servers, setServers] = useState<ServerConfig[]>([]);
+  const [groups, setGroups] = useState<ServerGroup[]>([]);
+  const [health, setHealth] = useState<Record<string, ServerHealth>>({});
   const [connected, setConnected] = useState(false);
   const [activeServer, setActiveServer] = useState('');
   const [accessMode, setAccessMode] = useState('auto');
@@ -143,6 +158,26 @@ export function AppSidebar() {
     }
   };
 
+  const getServerGroups = async () => {
+    let groups = await invoke<ServerGroup[]>('get_server_groups');
+    if (groups) {
+      setGroups(groups);
+    }
+    let health = await invoke<Record<string, ServerHealth>>('get_group_health');
+    if (health) {
+      setHealth(health);
+    }
+  };
+
+  const groupHealth = (group: ServerGroup) => {
+    const checked = group.servers.filter((host) => health[host]);
+    if (checked.length === 0) {
+      return '';
+    }
+    const healthy = checked.filter((host) => health[host].healthy === true);
+    return ` (${healthy.length}/${group.servers.length} up)`;
+  };
+
   const handleServerSelect = async (value: string) => {
     console.log('select:', value);
     setActiveServer(value);
@@ -156,6 +191,7 @@ export function AppSidebar() {
     getBindMode();
     getProtocolMode();
     getServers();
+    getServerGroups();
     getActiveServer();
 
     const unListen = listen('active_server_disable', () => {
@@ -163,10 +199,57 @@ export function AppSidebar() {
     });
     const unRefreshListen = listen('refresh_servers', () => {
       getServers();
+      getServerGroups();
+      getActiveServer();
+    });
+    const unProfileListen = listen<Profile>('profile_changed', (event) => {
+      setAccessMode(event.payload.access_mode);
+      setBindMode(event.payload.bind_mode);
+      setProtocolMode(event.payload.protocol_mode);
+      setActiveServer(event.payload.active_server ?? '');
+      setConnected(event.payload.enabled);
+    });
+    const unModesListen = listen('modes_changed', () => {
+      getAccessMode();
+      getBindMode();
+      getProtocolMode();
+    });
+    const unHealthListen = listen<Record<string, ServerHealth>>(
+      'group_health',
+      (event) => {
+        setHealth(event.payload);
+      },
+    );
+    const unFallbackListen = listen('protocol_fallback', () => {
+      toast('Quic is blocked on this network, using Tcp for now');
+    });
+    const unReconnectedListen = listen<Reconnected>('reconnected', (event) => {
+      const cause =
+        event.payload.reason === 'resumed'
+          ? 'Woke from sleep'
+          : 'Network changed';
+      const done = [
+        event.payload.agent_restarted && 'reconnected',
+        event.payload.proxy_reapplied && 'restored the system proxy',
+      ].filter(Boolean);
+      toast(`${cause}, ${done.join(' and ')}`);
     });
+    const unIncompatibleListen = listen<string>(
+      'agent_incompatible',
+      (event) => {
+        toast(event.payload);
+        setConnected(false);
+      },
+    );
     return () => {
       unListen.then((f) => f());
+      unFallbackListen.then((f) => f());
+      unReconnectedListen.then((f) => f());
+      unIncompatibleListen.then((f) => f());
       unRefreshListen.then((f) => f());
+      unHealthListen.then((f) => f());
+      unProfileListen.then((f) => f());
+      unModesListen.then((f) => f());
     };
   }, []);
 
@@ -195,6 +278,17 @@ export function AppSidebar() {
                     </SelectItem>
                   ))}
                 </SelectGroup>
+                {groups.length > 0 && (
+                  <SelectGroup>
+                    <SelectLabel>Server Groups</SelectLabel>
+                    {groups.map((group, index) => (
+                      <SelectItem key={index} value={group.name}>
+                        {group.name}
+                        {groupHealth(group)}
+                      </SelectItem>
+                    ))}
+                  </SelectGroup>
+                )}
               </SelectContent>
             </Select>
             <Switch
@@ -222,6 +316,7 @@ export function AppSidebar() {
           <div className="flex items-center space-x-4 rounded-md border p-4">
             <Tabs value={protocolMode} onValueChange={handleProtocolModeSwitch}>
               <TabsList>
+                <TabsTrigger value="auto">Auto</TabsTrigger>
                 <TabsTrigger value="tcp">Tcp Mode</TabsTrigger>
                 <TabsTrigger value="quic">Quic Mode</TabsTrigger>
               </TabsList>
@@ -0,0 +1,173 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { invoke } from '@tauri-apps/api/core';
+import { open, save } from '@tauri-apps/plugin-dialog';
+import { Archive } from 'lucide-react';
+import { useState } from 'react';
+import { toast } from 'sonner';
+import { Button } from './ui/button';
+import { Input } from './ui/input';
+import { Label } from './ui/label';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+
+const filters = [{ name: 'Secc Configuration', extensions: ['json'] }];
+
+const conflictLabels: Record<ImportConflict['kind'], string> = {
+  server: 'Server',
+  group: 'Server group',
+  policy: 'Policy',
+  profile: 'Profile',
+  rule_source: 'Rule source',
+  setting: 'Setting',
+};
+
+export default function ConfigBundle() {
+  const [exportPassphrase, setExportPassphrase] = useState('');
+  const [importPassphrase, setImportPassphrase] = useState('');
+  const [mode, setMode] = useState<ImportMode>('merge');
+  const [preview, setPreview] = useState<{
+    path: string;
+    report: ConfigImportReport;
+  }>();
+
+  const exportConfig = async () => {
+    const path = await save({ defaultPath: 'secc-config.json', filters });
+    if (!path) {
+      return;
+    }
+    try {
+      await invoke('export_config', {
+        path,
+        passphrase: exportPassphrase || null,
+      });
+    } catch (e) {
+      toast.error(e as string);
+      return;
+    }
+    toast('exported');
+  };
+
+  const importConfig = async (path: string, dryRun: boolean) => {
+    try {
+      const report = await invoke<ConfigImportReport>('import_config', {
+        path,
+        mode,
+        passphrase: importPassphrase || null,
+        dryRun,
+      });
+      if (report.applied) {
+        setPreview(undefined);
+        toast(report.keys_imported ? 'imported with keys' : 'imported');
+      } else {
+        setPreview({ path, report });
+      }
+    } catch (e) {
+      setPreview(undefined);
+      toast.error(e as string);
+    }
+  };
+
+  const pickBundle = async () => {
+    const path = await open({ multiple: false, directory: false, filters });
+    if (path) {
+      await importConfig(path, true);
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center space-x-2">
+          <Archive /> <div>Backup</div>
+        </CardTitle>
+        <CardDescription>
+          Move servers, rules and settings to another machine
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="p-4 rounded-md border space-y-2">
+          <Label>Export</Label>
+          <div className="flex items-center space-x-4">
+            <Input
+              type="password"
+              placeholder="Passphrase to include private keys (optional)"
+              value={exportPassphrase}
+              onChange={(e) => setExportPassphrase(e.target.value)}
+            />
+            <Button onClick={exportConfig}>Export</Button>
+          </div>
+        </div>
+        <div className="p-4 rounded-md border space-y-2">
+          <Label>Import</Label>
+          <div className="flex items-center space-x-4">
+            <Select
+              value={mode}
+              onValueChange={(value) => {
+                setMode(value as ImportMode);
+                setPreview(undefined);
+              }}
+            >
+              <SelectTrigger className="w-36">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="merge">Merge</SelectItem>
+                <SelectItem value="replace">Replace</SelectItem>
+              </SelectContent>
+            </Select>
+            <Input
+              type="password"
+              placeholder="Passphrase of the private keys (optional)"
+              value={importPassphrase}
+              onChange={(e) => setImportPassphrase(e.target.value)}
+            />
+            <Button onClick={pickBundle}>Import</Button>
+          </div>
+          {preview && (
+            <div className="space-y-2 text-sm">
+              {preview.report.conflicts.length === 0 ? (
+                <div>No existing entries are overwritten.</div>
+              ) : (
+                <div>
+                  <div>These entries are overwritten:</div>
+                  <ul className="list-disc pl-6">
+                    {preview.report.conflicts.map((conflict) => (
+                      <li key={`${conflict.kind}:${conflict.name}`}>
+                        {conflictLabels[conflict.kind]} {conflict.name}
+                      </li>
+                    ))}
+                  </ul>
+                </div>
+              )}
+              {preview.report.has_keys && !importPassphrase && (
+                <div className="text-muted-foreground">
+                  The bundle holds private keys, enter its passphrase to import
+                  them.
+                </div>
+              )}
+              <div className="flex space-x-2">
+                <Button onClick={() => importConfig(preview.path, false)}>
+                  Apply
+                </Button>
+                <Button variant="outline" onClick={() => setPreview(undefined)}>
+                  Cancel
+                </Button>
+              </div>
+            </div>
+          )}
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
@@ -13,7 +13,7 @@ import {
 import { Textarea } from '@/components/ui/textarea';
 import { invoke } from '@tauri-apps/api/core';
 import { useEffect } from 'react';
-import { toast } from 'sonner';
+import LintIssues, { toastSaved, useLint } from '@/components/lint-issues';
 
 const FormSchema = z.object({
   proxyRules: z.string(),
@@ -23,11 +23,24 @@ function CustomProxyRule({ callback }: { callback: () => void }) {
   const form = useForm<z.infer<typeof FormSchema>>({
     resolver: zodResolver(FormSchema),
   });
+  const [report, setReport] = useLint(
+    'lint_custom_proxy_rules',
+    'proxyRules',
+    form.watch('proxyRules'),
+  );
 
   async function onSubmit(data: z.infer<typeof FormSchema>) {
-    await saveProxyRules(data);
-    toast('success');
-    callback();
+    try {
+      const report = await saveProxyRules(data);
+      setReport(report);
+      toastSaved(report);
+      callback();
+    } catch (report) {
+      setReport(report as LintReport);
+      form.setError('proxyRules', {
+        message: 'Fix the errors below before saving',
+      });
+    }
   }
 
   const loadProxyRules = async () => {
@@ -38,7 +51,7 @@ function CustomProxyRule({ callback }: { callback: () => void }) {
   };
 
   const saveProxyRules = async (data: z.infer<typeof FormSchema>) => {
-    await invoke('set_custom_proxy_rules', {
+    return await invoke<LintReport>('set_custom_proxy_rules', {
       proxyRules: data.proxyRules,
     });
   };
@@ -68,6 +81,7 @@ function CustomProxyRule({ callback }: { callback: () => void }) {
               </FormItem>
             )}
           />
+          <LintIssues report={report} />
           <Button type="submit">Submit</Button>
         </form>
       </Form>
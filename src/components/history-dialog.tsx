@@ -0,0 +1,130 @@
+import { invoke } from '@tauri-apps/api/core';
+import { History } from 'lucide-react';
+import { useState } from 'react';
+import { toast } from 'sonner';
+import { Button } from './ui/button';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogTrigger,
+} from './ui/dialog';
+import { ScrollArea } from './ui/scroll-area';
+
+const sourceLabels: Record<HistorySource, string> = {
+  initial: 'Initial',
+  ui: 'Edited',
+  import: 'Imported',
+  community_refresh: 'Source refresh',
+  external: 'Edited outside secc',
+  restore: 'Restored',
+};
+
+function HistoryDialog({
+  subject,
+  callback,
+}: {
+  subject: string;
+  callback?: () => void;
+}) {
+  const [versions, setVersions] = useState<HistoryVersion[]>([]);
+  const [selected, setSelected] = useState<number | null>(null);
+  const [diff, setDiff] = useState('');
+
+  const loadVersions = async () => {
+    const versions = await invoke<HistoryVersion[]>('list_history', {
+      subject,
+    });
+    setVersions(versions);
+    setSelected(null);
+    setDiff('');
+  };
+
+  const selectVersion = async (id: number) => {
+    setSelected(id);
+    try {
+      // compared with the current content
+      const diff = await invoke<string>('diff_history', { subject, from: id });
+      setDiff(diff);
+    } catch (e) {
+      toast.error(e as string);
+    }
+  };
+
+  const restoreVersion = async () => {
+    if (selected === null) {
+      return;
+    }
+    try {
+      await invoke('restore_history', { subject, id: selected });
+    } catch (e) {
+      toast.error(e as string);
+      return;
+    }
+    toast('restored');
+    callback?.();
+    await loadVersions();
+  };
+
+  const handleOpenChange = async (open: boolean) => {
+    if (open) {
+      await loadVersions();
+    }
+  };
+
+  return (
+    <Dialog onOpenChange={handleOpenChange}>
+      <DialogTrigger asChild>
+        <Button variant="outline">
+          <History /> History
+        </Button>
+      </DialogTrigger>
+      <DialogContent className="sm:max-w-[800px]">
+        <DialogHeader>
+          <DialogTitle>History</DialogTitle>
+          <DialogDescription>
+            Pick a version to compare it with the current content
+          </DialogDescription>
+        </DialogHeader>
+        <div className="flex space-x-4">
+          <ScrollArea className="h-[400px] w-56 rounded-md border">
+            {versions.length === 0 && (
+              <div className="p-2 text-sm text-muted-foreground">
+                No versions yet
+              </div>
+            )}
+            {versions.map((version) => (
+              <Button
+                key={version.id}
+                variant={version.id === selected ? 'secondary' : 'ghost'}
+                className="w-full h-auto justify-start flex-col items-start"
+                onClick={() => selectVersion(version.id)}
+              >
+                <span>
+                  {new Date(version.timestamp * 1000).toLocaleString()}
+                </span>
+                <span className="text-xs text-muted-foreground">
+                  {sourceLabels[version.source]}, {version.size} bytes
+                </span>
+              </Button>
+            ))}
+          </ScrollArea>
+          <ScrollArea className="h-[400px] flex-1 rounded-md border">
+            <pre className="p-2 text-xs">
+              {selected === null ? '' : diff || 'Same as the current content'}
+            </pre>
+          </ScrollArea>
+        </div>
+        <div className="flex justify-end">
+          <Button disabled={selected === null} onClick={restoreVersion}>
+            Restore
+          </Button>
+        </div>
+      </DialogContent>
+    </Dialog>
+  );
+}
+
+export default HistoryDialog;
@@ -1,23 +1,45 @@
 import { AppSidebar } from '@/components/app-sidebar';
 import { SidebarProvider } from '@/components/ui/sidebar';
 import Tls from '@/pages/tls';
-import { Route, Routes } from 'react-router';
+import { Route, Routes, useNavigate } from 'react-router';
 import Dashboard from '../pages/dashboard';
 import DirectRules from '../pages/direct-rules';
 import ProxyRules from '../pages/proxy-rules';
+import BlockRules from '../pages/block-rules';
 import Servers from '../pages/servers';
 import Setting from '../pages/setting';
 import TrafficLogs from '@/pages/traffic-logs';
 import { useEffect } from 'react';
 import { listen } from '@tauri-apps/api/event';
+import { toast } from 'sonner';
+import { formatLintErrors } from '@/lib/utils';
 
 export default function Layout() {
+  const navigate = useNavigate();
+
   useEffect(() => {
     const unLogListen = listen('refresh', () => {
       window.location.reload();
     });
+    const unInvalidRulesListen = listen<{ path: string; report: LintReport }>(
+      'rules_invalid',
+      (event) => {
+        toast(
+          `${event.payload.path} was edited outside secc: ${formatLintErrors(event.payload.report)}`,
+        );
+      },
+    );
+    const unInvalidConfigListen = listen<string>('config_invalid', (event) => {
+      toast(event.payload);
+    });
+    const unNavigateListen = listen<string>('navigate', (event) => {
+      navigate(event.payload);
+    });
     return () => {
       unLogListen.then((f) => f());
+      unInvalidRulesListen.then((f) => f());
+      unInvalidConfigListen.then((f) => f());
+      unNavigateListen.then((f) => f());
     };
   }, []);
   return (
@@ -30,6 +52,7 @@ export default function Layout() {
           <Route path="/servers" element={<Servers />} />
           <Route path="/direct-rules" element={<DirectRules />} />
           <Route path="/proxy-rules" element={<ProxyRules />} />
+          <Route path="/block-rules" element={<BlockRules />} />
           <Route path="/tls" element={<Tls />} />
           <Route path="/settings" element={<Setting />} />
         </Routes>
@@ -0,0 +1,56 @@
+import { invoke } from '@tauri-apps/api/core';
+import { useEffect, useState } from 'react';
+import { toast } from 'sonner';
+
+// Lint the rules being edited with `command` once the user stops typing.
+export function useLint(command: string, argument: string, rules?: string) {
+  const [report, setReport] = useState<LintReport>({ issues: [] });
+
+  useEffect(() => {
+    const timer = setTimeout(() => {
+      invoke<LintReport>(command, { [argument]: rules ?? '' })
+        .then(setReport)
+        .catch(() => {});
+    }, 500);
+    return () => clearTimeout(timer);
+  }, [command, argument, rules]);
+
+  return [report, setReport] as const;
+}
+
+// Saving only fails on errors, warnings are reported along with the success.
+export function toastSaved(report: LintReport) {
+  const warnings = report.issues.filter(
+    (issue) => issue.severity === 'warning',
+  ).length;
+  if (warnings > 0) {
+    toast.warning(`Saved with ${warnings} warning${warnings > 1 ? 's' : ''}`);
+  } else {
+    toast('success');
+  }
+}
+
+function LintIssues({ report }: { report: LintReport }) {
+  if (report.issues.length === 0) {
+    return null;
+  }
+  return (
+    <ul className="max-h-40 overflow-y-auto rounded-md border p-2 text-sm">
+      {report.issues.map((issue, index) => (
+        <li
+          key={index}
+          className={
+            issue.severity === 'error'
+              ? 'text-destructive'
+              : 'text-amber-600 dark:text-amber-400'
+          }
+        >
+          {issue.line > 0 && `line ${issue.line} `}
+          <code>{issue.entry}</code>: {issue.message}
+        </li>
+      ))}
+    </ul>
+  );
+}
+
+export default LintIssues;
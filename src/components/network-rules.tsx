@@ -0,0 +1,302 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { RefreshCw, Trash2, Wifi } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import Confirm from './confirm';
+import { Button } from './ui/button';
+import { Input } from './ui/input';
+import { Label } from './ui/label';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import { Switch } from './ui/switch';
+
+const NO_PROFILE = '__none';
+
+const conditionFields = [
+  { key: 'ssids', label: 'SSID' },
+  { key: 'interfaces', label: 'Interface' },
+  { key: 'gateways', label: 'Gateway IP' },
+  { key: 'gateway_macs', label: 'Gateway MAC' },
+  { key: 'dns_suffixes', label: 'DNS suffix' },
+] as const;
+
+type ConditionKey = (typeof conditionFields)[number]['key'];
+
+const outcomeLabels: Record<NetworkDecision['outcome'], string> = {
+  switched: 'switched',
+  already_active: 'already active',
+  no_match: 'no rule matched, left as is',
+  failed: 'failed',
+};
+
+const emptyConditions = (): Record<ConditionKey, string> => ({
+  ssids: '',
+  interfaces: '',
+  gateways: '',
+  gateway_macs: '',
+  dns_suffixes: '',
+});
+
+const splitValues = (value: string) =>
+  value
+    .split(',')
+    .map((x) => x.trim())
+    .filter((x) => x);
+
+const describeRule = (rule: NetworkRule) =>
+  conditionFields
+    .filter(({ key }) => rule[key].length > 0)
+    .map(({ key, label }) => `${label} ${rule[key].join(' or ')}`)
+    .join(', ');
+
+export default function NetworkRules() {
+  const [settings, setSettings] = useState<NetworkSettings>({
+    enabled: false,
+    rules: [],
+  });
+  const [profiles, setProfiles] = useState<Profile[]>([]);
+  const [network, setNetwork] = useState<NetworkInfo>();
+  const [decisions, setDecisions] = useState<NetworkDecision[]>([]);
+  const [name, setName] = useState('');
+  const [conditions, setConditions] = useState(emptyConditions());
+  const [profile, setProfile] = useState('');
+
+  const loadSettings = async () => {
+    setSettings(await invoke<NetworkSettings>('get_network_settings'));
+    setProfiles(await invoke<Profile[]>('get_profiles'));
+    setDecisions(await invoke<NetworkDecision[]>('get_network_decisions'));
+  };
+
+  const loadNetwork = async () => {
+    setNetwork(await invoke<NetworkInfo>('get_network_info'));
+  };
+
+  const saveSettings = async (next: NetworkSettings) => {
+    try {
+      await invoke('set_network_settings', { settings: next });
+    } catch (e) {
+      toast.error(e as string);
+      return false;
+    }
+    setSettings(next);
+    return true;
+  };
+
+  const useCurrentNetwork = () => {
+    if (!network) {
+      return;
+    }
+    setConditions({
+      ...emptyConditions(),
+      ssids: network.ssid ?? '',
+      gateways: network.ssid ? '' : (network.gateway ?? ''),
+    });
+  };
+
+  const addRule = async () => {
+    const rule: NetworkRule = {
+      name: name.trim(),
+      ssids: splitValues(conditions.ssids),
+      interfaces: splitValues(conditions.interfaces),
+      gateways: splitValues(conditions.gateways),
+      gateway_macs: splitValues(conditions.gateway_macs),
+      dns_suffixes: splitValues(conditions.dns_suffixes),
+      profile,
+    };
+    const added = await saveSettings({
+      ...settings,
+      rules: [...settings.rules, rule],
+    });
+    if (added) {
+      setName('');
+      setConditions(emptyConditions());
+    }
+  };
+
+  const deleteRule = async (rule: string) => {
+    await saveSettings({
+      ...settings,
+      rules: settings.rules.filter((x) => x.name !== rule),
+    });
+  };
+
+  useEffect(() => {
+    loadSettings();
+    loadNetwork();
+    const unListen = listen<NetworkDecision>('network_decision', (event) => {
+      setNetwork(event.payload.network);
+      setDecisions((current) => [event.payload, ...current].slice(0, 50));
+    });
+    return () => {
+      unListen.then((f) => f());
+    };
+  }, []);
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center space-x-2">
+          <Wifi /> <div>Network</div>
+        </CardTitle>
+        <CardDescription>
+          Switch profiles when the network changes, the first matching rule
+          wins
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="flex items-center space-x-2">
+          <Switch
+            id="network-switching"
+            checked={settings.enabled}
+            disabled={profiles.length === 0}
+            onCheckedChange={(enabled) =>
+              saveSettings({ ...settings, enabled })
+            }
+          />
+          <Label htmlFor="network-switching">
+            Switch profiles automatically
+          </Label>
+        </div>
+        <div className="p-4 rounded-md border space-y-2">
+          <div className="flex items-center justify-between">
+            <Label>Current network</Label>
+            <Button variant="ghost" onClick={loadNetwork}>
+              <RefreshCw />
+            </Button>
+          </div>
+          {network && (
+            <div className="grid grid-cols-2 gap-1 text-sm">
+              <div className="text-muted-foreground">SSID</div>
+              <div>{network.ssid ?? '-'}</div>
+              <div className="text-muted-foreground">Interface</div>
+              <div>{network.interface ?? '-'}</div>
+              <div className="text-muted-foreground">Gateway</div>
+              <div>
+                {network.gateway ?? '-'}
+                {network.gateway_mac && ` (${network.gateway_mac})`}
+              </div>
+              <div className="text-muted-foreground">DNS suffixes</div>
+              <div>{network.dns_suffixes.join(', ') || '-'}</div>
+            </div>
+          )}
+        </div>
+        {settings.rules.length > 0 && (
+          <div className="rounded-md border divide-y">
+            {settings.rules.map((rule) => (
+              <div
+                key={rule.name}
+                className="flex items-center justify-between p-2 pl-4"
+              >
+                <div>
+                  <div>
+                    {rule.name} → {rule.profile}
+                  </div>
+                  <div className="text-sm text-muted-foreground">
+                    {describeRule(rule)}
+                  </div>
+                </div>
+                <Confirm callback={() => deleteRule(rule.name)}>
+                  <Trash2 />
+                </Confirm>
+              </div>
+            ))}
+          </div>
+        )}
+        <div className="p-4 rounded-md border space-y-2">
+          <div className="flex items-center justify-between">
+            <Label>New rule</Label>
+            <Button
+              variant="outline"
+              disabled={!network}
+              onClick={useCurrentNetwork}
+            >
+              Use current network
+            </Button>
+          </div>
+          <Input
+            placeholder="Rule name"
+            value={name}
+            onChange={(e) => setName(e.target.value)}
+          />
+          {conditionFields.map(({ key, label }) => (
+            <Input
+              key={key}
+              placeholder={`${label}, comma separated (optional)`}
+              value={conditions[key]}
+              onChange={(e) =>
+                setConditions({ ...conditions, [key]: e.target.value })
+              }
+            />
+          ))}
+          <div className="flex items-center space-x-4">
+            <Select value={profile} onValueChange={setProfile}>
+              <SelectTrigger>
+                <SelectValue placeholder="Profile" />
+              </SelectTrigger>
+              <SelectContent>
+                {profiles.map((x) => (
+                  <SelectItem key={x.name} value={x.name}>
+                    {x.name}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Button onClick={addRule}>Add</Button>
+          </div>
+        </div>
+        <div className="flex items-center space-x-4">
+          <Label className="shrink-0">Other networks</Label>
+          <Select
+            value={settings.fallback_profile ?? NO_PROFILE}
+            onValueChange={(value) =>
+              saveSettings({
+                ...settings,
+                fallback_profile: value === NO_PROFILE ? undefined : value,
+              })
+            }
+          >
+            <SelectTrigger>
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              <SelectItem value={NO_PROFILE}>Leave as is</SelectItem>
+              {profiles.map((x) => (
+                <SelectItem key={x.name} value={x.name}>
+                  {x.name}
+                </SelectItem>
+              ))}
+            </SelectContent>
+          </Select>
+        </div>
+        {decisions.length > 0 && (
+          <div className="space-y-1 text-sm">
+            <Label>Recent decisions</Label>
+            {decisions.slice(0, 5).map((decision) => (
+              <div key={decision.timestamp} className="text-muted-foreground">
+                {new Date(decision.timestamp * 1000).toLocaleString()}:{' '}
+                {decision.network.ssid ?? decision.network.gateway ?? '-'},{' '}
+                {decision.rule ?? 'no rule'}
+                {decision.profile && ` → ${decision.profile}`},{' '}
+                {outcomeLabels[decision.outcome]}
+                {decision.error && `: ${decision.error}`}
+              </div>
+            ))}
+          </div>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
@@ -0,0 +1,248 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { ListTree, Pencil, Trash2 } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import Confirm from './confirm';
+import HistoryDialog from './history-dialog';
+import LintIssues, { toastSaved, useLint } from './lint-issues';
+import { Button } from './ui/button';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogTrigger,
+} from './ui/dialog';
+import { Input } from './ui/input';
+import { Label } from './ui/label';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import { Switch } from './ui/switch';
+import { Textarea } from './ui/textarea';
+
+function PolicyRules({ name }: { name: string }) {
+  const [open, setOpen] = useState(false);
+  const [rules, setRules] = useState('');
+  const [error, setError] = useState('');
+  // policy rules are linted like the custom proxy rules
+  const [report, setReport] = useLint(
+    'lint_custom_proxy_rules',
+    'proxyRules',
+    rules,
+  );
+
+  const loadRules = async () => {
+    try {
+      setRules(await invoke<string>('get_policy_rules', { name }));
+    } catch (e) {
+      toast.error(e as string);
+    }
+  };
+
+  const saveRules = async () => {
+    try {
+      const report = await invoke<LintReport>('set_policy_rules', {
+        name,
+        policyRules: rules,
+      });
+      setReport(report);
+      setError('');
+      toastSaved(report);
+      setOpen(false);
+    } catch (report) {
+      setReport(report as LintReport);
+      setError('Fix the errors below before saving');
+    }
+  };
+
+  return (
+    <Dialog
+      open={open}
+      onOpenChange={(open) => {
+        setOpen(open);
+        setError('');
+        if (open) {
+          loadRules();
+        }
+      }}
+    >
+      <DialogTrigger asChild>
+        <Button variant="ghost">
+          <Pencil />
+        </Button>
+      </DialogTrigger>
+      <DialogContent>
+        <DialogHeader>
+          <DialogTitle>Rules of {name}</DialogTitle>
+          <DialogDescription>
+            Hosts matching these rules are sent to the policy server
+          </DialogDescription>
+        </DialogHeader>
+        <div className="space-y-4">
+          <Textarea
+            placeholder="Input the policy domain list with new line"
+            className="resize-none w-full"
+            rows={10}
+            value={rules}
+            onChange={(e) => setRules(e.target.value)}
+          />
+          {error && <div className="text-sm text-destructive">{error}</div>}
+          <LintIssues report={report} />
+          <div className="flex justify-end">
+            <Button onClick={saveRules}>Submit</Button>
+          </div>
+        </div>
+      </DialogContent>
+    </Dialog>
+  );
+}
+
+export default function Policies() {
+  const [policies, setPolicies] = useState<Policy[]>([]);
+  const [servers, setServers] = useState<ServerConfig[]>([]);
+  const [groups, setGroups] = useState<ServerGroup[]>([]);
+  const [name, setName] = useState('');
+  const [server, setServer] = useState('');
+
+  const loadPolicies = async () => {
+    setPolicies(await invoke<Policy[]>('get_policies'));
+    setServers((await invoke<ServerConfig[] | null>('get_servers')) ?? []);
+    setGroups(await invoke<ServerGroup[]>('get_server_groups'));
+  };
+
+  const savePolicies = async (next: Policy[]) => {
+    try {
+      await invoke('set_policies', { policies: next });
+    } catch (e) {
+      toast.error(e as string);
+      return false;
+    }
+    setPolicies(next);
+    return true;
+  };
+
+  const addPolicy = async () => {
+    const policy: Policy = { name: name.trim(), server, enabled: true };
+    if (await savePolicies([...policies, policy])) {
+      setName('');
+    }
+  };
+
+  const togglePolicy = async (policy: string, enabled: boolean) => {
+    await savePolicies(
+      policies.map((x) => (x.name === policy ? { ...x, enabled } : x)),
+    );
+  };
+
+  // the rules and their history are deleted with the policy
+  const deletePolicy = async (policy: string) => {
+    await savePolicies(policies.filter((x) => x.name !== policy));
+  };
+
+  const serverLabel = (target: string) => {
+    if (groups.some((x) => x.name === target)) {
+      return `${target} (group)`;
+    }
+    const server = servers.find((x) => x.host === target);
+    return server?.alias ? `${server.alias} (${target})` : target;
+  };
+
+  useEffect(() => {
+    loadPolicies();
+    const unListen = listen('refresh_servers', () => {
+      loadPolicies();
+    });
+    return () => {
+      unListen.then((f) => f());
+    };
+  }, []);
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center space-x-2">
+          <ListTree /> <div>Policies</div>
+        </CardTitle>
+        <CardDescription>
+          Send the hosts of a rule list to another server than the active one,
+          the first matching policy wins
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        {policies.length > 0 && (
+          <div className="rounded-md border divide-y">
+            {policies.map((policy) => (
+              <div
+                key={policy.name}
+                className="flex items-center justify-between space-x-4 p-2 pl-4"
+              >
+                <div className="min-w-0">
+                  <div>{policy.name}</div>
+                  <div className="truncate text-sm text-muted-foreground">
+                    → {serverLabel(policy.server)}
+                  </div>
+                </div>
+                <div className="flex shrink-0 items-center space-x-2">
+                  <Switch
+                    checked={policy.enabled}
+                    onCheckedChange={(enabled) =>
+                      togglePolicy(policy.name, enabled)
+                    }
+                  />
+                  <PolicyRules name={policy.name} />
+                  <HistoryDialog subject={`policies/${policy.name}.txt`} />
+                  <Confirm callback={() => deletePolicy(policy.name)}>
+                    <Trash2 />
+                  </Confirm>
+                </div>
+              </div>
+            ))}
+          </div>
+        )}
+        <div className="p-4 rounded-md border space-y-2">
+          <Label>New policy</Label>
+          <div className="flex items-center space-x-4">
+            <Input
+              className="w-1/3"
+              placeholder="Name"
+              value={name}
+              onChange={(e) => setName(e.target.value)}
+            />
+            <Select value={server} onValueChange={setServer}>
+              <SelectTrigger>
+                <SelectValue placeholder="Server or group" />
+              </SelectTrigger>
+              <SelectContent>
+                {groups.map((group) => (
+                  <SelectItem key={group.name} value={group.name}>
+                    {serverLabel(group.name)}
+                  </SelectItem>
+                ))}
+                {servers.map((server) => (
+                  <SelectItem key={server.host} value={server.host}>
+                    {serverLabel(server.host)}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Button onClick={addPolicy}>Add</Button>
+          </div>
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
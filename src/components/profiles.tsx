@@ -0,0 +1,140 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { Layers, Trash2 } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import Confirm from './confirm';
+import { Badge } from './ui/badge';
+import { Button } from './ui/button';
+import { Input } from './ui/input';
+
+export default function Profiles() {
+  const [profiles, setProfiles] = useState<Profile[]>([]);
+  const [activeProfile, setActiveProfile] = useState<string>();
+  const [name, setName] = useState('');
+
+  const loadProfiles = async () => {
+    setProfiles(await invoke<Profile[]>('get_profiles'));
+    setActiveProfile(
+      (await invoke<string | null>('get_active_profile')) ?? undefined,
+    );
+  };
+
+  const saveProfiles = async (next: Profile[]) => {
+    try {
+      await invoke('set_profiles', { profiles: next });
+    } catch (e) {
+      toast.error(e as string);
+      return false;
+    }
+    setProfiles(next);
+    return true;
+  };
+
+  const captureProfile = async () => {
+    const trimmed = name.trim();
+    if (!trimmed) {
+      return;
+    }
+    try {
+      const profile = await invoke<Profile>('capture_profile', {
+        name: trimmed,
+      });
+      const next = profiles.filter((x) => x.name !== trimmed);
+      if (await saveProfiles([...next, profile])) {
+        setName('');
+        toast('saved');
+      }
+    } catch (e) {
+      toast.error(e as string);
+    }
+  };
+
+  const switchProfile = async (profile: string) => {
+    try {
+      await invoke('switch_profile', { name: profile });
+    } catch (e) {
+      toast.error(e as string);
+    }
+  };
+
+  const deleteProfile = async (profile: string) => {
+    await saveProfiles(profiles.filter((x) => x.name !== profile));
+  };
+
+  useEffect(() => {
+    loadProfiles();
+    const unListen = listen<Profile>('profile_changed', (event) => {
+      setActiveProfile(event.payload.name);
+      toast(`switched to ${event.payload.name}`);
+    });
+    return () => {
+      unListen.then((f) => f());
+    };
+  }, []);
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center space-x-2">
+          <Layers /> <div>Profiles</div>
+        </CardTitle>
+        <CardDescription>
+          Save the server, modes, listen addresses and rule sets under a name
+          and switch them all at once
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <div className="flex items-center space-x-4">
+          <Input
+            placeholder="Profile name"
+            value={name}
+            onChange={(e) => setName(e.target.value)}
+          />
+          <Button onClick={captureProfile}>Save current</Button>
+        </div>
+        {profiles.length > 0 && (
+          <div className="rounded-md border divide-y">
+            {profiles.map((profile) => (
+              <div
+                key={profile.name}
+                className="flex items-center justify-between p-2 pl-4"
+              >
+                <div className="flex items-center space-x-2">
+                  <div>{profile.name}</div>
+                  {profile.name === activeProfile && <Badge>active</Badge>}
+                  <div className="text-sm text-muted-foreground">
+                    {profile.enabled
+                      ? `${profile.active_server ?? 'no server'}, ` +
+                        `${profile.access_mode}, ${profile.bind_mode}, ` +
+                        `${profile.protocol_mode}`
+                      : 'off'}
+                  </div>
+                </div>
+                <div className="flex items-center">
+                  <Button
+                    variant="outline"
+                    disabled={profile.name === activeProfile}
+                    onClick={() => switchProfile(profile.name)}
+                  >
+                    Switch
+                  </Button>
+                  <Confirm callback={() => deleteProfile(profile.name)}>
+                    <Trash2 />
+                  </Confirm>
+                </div>
+              </div>
+            ))}
+          </div>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
@@ -0,0 +1,96 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { invoke } from '@tauri-apps/api/core';
+import { Search } from 'lucide-react';
+import { useState } from 'react';
+import { toast } from 'sonner';
+import { Button } from './ui/button';
+import { Input } from './ui/input';
+
+const actionLabels: Record<RouteDecision['action'], string> = {
+  proxy: 'Proxied',
+  direct: 'Connected directly',
+  block: 'Blocked',
+};
+
+export default function RouteLookup() {
+  const [host, setHost] = useState('');
+  const [decision, setDecision] = useState<RouteDecision>();
+
+  const lookupRoute = async () => {
+    try {
+      setDecision(await invoke<RouteDecision>('lookup_route', { host }));
+    } catch (e) {
+      setDecision(undefined);
+      toast.error(e as string);
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center space-x-2">
+          <Search /> <div>Route Lookup</div>
+        </CardTitle>
+        <CardDescription>
+          Check which rule and server a host would be sent through
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <form
+          className="flex items-center space-x-4"
+          onSubmit={(e) => {
+            e.preventDefault();
+            lookupRoute();
+          }}
+        >
+          <Input
+            placeholder="www.example.com"
+            value={host}
+            onChange={(e) => setHost(e.target.value)}
+          />
+          <Button type="submit">Lookup</Button>
+        </form>
+        {decision && (
+          <dl className="grid grid-cols-[auto_1fr] gap-x-4 gap-y-1 text-sm">
+            <dt className="text-muted-foreground">Host</dt>
+            <dd>{decision.host}</dd>
+            <dt className="text-muted-foreground">Route</dt>
+            <dd>{actionLabels[decision.action]}</dd>
+            {decision.action === 'proxy' && (
+              <>
+                <dt className="text-muted-foreground">Server</dt>
+                <dd>{decision.server ?? 'none available'}</dd>
+              </>
+            )}
+            {decision.group && (
+              <>
+                <dt className="text-muted-foreground">Group</dt>
+                <dd>{decision.group}</dd>
+              </>
+            )}
+            {decision.policy && (
+              <>
+                <dt className="text-muted-foreground">Policy</dt>
+                <dd>{decision.policy}</dd>
+              </>
+            )}
+            <dt className="text-muted-foreground">Rule</dt>
+            <dd>
+              {decision.matched_rule ? (
+                <code>{decision.matched_rule}</code>
+              ) : (
+                'none matched, the access mode default applied'
+              )}
+            </dd>
+          </dl>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
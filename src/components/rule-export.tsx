@@ -0,0 +1,108 @@
+import { invoke } from '@tauri-apps/api/core';
+import { open } from '@tauri-apps/plugin-dialog';
+import { FileUp } from 'lucide-react';
+import { useState } from 'react';
+import { toast } from 'sonner';
+import { Button } from './ui/button';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogTrigger,
+} from './ui/dialog';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+
+const formatLabels: Record<ExportFormat, string> = {
+  pac: 'PAC script',
+  clash: 'Clash rule providers',
+  surge: 'Surge rule sets',
+};
+
+function RuleExport() {
+  const [format, setFormat] = useState<ExportFormat>('pac');
+  const [files, setFiles] = useState<ExportFile[]>();
+
+  const exportRules = async () => {
+    const directory = await open({ directory: true, multiple: false });
+    if (!directory) {
+      return;
+    }
+    try {
+      const files = await invoke<ExportFile[]>('export_rules', {
+        directory,
+        format,
+      });
+      setFiles(files);
+      toast('exported');
+    } catch (e) {
+      setFiles(undefined);
+      toast.error(e as string);
+    }
+  };
+
+  return (
+    <Dialog onOpenChange={() => setFiles(undefined)}>
+      <DialogTrigger asChild>
+        <Button variant="outline">
+          <FileUp /> Export
+        </Button>
+      </DialogTrigger>
+      <DialogContent>
+        <DialogHeader>
+          <DialogTitle>Export rules</DialogTitle>
+          <DialogDescription>
+            Write the combined proxy and direct rules for other tools
+          </DialogDescription>
+        </DialogHeader>
+        <div className="space-y-4">
+          <Select
+            value={format}
+            onValueChange={(value) => {
+              setFormat(value as ExportFormat);
+              setFiles(undefined);
+            }}
+          >
+            <SelectTrigger>
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              {Object.entries(formatLabels).map(([value, label]) => (
+                <SelectItem key={value} value={value}>
+                  {label}
+                </SelectItem>
+              ))}
+            </SelectContent>
+          </Select>
+          {files && (
+            <ul className="space-y-1 text-sm">
+              {files.map((file) => (
+                <li key={file.name}>
+                  <div>{file.name}</div>
+                  {file.skipped.length > 0 && (
+                    <div className="text-muted-foreground">
+                      Left out, not supported by the format:{' '}
+                      {file.skipped.join(', ')}
+                    </div>
+                  )}
+                </li>
+              ))}
+            </ul>
+          )}
+          <div className="flex justify-end">
+            <Button onClick={exportRules}>Pick a folder</Button>
+          </div>
+        </div>
+      </DialogContent>
+    </Dialog>
+  );
+}
+
+export default RuleExport;
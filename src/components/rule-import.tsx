@@ -0,0 +1,138 @@
+import { invoke } from '@tauri-apps/api/core';
+import { open } from '@tauri-apps/plugin-dialog';
+import { FileDown } from 'lucide-react';
+import { useState } from 'react';
+import { toast } from 'sonner';
+import { formatLabels, targetLabels } from './rule-sources';
+import { Button } from './ui/button';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogTrigger,
+} from './ui/dialog';
+import { Label } from './ui/label';
+import { ScrollArea } from './ui/scroll-area';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import { Switch } from './ui/switch';
+
+const filters: Record<RuleFormat, { name: string; extensions: string[] }[]> = {
+  plain: [{ name: 'Rule list', extensions: ['txt', 'list'] }],
+  gfwlist: [{ name: 'gfwlist', extensions: ['txt'] }],
+  clash: [{ name: 'Clash', extensions: ['yaml', 'yml'] }],
+  switchy_omega: [{ name: 'SwitchyOmega backup', extensions: ['bak', 'json'] }],
+};
+
+// Import a rule list of another tool, entries land in `target` unless the
+// format names an outcome of its own (e.g. Clash DIRECT rules).
+function RuleImport({ target }: { target: RuleTarget }) {
+  const [format, setFormat] = useState<RuleFormat>('plain');
+  const [replace, setReplace] = useState(false);
+  const [report, setReport] = useState<RuleImportReport>();
+
+  const importRules = async () => {
+    const path = await open({
+      multiple: false,
+      directory: false,
+      filters: filters[format],
+    });
+    if (!path) {
+      return;
+    }
+    try {
+      const report = await invoke<RuleImportReport>('import_rules', {
+        path,
+        format,
+        target,
+        replace,
+      });
+      setReport(report);
+      toast(`imported ${report.translated} entries`);
+    } catch (e) {
+      setReport(undefined);
+      toast.error(e as string);
+    }
+  };
+
+  return (
+    <Dialog onOpenChange={() => setReport(undefined)}>
+      <DialogTrigger asChild>
+        <Button variant="outline">
+          <FileDown /> Import
+        </Button>
+      </DialogTrigger>
+      <DialogContent>
+        <DialogHeader>
+          <DialogTitle>Import rules</DialogTitle>
+          <DialogDescription>
+            Translate a rule list of another tool into the{' '}
+            {targetLabels[target].toLowerCase()} rules
+          </DialogDescription>
+        </DialogHeader>
+        <div className="space-y-4">
+          <Select
+            value={format}
+            onValueChange={(value) => setFormat(value as RuleFormat)}
+          >
+            <SelectTrigger>
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              {Object.entries(formatLabels).map(([value, label]) => (
+                <SelectItem key={value} value={value}>
+                  {label}
+                </SelectItem>
+              ))}
+            </SelectContent>
+          </Select>
+          <div className="flex items-center space-x-2">
+            <Switch
+              id="replace-rules"
+              checked={replace}
+              onCheckedChange={setReplace}
+            />
+            <Label htmlFor="replace-rules">
+              Replace the lists instead of appending
+            </Label>
+          </div>
+          {report && (
+            <div className="space-y-2 text-sm">
+              <div>
+                {(['proxy', 'direct', 'block'] as const)
+                  .filter((x) => report[x].length > 0)
+                  .map((x) => `${report[x].length} ${x}`)
+                  .join(', ') || 'Nothing'}{' '}
+                imported
+              </div>
+              {report.dropped.length > 0 && (
+                <ScrollArea className="h-40 rounded-md border">
+                  <ul className="p-2 text-muted-foreground">
+                    {report.dropped.map((dropped) => (
+                      <li key={`${dropped.line}:${dropped.entry}`}>
+                        line {dropped.line} <code>{dropped.entry}</code>:{' '}
+                        {dropped.reason}
+                      </li>
+                    ))}
+                  </ul>
+                </ScrollArea>
+              )}
+            </div>
+          )}
+          <div className="flex justify-end">
+            <Button onClick={importRules}>Pick a file</Button>
+          </div>
+        </div>
+      </DialogContent>
+    </Dialog>
+  );
+}
+
+export default RuleImport;
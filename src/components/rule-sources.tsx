@@ -0,0 +1,286 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { Library, RefreshCw, Trash2 } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import Confirm from './confirm';
+import { Button } from './ui/button';
+import { Input } from './ui/input';
+import { Label } from './ui/label';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import { Switch } from './ui/switch';
+
+export const targetLabels: Record<RuleTarget, string> = {
+  proxy: 'Proxy',
+  direct: 'Direct',
+  block: 'Block',
+};
+
+export const formatLabels: Record<RuleFormat, string> = {
+  plain: 'Plain list',
+  gfwlist: 'gfwlist',
+  clash: 'Clash',
+  switchy_omega: 'SwitchyOmega backup',
+};
+
+const fetchLabels: Record<RuleSource['fetch_via'], string> = {
+  fallback: 'Direct, then through secc',
+  direct: 'Always direct',
+  tunnel: 'Through secc',
+};
+
+const stateLabels: Record<SourceStatus['state'], string> = {
+  pending: 'not downloaded yet',
+  updated: 'updated',
+  not_modified: 'up to date',
+  failed: 'failed',
+};
+
+const describeStatus = (status?: SourceStatus) => {
+  if (!status) {
+    return stateLabels.pending;
+  }
+  const parts = [stateLabels[status.state]];
+  if (status.last_update) {
+    const checked = new Date(status.last_update * 1000).toLocaleString();
+    parts.push(`${status.entries} entries, checked ${checked}`);
+  }
+  if (status.through_tunnel) {
+    parts.push('through secc');
+  }
+  if (status.failures > 1) {
+    parts.push(`${status.failures} failures in a row`);
+  }
+  if (status.message) {
+    parts.push(status.message);
+  }
+  return parts.join(', ');
+};
+
+export default function RuleSources() {
+  const [sources, setSources] = useState<RuleSource[]>([]);
+  const [statuses, setStatuses] = useState<Record<string, SourceStatus>>({});
+  const [id, setId] = useState('');
+  const [url, setUrl] = useState('');
+  const [target, setTarget] = useState<RuleTarget>('proxy');
+  const [format, setFormat] = useState<RuleFormat>('plain');
+  const [fetchVia, setFetchVia] = useState<RuleSource['fetch_via']>('fallback');
+  const [hours, setHours] = useState('24');
+
+  const loadSources = async () => {
+    setSources(await invoke<RuleSource[]>('get_rule_sources'));
+    setStatuses(
+      await invoke<Record<string, SourceStatus>>('get_rule_sources_status'),
+    );
+  };
+
+  const saveSources = async (next: RuleSource[]) => {
+    try {
+      await invoke('set_rule_sources', { sources: next });
+    } catch (e) {
+      toast.error(e as string);
+      return false;
+    }
+    setSources(next);
+    return true;
+  };
+
+  const addSource = async () => {
+    const source: RuleSource = {
+      id: id.trim(),
+      url: url.trim(),
+      target,
+      enabled: true,
+      refresh_interval: Math.round((parseFloat(hours) || 0) * 3600),
+      format,
+      min_entries: 1,
+      fetch_via: fetchVia,
+    };
+    if (await saveSources([...sources, source])) {
+      setId('');
+      setUrl('');
+    }
+  };
+
+  const toggleSource = async (source: string, enabled: boolean) => {
+    await saveSources(
+      sources.map((x) => (x.id === source ? { ...x, enabled } : x)),
+    );
+  };
+
+  const deleteSource = async (source: string) => {
+    await saveSources(sources.filter((x) => x.id !== source));
+  };
+
+  const refreshSources = async () => {
+    await invoke('refresh_rule_sources');
+    toast('Refreshing rule sources');
+  };
+
+  useEffect(() => {
+    loadSources();
+    const unListen = listen<Record<string, SourceStatus>>(
+      'rule_sources_status',
+      (event) => {
+        setStatuses(event.payload);
+      },
+    );
+    return () => {
+      unListen.then((f) => f());
+    };
+  }, []);
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center space-x-2">
+          <Library /> <div>Rule Sources</div>
+        </CardTitle>
+        <CardDescription className="flex items-center justify-between">
+          <div>
+            Remote rule lists layered below your own lists, in this order
+          </div>
+          <Button variant="ghost" onClick={refreshSources}>
+            <RefreshCw />
+          </Button>
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        {sources.length > 0 && (
+          <div className="rounded-md border divide-y">
+            {sources.map((source) => (
+              <div
+                key={source.id}
+                className="flex items-center justify-between space-x-4 p-2 pl-4"
+              >
+                <div className="min-w-0">
+                  <div>
+                    {source.id} → {targetLabels[source.target]} (
+                    {formatLabels[source.format]})
+                  </div>
+                  <div className="truncate text-sm text-muted-foreground">
+                    {source.url}
+                  </div>
+                  <div
+                    className={
+                      statuses[source.id]?.state === 'failed'
+                        ? 'text-sm text-destructive'
+                        : 'text-sm text-muted-foreground'
+                    }
+                  >
+                    {source.enabled
+                      ? describeStatus(statuses[source.id])
+                      : 'disabled'}
+                  </div>
+                </div>
+                <div className="flex shrink-0 items-center space-x-2">
+                  <Switch
+                    checked={source.enabled}
+                    onCheckedChange={(enabled) =>
+                      toggleSource(source.id, enabled)
+                    }
+                  />
+                  <Confirm callback={() => deleteSource(source.id)}>
+                    <Trash2 />
+                  </Confirm>
+                </div>
+              </div>
+            ))}
+          </div>
+        )}
+        <div className="p-4 rounded-md border space-y-2">
+          <Label>New source</Label>
+          <div className="flex items-center space-x-4">
+            <Input
+              className="w-1/3"
+              placeholder="Id"
+              value={id}
+              onChange={(e) => setId(e.target.value)}
+            />
+            <Input
+              placeholder="https://..."
+              value={url}
+              onChange={(e) => setUrl(e.target.value)}
+            />
+          </div>
+          <div className="flex items-center space-x-4">
+            <Select
+              value={target}
+              onValueChange={(value) => setTarget(value as RuleTarget)}
+            >
+              <SelectTrigger>
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {Object.entries(targetLabels).map(([value, label]) => (
+                  <SelectItem key={value} value={value}>
+                    {label}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Select
+              value={format}
+              onValueChange={(value) => setFormat(value as RuleFormat)}
+            >
+              <SelectTrigger>
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {Object.entries(formatLabels).map(([value, label]) => (
+                  <SelectItem key={value} value={value}>
+                    {label}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Select
+              value={fetchVia}
+              onValueChange={(value) =>
+                setFetchVia(value as RuleSource['fetch_via'])
+              }
+            >
+              <SelectTrigger>
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {Object.entries(fetchLabels).map(([value, label]) => (
+                  <SelectItem key={value} value={value}>
+                    {label}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+          </div>
+          <div className="flex items-center space-x-4">
+            <Label className="shrink-0">Refresh every</Label>
+            <Input
+              className="w-24"
+              type="number"
+              min={0}
+              value={hours}
+              onChange={(e) => setHours(e.target.value)}
+            />
+            <div className="text-sm text-muted-foreground">
+              hours, 0 only on demand
+            </div>
+            <Button onClick={addSource}>Add</Button>
+          </div>
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
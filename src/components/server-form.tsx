@@ -24,6 +24,14 @@ import {
   DialogTrigger,
 } from './ui/dialog';
 import { Textarea } from './ui/textarea';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import { Switch } from './ui/switch';
 import { useState } from 'react';
 
 const FormSchema = z.object({
@@ -33,8 +41,23 @@ const FormSchema = z.object({
   quicPort: z.string().optional(),
   cert: z.string().nonempty(),
   certKey: z.string().nonempty(),
+  congestionControl: z.enum(['bbr', 'cubic']),
+  idleTimeout: z.string().nonempty(),
+  keepAliveInterval: z.string().nonempty(),
+  maxConcurrentStreams: z.string().nonempty(),
+  mtuDiscovery: z.boolean(),
+  tcpPoolSize: z.string().nonempty(),
 });
 
+const defaultValues = {
+  congestionControl: 'bbr' as const,
+  idleTimeout: '30',
+  keepAliveInterval: '10',
+  maxConcurrentStreams: '100',
+  mtuDiscovery: true,
+  tcpPoolSize: '4',
+};
+
 export default function ServerForm({
   host,
   callback,
@@ -46,11 +69,17 @@ export default function ServerForm({
 
   const form = useForm<z.infer<typeof FormSchema>>({
     resolver: zodResolver(FormSchema),
+    defaultValues,
   });
 
   async function onSubmit(data: z.infer<typeof FormSchema>) {
-    let res = await saveServerConfig(data);
-    console.log(res);
+    try {
+      let res = await saveServerConfig(data);
+      console.log(res);
+    } catch (e) {
+      form.setError('idleTimeout', { message: e as string });
+      return;
+    }
     toast('success');
     callback();
     setOpen(false);
@@ -66,10 +95,33 @@ export default function ServerForm({
       form.setValue('tcpPort', serverConfig?.tcp_port?.toString() || '');
       form.setValue('cert', serverConfig?.cert || '');
       form.setValue('certKey', serverConfig?.cert_key || '');
+      const transport = serverConfig.transport;
+      if (transport) {
+        form.setValue('congestionControl', transport.congestion_control);
+        form.setValue('idleTimeout', transport.idle_timeout.toString());
+        form.setValue(
+          'keepAliveInterval',
+          transport.keep_alive_interval.toString(),
+        );
+        form.setValue(
+          'maxConcurrentStreams',
+          transport.max_concurrent_streams.toString(),
+        );
+        form.setValue('mtuDiscovery', transport.mtu_discovery);
+        form.setValue('tcpPoolSize', transport.tcp_pool_size.toString());
+      }
     }
   };
 
   const saveServerConfig = async (data: z.infer<typeof FormSchema>) => {
+    const transport: TransportOptions = {
+      congestion_control: data.congestionControl,
+      idle_timeout: parseInt(data.idleTimeout),
+      keep_alive_interval: parseInt(data.keepAliveInterval),
+      max_concurrent_streams: parseInt(data.maxConcurrentStreams),
+      mtu_discovery: data.mtuDiscovery,
+      tcp_pool_size: parseInt(data.tcpPoolSize),
+    };
     if (host) {
       let res = await invoke<ServerConfig>('update_server', {
         server: {
@@ -79,6 +131,7 @@ export default function ServerForm({
           tcp_port: parseInt(data.tcpPort),
           cert: data.cert,
           cert_key: data.certKey,
+          transport,
         },
       });
       console.log(res);
@@ -92,6 +145,7 @@ export default function ServerForm({
         tcp_port: parseInt(data.tcpPort),
         cert: data.cert,
         cert_key: data.certKey,
+        transport,
       },
     });
     console.log(res);
@@ -234,6 +288,105 @@ export default function ServerForm({
                   )}
                 />
               </div>
+              <div className="p-4 rounded-md border space-y-4">
+                <div className="text-sm font-medium">Advanced</div>
+                <div className="space-x-4 flex items-start">
+                  <FormField
+                    control={form.control}
+                    name="congestionControl"
+                    render={({ field }) => (
+                      <FormItem className="w-32">
+                        <FormLabel>Congestion</FormLabel>
+                        <Select
+                          onValueChange={field.onChange}
+                          value={field.value}
+                        >
+                          <FormControl>
+                            <SelectTrigger>
+                              <SelectValue />
+                            </SelectTrigger>
+                          </FormControl>
+                          <SelectContent>
+                            <SelectItem value="bbr">BBR</SelectItem>
+                            <SelectItem value="cubic">Cubic</SelectItem>
+                          </SelectContent>
+                        </Select>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="idleTimeout"
+                    render={({ field }) => (
+                      <FormItem className="flex-1">
+                        <FormLabel>Idle Timeout (s)</FormLabel>
+                        <FormControl>
+                          <Input placeholder="Idle Timeout" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="keepAliveInterval"
+                    render={({ field }) => (
+                      <FormItem className="flex-1">
+                        <FormLabel>Keep-alive (s)</FormLabel>
+                        <FormControl>
+                          <Input placeholder="Keep-alive" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                </div>
+                <div className="space-x-4 flex items-start">
+                  <FormField
+                    control={form.control}
+                    name="maxConcurrentStreams"
+                    render={({ field }) => (
+                      <FormItem className="flex-1">
+                        <FormLabel>Max Streams</FormLabel>
+                        <FormControl>
+                          <Input placeholder="Max Streams" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="tcpPoolSize"
+                    render={({ field }) => (
+                      <FormItem className="flex-1">
+                        <FormLabel>Tcp Pool Size</FormLabel>
+                        <FormControl>
+                          <Input placeholder="Tcp Pool Size" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="mtuDiscovery"
+                    render={({ field }) => (
+                      <FormItem className="w-32">
+                        <FormLabel>MTU Discovery</FormLabel>
+                        <FormControl>
+                          <Switch
+                            checked={field.value}
+                            onCheckedChange={field.onChange}
+                          />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                </div>
+              </div>
               <DialogFooter>
                 <Button type="submit">Submit</Button>
               </DialogFooter>
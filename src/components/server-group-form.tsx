@@ -0,0 +1,264 @@
+import { zodResolver } from '@hookform/resolvers/zod';
+import { invoke } from '@tauri-apps/api/core';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+
+import { Button } from '@/components/ui/button';
+import {
+  Form,
+  FormControl,
+  FormField,
+  FormItem,
+  FormLabel,
+  FormMessage,
+} from '@/components/ui/form';
+import { Input } from '@/components/ui/input';
+import { toast } from 'sonner';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+  DialogTrigger,
+} from './ui/dialog';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import { useState } from 'react';
+
+const FormSchema = z.object({
+  name: z.string().nonempty(),
+  strategy: z.enum(['url_test', 'fallback', 'load_balance']),
+  balance: z.enum(['round_robin', 'consistent_hash']),
+  servers: z.string().nonempty(),
+  interval: z.string().nonempty(),
+});
+
+export default function ServerGroupForm({
+  name,
+  callback,
+}: {
+  name?: string;
+  callback: () => void;
+}) {
+  const [open, setOpen] = useState(false);
+
+  const form = useForm<z.infer<typeof FormSchema>>({
+    resolver: zodResolver(FormSchema),
+    defaultValues: {
+      strategy: 'url_test',
+      balance: 'round_robin',
+      interval: '300',
+    },
+  });
+
+  async function onSubmit(data: z.infer<typeof FormSchema>) {
+    let groups = await invoke<ServerGroup[]>('get_server_groups');
+    const group: ServerGroup = {
+      name: data.name,
+      strategy: data.strategy,
+      balance: data.balance,
+      servers: data.servers
+        .split(',')
+        .map((host) => host.trim())
+        .filter((host) => host !== ''),
+      interval: parseInt(data.interval),
+    };
+    const index = groups.findIndex((x) => x.name === name);
+    if (index >= 0) {
+      groups[index] = group;
+    } else {
+      groups.push(group);
+    }
+    try {
+      await invoke('set_server_groups', { groups });
+    } catch (e) {
+      form.setError('servers', { message: e as string });
+      return;
+    }
+    toast('success');
+    callback();
+    setOpen(false);
+  }
+
+  const loadServerGroup = async () => {
+    let groups = await invoke<ServerGroup[]>('get_server_groups');
+    const group = groups.find((x) => x.name === name);
+    if (group) {
+      form.setValue('name', group.name);
+      form.setValue('strategy', group.strategy);
+      form.setValue('balance', group.balance);
+      form.setValue('servers', group.servers.join(', '));
+      form.setValue('interval', group.interval.toString());
+    }
+  };
+
+  const handleOpenChange = async (open: boolean) => {
+    form.clearErrors();
+    form.reset();
+    if (open && name) {
+      loadServerGroup();
+    }
+    setOpen(open);
+  };
+
+  return (
+    <div>
+      <Dialog open={open} onOpenChange={handleOpenChange}>
+        <DialogTrigger asChild>
+          {name ? (
+            <Button variant="ghost">Edit</Button>
+          ) : (
+            <Button>New Group</Button>
+          )}
+        </DialogTrigger>
+        <DialogContent
+          className="sm:max-w-[800px]"
+          onInteractOutside={(e) => {
+            e.preventDefault();
+          }}
+        >
+          <DialogHeader>
+            <DialogTitle>
+              {name ? 'Edit Server Group' : 'Create New Server Group'}
+            </DialogTitle>
+            <DialogDescription>
+              Use several servers behind one name
+            </DialogDescription>
+          </DialogHeader>
+          <form onSubmit={form.handleSubmit(onSubmit)} className="space-y-4">
+            <Form {...form}>
+              <div className="p-4 rounded-md border space-y-4">
+                <div className="space-x-4 flex items-center">
+                  <FormField
+                    control={form.control}
+                    name="name"
+                    render={({ field }) => (
+                      <FormItem className="flex-1">
+                        <FormLabel>
+                          <span className="text-red-500">*</span>Name
+                        </FormLabel>
+                        <FormControl>
+                          <Input
+                            disabled={!!name}
+                            placeholder="Name"
+                            {...field}
+                          />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="interval"
+                    render={({ field }) => (
+                      <FormItem className="w-36">
+                        <FormLabel>
+                          <span className="text-red-500">*</span>Check Interval
+                        </FormLabel>
+                        <FormControl>
+                          <Input placeholder="Seconds" {...field} />
+                        </FormControl>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                </div>
+                <div className="space-x-4 flex items-center">
+                  <FormField
+                    control={form.control}
+                    name="strategy"
+                    render={({ field }) => (
+                      <FormItem className="flex-1">
+                        <FormLabel>Strategy</FormLabel>
+                        <Select
+                          onValueChange={field.onChange}
+                          value={field.value}
+                        >
+                          <FormControl>
+                            <SelectTrigger>
+                              <SelectValue />
+                            </SelectTrigger>
+                          </FormControl>
+                          <SelectContent>
+                            <SelectItem value="url_test">
+                              Lowest Latency
+                            </SelectItem>
+                            <SelectItem value="fallback">
+                              First Healthy
+                            </SelectItem>
+                            <SelectItem value="load_balance">
+                              Load Balance
+                            </SelectItem>
+                          </SelectContent>
+                        </Select>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                  <FormField
+                    control={form.control}
+                    name="balance"
+                    render={({ field }) => (
+                      <FormItem className="flex-1">
+                        <FormLabel>Balance</FormLabel>
+                        <Select
+                          onValueChange={field.onChange}
+                          value={field.value}
+                          disabled={form.watch('strategy') !== 'load_balance'}
+                        >
+                          <FormControl>
+                            <SelectTrigger>
+                              <SelectValue />
+                            </SelectTrigger>
+                          </FormControl>
+                          <SelectContent>
+                            <SelectItem value="round_robin">
+                              Round Robin
+                            </SelectItem>
+                            <SelectItem value="consistent_hash">
+                              Consistent Hash
+                            </SelectItem>
+                          </SelectContent>
+                        </Select>
+                        <FormMessage />
+                      </FormItem>
+                    )}
+                  />
+                </div>
+                <FormField
+                  control={form.control}
+                  name="servers"
+                  render={({ field }) => (
+                    <FormItem>
+                      <FormLabel>
+                        <span className="text-red-500">*</span>Servers
+                      </FormLabel>
+                      <FormControl>
+                        <Input
+                          placeholder="Server IP addresses, separated by commas"
+                          {...field}
+                        />
+                      </FormControl>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+              </div>
+              <DialogFooter>
+                <Button type="submit">Submit</Button>
+              </DialogFooter>
+            </Form>
+          </form>
+        </DialogContent>
+      </Dialog>
+    </div>
+  );
+}
@@ -0,0 +1,107 @@
+import {
+  Table,
+  TableBody,
+  TableCell,
+  TableHead,
+  TableHeader,
+  TableRow,
+} from '@/components/ui/table';
+import { ScrollArea } from '@radix-ui/react-scroll-area';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { forwardRef, useEffect, useImperativeHandle, useState } from 'react';
+import { toast } from 'sonner';
+import Confirm from './confirm';
+import ServerGroupForm from './server-group-form';
+
+const strategyNames = {
+  url_test: 'Lowest Latency',
+  fallback: 'First Healthy',
+  load_balance: 'Load Balance',
+};
+
+const ServerGroupTable = forwardRef((_props, ref) => {
+  const [groups, setGroups] = useState<ServerGroup[]>([]);
+  const [health, setHealth] = useState<Record<string, ServerHealth>>({});
+  const loadData = async () => {
+    let groups = await invoke<ServerGroup[]>('get_server_groups');
+    setGroups(groups);
+    let health = await invoke<Record<string, ServerHealth>>('get_group_health');
+    setHealth(health);
+  };
+
+  const handleDelete = async (name: string) => {
+    const res = await invoke('set_server_groups', {
+      groups: groups.filter((x) => x.name !== name),
+    });
+    console.log(res);
+    toast('success');
+    loadData();
+  };
+
+  const formatHealth = (host: string) => {
+    const serverHealth = health[host];
+    if (!serverHealth) {
+      return host;
+    }
+    if (serverHealth.healthy === false) {
+      return `${host} (down)`;
+    }
+    if (serverHealth.healthy === null) {
+      return `${host} (unknown)`;
+    }
+    if (serverHealth.latency !== undefined && serverHealth.latency !== null) {
+      return `${host} (${serverHealth.latency} ms)`;
+    }
+    return host;
+  };
+
+  useImperativeHandle(ref, () => ({ loadData }));
+  useEffect(() => {
+    loadData();
+    const unListen = listen<Record<string, ServerHealth>>(
+      'group_health',
+      (event) => {
+        setHealth(event.payload);
+      },
+    );
+    return () => {
+      unListen.then((f) => f());
+    };
+  }, []);
+  return (
+    <ScrollArea className="overflow-auto h-[300px]">
+      <Table>
+        <TableHeader className="sticky top-0 bg-secondary rounded-md">
+          <TableRow>
+            <TableHead className="w-[10px]">No.</TableHead>
+            <TableHead className="w-[15px]">Name</TableHead>
+            <TableHead className="w-[15px]">Strategy</TableHead>
+            <TableHead>Servers</TableHead>
+            <TableHead className="w-[20px]">Operation</TableHead>
+          </TableRow>
+        </TableHeader>
+        <TableBody>
+          {groups?.map((group, index) => (
+            <TableRow key={index}>
+              <TableCell className="font-medium">{index + 1}</TableCell>
+              <TableCell>{group.name}</TableCell>
+              <TableCell>{strategyNames[group.strategy]}</TableCell>
+              <TableCell>
+                {group.servers.map(formatHealth).join(', ')}
+              </TableCell>
+              <TableCell className="flex items-center space-x-2">
+                <ServerGroupForm name={group.name} callback={loadData} />
+                <Confirm callback={() => handleDelete(group.name)}>
+                  Delete
+                </Confirm>
+              </TableCell>
+            </TableRow>
+          ))}
+        </TableBody>
+      </Table>
+    </ScrollArea>
+  );
+});
+
+export default ServerGroupTable;
@@ -21,8 +21,12 @@ const ServerTable = forwardRef((_props, ref) => {
   };
 
   const handleDelete = async (host: string) => {
-    const res = await invoke('delete_server', { host });
-    console.log(res);
+    try {
+      await invoke('delete_server', { host });
+    } catch (e) {
+      toast.error(e as string);
+      return;
+    }
     toast('success');
     loadData();
   };
@@ -4,3 +4,10 @@ import { twMerge } from "tailwind-merge"
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+export function formatLintErrors(report: LintReport) {
+  return report.issues
+    .filter((issue) => issue.severity === "error")
+    .map((issue) => `line ${issue.line}: ${issue.message}`)
+    .join("\n")
+}
@@ -0,0 +1,126 @@
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { Ban } from 'lucide-react';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+
+import { Button } from '@/components/ui/button';
+import {
+  Form,
+  FormControl,
+  FormField,
+  FormItem,
+  FormMessage,
+} from '@/components/ui/form';
+import { Textarea } from '@/components/ui/textarea';
+import { listen } from '@tauri-apps/api/event';
+import { useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import LintIssues, { toastSaved, useLint } from '@/components/lint-issues';
+import HistoryDialog from '@/components/history-dialog';
+import RuleImport from '@/components/rule-import';
+
+const FormSchema = z.object({
+  blockRules: z.string(),
+});
+
+function BlockRules() {
+  const form = useForm<z.infer<typeof FormSchema>>({
+    resolver: zodResolver(FormSchema),
+  });
+  const [report, setReport] = useLint(
+    'lint_block_rules',
+    'blockRules',
+    form.watch('blockRules'),
+  );
+
+  async function onSubmit(data: z.infer<typeof FormSchema>) {
+    try {
+      const report = await saveBlockRules(data);
+      setReport(report);
+      toastSaved(report);
+    } catch (report) {
+      setReport(report as LintReport);
+      form.setError('blockRules', {
+        message: 'Fix the errors below before saving',
+      });
+    }
+  }
+
+  const loadBlockRules = async () => {
+    let blockRules = await invoke<string>('get_block_rules');
+    if (blockRules) {
+      form.setValue('blockRules', blockRules);
+    }
+  };
+
+  const saveBlockRules = async (data: z.infer<typeof FormSchema>) => {
+    return await invoke<LintReport>('set_block_rules', {
+      blockRules: data.blockRules,
+    });
+  };
+
+  useEffect(() => {
+    loadBlockRules();
+    const unRefreshListen = listen<string>('refresh_rules', (event) => {
+      if (event.payload === 'block_list.txt') {
+        loadBlockRules();
+      }
+    });
+    return () => {
+      unRefreshListen.then((f) => f());
+    };
+  }, []);
+
+  return (
+    <div>
+      <Card>
+        <CardHeader>
+          <CardTitle className="flex items-center space-x-1">
+            <Ban /> <span>Block Rules</span>
+          </CardTitle>
+          <CardDescription className="flex items-center justify-between">
+            <div>Connections matching these rules are refused</div>
+            <div className="flex items-center space-x-2">
+              <RuleImport target="block" />
+              <HistoryDialog subject="block_list.txt" />
+            </div>
+          </CardDescription>
+        </CardHeader>
+        <CardContent>
+          <Form {...form}>
+            <form onSubmit={form.handleSubmit(onSubmit)} className="space-y-4">
+              <FormField
+                control={form.control}
+                name="blockRules"
+                render={({ field }) => (
+                  <FormItem>
+                    <FormControl>
+                      <Textarea
+                        placeholder="Input your blocked domain list with new line"
+                        className="resize-none w-full"
+                        {...field}
+                        rows={24}
+                      />
+                    </FormControl>
+                    <FormMessage />
+                  </FormItem>
+                )}
+              />
+              <LintIssues report={report} />
+              <Button type="submit">Submit</Button>
+            </form>
+          </Form>
+        </CardContent>
+      </Card>
+    </div>
+  );
+}
+
+export default BlockRules;
@@ -19,9 +19,13 @@ import {
   FormMessage,
 } from '@/components/ui/form';
 import { Textarea } from '@/components/ui/textarea';
-import { toast } from 'sonner';
+import { listen } from '@tauri-apps/api/event';
 import { useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import LintIssues, { toastSaved, useLint } from '@/components/lint-issues';
+import HistoryDialog from '@/components/history-dialog';
+import RuleImport from '@/components/rule-import';
+import RuleExport from '@/components/rule-export';
 
 const FormSchema = z.object({
   directRules: z.string(),
@@ -31,10 +35,23 @@ function DirectRules() {
   const form = useForm<z.infer<typeof FormSchema>>({
     resolver: zodResolver(FormSchema),
   });
+  const [report, setReport] = useLint(
+    'lint_direct_rules',
+    'directRules',
+    form.watch('directRules'),
+  );
 
   async function onSubmit(data: z.infer<typeof FormSchema>) {
-    await saveDirectRules(data);
-    toast('success');
+    try {
+      const report = await saveDirectRules(data);
+      setReport(report);
+      toastSaved(report);
+    } catch (report) {
+      setReport(report as LintReport);
+      form.setError('directRules', {
+        message: 'Fix the errors below before saving',
+      });
+    }
   }
 
   const loadDirectRules = async () => {
@@ -45,13 +62,21 @@ function DirectRules() {
   };
 
   const saveDirectRules = async (data: z.infer<typeof FormSchema>) => {
-    await invoke('set_direct_rules', {
+    return await invoke<LintReport>('set_direct_rules', {
       directRules: data.directRules,
     });
   };
 
   useEffect(() => {
     loadDirectRules();
+    const unRefreshListen = listen<string>('refresh_rules', (event) => {
+      if (event.payload === 'direct_list.txt') {
+        loadDirectRules();
+      }
+    });
+    return () => {
+      unRefreshListen.then((f) => f());
+    };
   }, []);
 
   return (
@@ -61,7 +86,14 @@ function DirectRules() {
           <CardTitle className="flex items-center space-x-1">
             <Zap /> <span>Direct Rules</span>
           </CardTitle>
-          <CardDescription>Rules for directly connection</CardDescription>
+          <CardDescription className="flex items-center justify-between">
+            <div>Rules for directly connection</div>
+            <div className="flex items-center space-x-2">
+              <RuleImport target="direct" />
+              <RuleExport />
+              <HistoryDialog subject="direct_list.txt" />
+            </div>
+          </CardDescription>
         </CardHeader>
         <CardContent>
           <Form {...form}>
@@ -83,6 +115,7 @@ function DirectRules() {
                   </FormItem>
                 )}
               />
+              <LintIssues report={report} />
               <Button type="submit">Submit</Button>
             </form>
           </Form>
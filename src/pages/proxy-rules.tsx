@@ -22,9 +22,17 @@ import { Textarea } from '@/components/ui/textarea';
 import { invoke } from '@tauri-apps/api/core';
 import { useEffect } from 'react';
 import { toast } from 'sonner';
+import { listen } from '@tauri-apps/api/event';
+import LintIssues, { toastSaved, useLint } from '@/components/lint-issues';
 import { Copy, Route } from 'lucide-react';
 import { Input } from '@/components/ui/input';
 import CombinedProxyRule from '@/components/combind-proxy-rule';
+import HistoryDialog from '@/components/history-dialog';
+import RuleImport from '@/components/rule-import';
+import RuleExport from '@/components/rule-export';
+import RuleSources from '@/components/rule-sources';
+import Policies from '@/components/policies';
+import RouteLookup from '@/components/route-lookup';
 import {
   Tooltip,
   TooltipContent,
@@ -48,10 +56,23 @@ function ProxyRules() {
       proxyRules: '',
     },
   });
+  const [report, setReport] = useLint(
+    'lint_custom_proxy_rules',
+    'proxyRules',
+    form.watch('proxyRules'),
+  );
 
   async function onSubmit(data: z.infer<typeof FormSchema>) {
-    await saveProxyRules(data);
-    toast('success');
+    try {
+      const report = await saveProxyRules(data);
+      setReport(report);
+      toastSaved(report);
+    } catch (report) {
+      setReport(report as LintReport);
+      form.setError('proxyRules', {
+        message: 'Fix the errors below before saving',
+      });
+    }
   }
 
   const loadProxyRules = async () => {
@@ -62,7 +83,7 @@ function ProxyRules() {
   };
 
   const saveProxyRules = async (data: z.infer<typeof FormSchema>) => {
-    await invoke('set_custom_proxy_rules', {
+    return await invoke<LintReport>('set_custom_proxy_rules', {
       proxyRules: data.proxyRules,
       url: data.communityRulesUrl,
     });
@@ -77,9 +98,17 @@ function ProxyRules() {
 
   useEffect(() => {
     loadProxyRules();
+    const unRefreshListen = listen<string>('refresh_rules', (event) => {
+      if (event.payload === 'custom_proxy_list.txt') {
+        loadProxyRules();
+      }
+    });
+    return () => {
+      unRefreshListen.then((f) => f());
+    };
   }, []);
   return (
-    <div>
+    <div className="space-y-8">
       <Card>
         <CardHeader>
           <CardTitle className="flex items-center space-x-1">
@@ -88,7 +117,12 @@ function ProxyRules() {
           </CardTitle>
           <CardDescription className="flex items-center justify-between">
             <div>Custom Proxy Rules</div>
-            <CombinedProxyRule />
+            <div className="flex items-center space-x-2">
+              <RuleImport target="proxy" />
+              <RuleExport />
+              <HistoryDialog subject="custom_proxy_list.txt" />
+              <CombinedProxyRule />
+            </div>
           </CardDescription>
         </CardHeader>
         <CardContent>
@@ -148,12 +182,16 @@ function ProxyRules() {
                     </FormItem>
                   )}
                 />
+                <LintIssues report={report} />
                 <Button type="submit">Submit</Button>
               </form>
             </Form>
           </div>
         </CardContent>
       </Card>
+      <RuleSources />
+      <Policies />
+      <RouteLookup />
     </div>
   );
 }
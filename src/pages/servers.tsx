@@ -8,16 +8,24 @@ import {
 
 import ServerForm from '@/components/server-form';
 import ServerTable from '@/components/server-table';
+import ServerGroupForm from '@/components/server-group-form';
+import ServerGroupTable from '@/components/server-group-table';
+import HistoryDialog from '@/components/history-dialog';
 import { useRef } from 'react';
-import { Server } from 'lucide-react';
+import { Group, Server } from 'lucide-react';
 
 export default function Servers() {
   const serverTableRef = useRef<{ loadData: () => void } | null>(null);
+  const serverGroupTableRef = useRef<{ loadData: () => void } | null>(null);
 
   const handleCreateSucceed = () => {
     serverTableRef.current?.loadData();
   };
 
+  const handleGroupCreateSucceed = () => {
+    serverGroupTableRef.current?.loadData();
+  };
+
   return (
     <div className="space-y-8">
       <Card>
@@ -25,8 +33,9 @@ export default function Servers() {
           <CardTitle className="flex items-center space-x-2">
             <Server /> <div>Server Configuration</div>
           </CardTitle>
-          <CardDescription>
-            Set proxy server address information
+          <CardDescription className="flex items-center justify-between">
+            <div>Set proxy server address information</div>
+            <HistoryDialog subject="servers" callback={handleCreateSucceed} />
           </CardDescription>
         </CardHeader>
         <CardContent className="space-y-2 h-[350px] flex flex-col">
@@ -36,6 +45,22 @@ export default function Servers() {
           <ServerForm callback={handleCreateSucceed} />
         </CardContent>
       </Card>
+      <Card>
+        <CardHeader>
+          <CardTitle className="flex items-center space-x-2">
+            <Group /> <div>Server Groups</div>
+          </CardTitle>
+          <CardDescription>
+            Pick a server from a group by latency, order or load balancing
+          </CardDescription>
+        </CardHeader>
+        <CardContent className="space-y-2 h-[350px] flex flex-col">
+          <div className="flex-1">
+            <ServerGroupTable ref={serverGroupTableRef} />
+          </div>
+          <ServerGroupForm callback={handleGroupCreateSucceed} />
+        </CardContent>
+      </Card>
       <Card>
         <CardHeader>
           <CardTitle>Server Installation</CardTitle>
@@ -23,6 +23,10 @@ import { toast } from 'sonner';
 import { useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { Settings } from 'lucide-react';
+import About from '@/components/about';
+import ConfigBundle from '@/components/config-bundle';
+import Profiles from '@/components/profiles';
+import NetworkRules from '@/components/network-rules';
 
 const FormSchema = z.object({
   socksIp: z.string().min(2, {
@@ -89,7 +93,7 @@ function Setting() {
   }, []);
 
   return (
-    <div>
+    <div className="space-y-8">
       <Card>
         <CardHeader>
           <CardTitle className="flex items-center space-x-2">
@@ -162,6 +166,10 @@ function Setting() {
           </div>
         </CardContent>
       </Card>
+      <Profiles />
+      <NetworkRules />
+      <ConfigBundle />
+      <About />
     </div>
   );
 }
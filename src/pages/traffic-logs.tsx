@@ -41,6 +41,10 @@ export default function TrafficLogs() {
         'DIRECT',
         '<span class="text-blue-500">DIRECT</span>',
       );
+      newLog = newLog.replace(
+        'BLOCK',
+        '<span class="text-gray-500">BLOCK</span>',
+      );
       // Update cache ref
       logsCache.current += `<div class="text-sm">${newLog}</div>`;
 
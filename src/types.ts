@@ -5,6 +5,16 @@ interface ServerConfig {
   cert?: string;
   cert_key?: string;
   alias?: string;
+  transport?: TransportOptions;
+}
+
+interface TransportOptions {
+  congestion_control: 'bbr' | 'cubic';
+  idle_timeout: number;
+  keep_alive_interval: number;
+  max_concurrent_streams: number;
+  mtu_discovery: boolean;
+  tcp_pool_size: number;
 }
 
 interface ListenConfig {
@@ -23,3 +33,199 @@ interface ListenConfigOption {
   socks_config?: Address;
   http_config?: Address;
 }
+
+interface LintIssue {
+  line: number;
+  entry: string;
+  kind: string;
+  severity: 'error' | 'warning';
+  message: string;
+}
+
+interface LintReport {
+  issues: LintIssue[];
+}
+
+type RuleTarget = 'proxy' | 'direct' | 'block';
+
+type RuleFormat = 'plain' | 'gfwlist' | 'clash' | 'switchy_omega';
+
+interface RuleSource {
+  id: string;
+  url: string;
+  target: RuleTarget;
+  enabled: boolean;
+  // seconds, 0 only refreshes on demand
+  refresh_interval: number;
+  format: RuleFormat;
+  min_entries: number;
+  checksum_url?: string;
+  fetch_via: 'direct' | 'tunnel' | 'fallback';
+}
+
+interface DroppedEntry {
+  line: number;
+  entry: string;
+  reason: string;
+}
+
+interface RuleImportReport {
+  proxy: string[];
+  direct: string[];
+  block: string[];
+  translated: number;
+  dropped: DroppedEntry[];
+}
+
+type ExportFormat = 'pac' | 'clash' | 'surge';
+
+interface ExportFile {
+  name: string;
+  content: string;
+  skipped: string[];
+}
+
+interface SourceStatus {
+  state: 'pending' | 'updated' | 'not_modified' | 'failed';
+  message?: string;
+  last_attempt?: number;
+  last_update?: number;
+  failures: number;
+  entries: number;
+  through_tunnel: boolean;
+}
+
+interface ServerGroup {
+  name: string;
+  strategy: 'url_test' | 'fallback' | 'load_balance';
+  balance: 'round_robin' | 'consistent_hash';
+  servers: string[];
+  interval: number;
+}
+
+interface Policy {
+  name: string;
+  // host of a server or name of a server group
+  server: string;
+  enabled: boolean;
+}
+
+interface RouteDecision {
+  host: string;
+  action: 'proxy' | 'direct' | 'block';
+  server?: string;
+  group?: string;
+  policy?: string;
+  matched_rule?: string;
+}
+
+interface ServerHealth {
+  // null when the server can't be probed
+  healthy: boolean | null;
+  latency?: number;
+  error?: string;
+  checked_at: number;
+}
+
+interface AgentInfo {
+  version: string;
+  capabilities: string[];
+  compatible: boolean;
+  message?: string;
+}
+
+interface Diagnostics {
+  app_version: string;
+  os: string;
+  arch: string;
+  agent: AgentInfo;
+  agent_running: boolean;
+  config_path?: string;
+}
+
+type HistorySource =
+  | 'initial'
+  | 'ui'
+  | 'import'
+  | 'community_refresh'
+  | 'external'
+  | 'restore';
+
+interface HistoryVersion {
+  id: number;
+  timestamp: number;
+  source: HistorySource;
+  size: number;
+}
+
+type ImportMode = 'merge' | 'replace';
+
+interface ImportConflict {
+  kind:
+    | 'server'
+    | 'group'
+    | 'policy'
+    | 'profile'
+    | 'rule_source'
+    | 'setting';
+  name: string;
+}
+
+interface ConfigImportReport {
+  conflicts: ImportConflict[];
+  has_keys: boolean;
+  keys_imported: boolean;
+  applied: boolean;
+}
+
+interface Profile {
+  name: string;
+  enabled: boolean;
+  active_server?: string;
+  access_mode: 'auto' | 'proxy';
+  bind_mode: 'socks' | 'http';
+  protocol_mode: 'auto' | 'quic' | 'tcp';
+  socks: Address;
+  http: Address;
+  rule_sources: string[];
+  policies: string[];
+}
+
+interface NetworkInfo {
+  ssid?: string;
+  interface?: string;
+  gateway?: string;
+  gateway_mac?: string;
+  dns_suffixes: string[];
+}
+
+interface NetworkRule {
+  name: string;
+  ssids: string[];
+  interfaces: string[];
+  gateways: string[];
+  gateway_macs: string[];
+  dns_suffixes: string[];
+  profile: string;
+}
+
+interface NetworkSettings {
+  enabled: boolean;
+  rules: NetworkRule[];
+  fallback_profile?: string;
+}
+
+interface NetworkDecision {
+  timestamp: number;
+  network: NetworkInfo;
+  rule?: string;
+  profile?: string;
+  outcome: 'switched' | 'already_active' | 'no_match' | 'failed';
+  error?: string;
+}
+
+interface Reconnected {
+  reason: 'network_changed' | 'resumed';
+  proxy_reapplied: boolean;
+  agent_restarted: boolean;
+}